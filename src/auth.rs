@@ -1,35 +1,229 @@
+use argon2::PasswordHash;
 use axum::{
-    extract::{Request, State},
-    http::{HeaderMap, HeaderValue},
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
     middleware::Next,
-    response::{Html, IntoResponse, Redirect, Response},
+    response::{IntoResponse, Redirect, Response},
     Form,
 };
-use serde::Deserialize;
+use blake2::{Blake2s256, Digest};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    sync::Arc,
+    fmt, fs, io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, SystemTime},
 };
-use tokio::sync::RwLock;
+use tokio::{sync::RwLock, task::JoinHandle};
 use uuid::Uuid;
 
 use crate::config::Config;
+use crate::login_throttle;
+use crate::pages::{self, LoginError, LoginPage};
+use crate::tokens::TokenStore;
 
-#[derive(Clone, Debug)]
+/// Header JSON API clients signed in with a session cookie send the CSRF token in
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Form field the CSRF token is submitted in
+pub const CSRF_FIELD: &str = "csrf_token";
+
+/// Largest form body buffered to look for the CSRF token, matching axum's default body limit
+const MAX_FORM_BYTES: usize = 2 * 1024 * 1024;
+
+/// What a signed-in user may do. Each role includes everything the roles before it can do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to dashboards, logs and the JSON APIs
+    Viewer,
+    /// Can also start, stop and restart containers
+    Operator,
+    /// Can also create and remove containers, images, volumes and networks, and open shells
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The highest role any of `groups` is mapped to, or `default` when none of them are
+pub fn role_for_groups(
+    groups: &[&str],
+    admin: &[String],
+    operator: &[String],
+    viewer: &[String],
+    default: Option<Role>,
+) -> Option<Role> {
+    let in_any = |mapped: &[String]| mapped.iter().any(|group| groups.contains(&group.as_str()));
+    if in_any(admin) {
+        Some(Role::Admin)
+    } else if in_any(operator) {
+        Some(Role::Operator)
+    } else if in_any(viewer) {
+        Some(Role::Viewer)
+    } else {
+        default
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!(
+                "unknown role `{}` (expected viewer, operator or admin)",
+                other
+            )),
+        }
+    }
+}
+
+/// An account from the users file
+#[derive(Clone)]
+pub struct UserAccount {
+    pub username: String,
+    pub role: Role,
+    pub password_hash: String, // Argon2 PHC string
+}
+
+/// `username:role`, leaving out the password hash
+impl std::fmt::Debug for UserAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.username, self.role)
+    }
+}
+
+/// Parse a users file: one `username:role:argon2-hash` entry per line. Blank lines and
+/// lines starting with `#` are ignored.
+pub fn parse_users(contents: &str) -> Result<Vec<UserAccount>, String> {
+    let mut users: Vec<UserAccount> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line_number = index + 1;
+        let mut fields = line.splitn(3, ':');
+        let (Some(username), Some(role), Some(password_hash)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(format!(
+                "line {}: expected `username:role:password-hash`",
+                line_number
+            ));
+        };
+
+        let username = username.trim();
+        if username.is_empty() {
+            return Err(format!("line {}: username is empty", line_number));
+        }
+        if users.iter().any(|user| user.username == username) {
+            return Err(format!(
+                "line {}: duplicate user `{}`",
+                line_number, username
+            ));
+        }
+        let role = role
+            .parse::<Role>()
+            .map_err(|e| format!("line {}: {}", line_number, e))?;
+        let password_hash = password_hash.trim();
+        PasswordHash::new(password_hash).map_err(|e| {
+            format!(
+                "line {}: password for `{}` is not an Argon2 hash: {}",
+                line_number, username, e
+            )
+        })?;
+
+        users.push(UserAccount {
+            username: username.to_string(),
+            role,
+            password_hash: password_hash.to_string(),
+        });
+    }
+
+    Ok(users)
+}
+
+/// How often expired sessions are swept and access times are saved
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest user agent kept with a session, so a client cannot bloat the session file
+const MAX_USER_AGENT_LEN: usize = 256;
+
+/// How the user of a session proved who they are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMethod {
+    /// A local account's password, and two-factor code if it has one
+    #[default]
+    Password,
+    /// OpenID Connect single sign-on; the role came from the identity provider
+    Oidc,
+    /// A header set by a trusted authenticating reverse proxy
+    Proxy,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Session {
-    #[allow(dead_code)]
-    pub user_id: String,
+    pub id: String, // Identifies the session when listing and revoking; not the cookie value
     pub username: String,
-    #[allow(dead_code)]
+    pub role: Role,
+    #[serde(skip)]
+    pub token_id: Option<String>, // Set when the request authenticated with an API token
+    pub csrf_token: String, // Must accompany state-changing requests from the browser
     pub created_at: SystemTime,
     pub last_accessed: SystemTime,
+    #[serde(default)]
+    pub address: Option<String>, // Client address at sign-in
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub method: AuthMethod,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSession {
+    key: String, // Hex Blake2s-256 of the session cookie
+    #[serde(flatten)]
+    session: Session,
+}
+
+/// Signed-in browser sessions, keyed by a hash of their cookie. When opened with a file,
+/// sessions are saved whenever one is created or removed, and access times are saved by
+/// the sweeper, so users stay signed in across restarts. The file cannot be used to
+/// hijack a session since it only holds hashes of the cookies.
+#[derive(Debug)]
 pub struct SessionStore {
     sessions: Arc<RwLock<HashMap<String, Session>>>,
     config: Arc<Config>,
+    path: Option<PathBuf>,
+    touched: AtomicBool, // Access times changed since the file was last written
 }
 
 impl SessionStore {
@@ -37,49 +231,126 @@ impl SessionStore {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             config,
+            path: None,
+            touched: AtomicBool::new(false),
         }
     }
 
-    pub async fn create_session(&self, username: &str) -> String {
+    /// Open (or create) the session file. Expired sessions and sessions of users who can
+    /// no longer sign in are dropped, and roles of local users are refreshed from the
+    /// current users; single sign-on sessions keep the role they signed in with, and proxy
+    /// sessions are checked against the proxy's headers on every request.
+    pub fn open(config: Arc<Config>, path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let stored: Vec<StoredSession> = if path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            if contents.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&contents)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+        } else {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            Vec::new()
+        };
+
+        let store = Self {
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            config,
+            path: Some(path),
+            touched: AtomicBool::new(false),
+        };
+        let now = SystemTime::now();
+        let sessions: HashMap<String, Session> = stored
+            .into_iter()
+            .filter(|stored| !store.is_expired(&stored.session, now))
+            .filter_map(|StoredSession { key, mut session }| {
+                match session.method {
+                    AuthMethod::Password => {
+                        session.role = store.config.role_of(&session.username)?
+                    }
+                    AuthMethod::Oidc => {
+                        store.config.oidc_issuer.as_ref()?;
+                    }
+                    AuthMethod::Proxy => {
+                        store.config.proxy_auth_user_header.as_ref()?;
+                    }
+                }
+                Some((key, session))
+            })
+            .collect();
+        store.save(&sessions)?;
+        tracing::info!("Restored {} session(s)", sessions.len());
+
+        Ok(Self {
+            sessions: Arc::new(RwLock::new(sessions)),
+            ..store
+        })
+    }
+
+    pub async fn create_session(&self, username: &str, role: Role) -> String {
+        self.create_session_for(username, role, AuthMethod::Password, None, None)
+            .await
+    }
+
+    /// Create a session, remembering where it was signed in from so users can tell their
+    /// sessions apart
+    pub async fn create_session_for(
+        &self,
+        username: &str,
+        role: Role,
+        method: AuthMethod,
+        address: Option<String>,
+        user_agent: Option<&str>,
+    ) -> String {
         let session_id = Uuid::new_v4().to_string();
         let session = Session {
-            user_id: Uuid::new_v4().to_string(),
+            id: Uuid::new_v4().to_string(),
             username: username.to_string(),
+            role,
+            token_id: None,
+            csrf_token: Uuid::new_v4().simple().to_string(),
             created_at: SystemTime::now(),
             last_accessed: SystemTime::now(),
+            address,
+            user_agent: user_agent.map(|agent| agent.chars().take(MAX_USER_AGENT_LEN).collect()),
+            method,
         };
 
         let mut sessions = self.sessions.write().await;
-        sessions.insert(session_id.clone(), session);
+        sessions.insert(hash_secret(&session_id), session);
+        self.save_or_warn(&sessions);
 
-        tracing::info!("Created session for user: {}", username);
+        tracing::info!("Created session for user: {} ({})", username, role);
         session_id
     }
 
     pub async fn get_session(&self, session_id: &str) -> Option<Session> {
+        let key = hash_secret(session_id);
         let mut sessions = self.sessions.write().await;
 
         // Check if session exists and is not expired
-        let should_remove = if let Some(session) = sessions.get(session_id) {
-            let session_duration = SystemTime::now()
-                .duration_since(session.last_accessed)
-                .unwrap_or(Duration::ZERO);
-
-            session_duration.as_secs() > self.config.session_timeout_seconds
+        let should_remove = if let Some(session) = sessions.get(&key) {
+            self.is_expired(session, SystemTime::now())
         } else {
             return None;
         };
 
         if should_remove {
-            if let Some(session) = sessions.remove(session_id) {
+            if let Some(session) = sessions.remove(&key) {
                 tracing::info!("Removed expired session for user: {}", session.username);
+                self.save_or_warn(&sessions);
             }
             return None;
         }
 
         // Update last accessed time and return session
-        if let Some(session) = sessions.get_mut(session_id) {
+        if let Some(session) = sessions.get_mut(&key) {
             session.last_accessed = SystemTime::now();
+            self.touched.store(true, Ordering::Relaxed);
             Some(session.clone())
         } else {
             None
@@ -88,27 +359,105 @@ impl SessionStore {
 
     pub async fn remove_session(&self, session_id: &str) -> bool {
         let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.remove(session_id) {
+        if let Some(session) = sessions.remove(&hash_secret(session_id)) {
             tracing::info!("Removed session for user: {}", session.username);
+            self.save_or_warn(&sessions);
             true
         } else {
             false
         }
     }
 
-    #[allow(dead_code)]
+    /// Active sessions of `username`, or of every user when `None`, most recently used first
+    pub async fn list(&self, username: Option<&str>) -> Vec<Session> {
+        let now = SystemTime::now();
+        let sessions = self.sessions.read().await;
+        let mut list: Vec<Session> = sessions
+            .values()
+            .filter(|session| username.is_none_or(|username| session.username == username))
+            .filter(|session| !self.is_expired(session, now))
+            .cloned()
+            .collect();
+        list.sort_by_key(|session| std::cmp::Reverse(session.last_accessed));
+        list
+    }
+
+    /// Sign a session out by its public ID, returning it if it existed
+    pub async fn revoke(&self, id: &str) -> Option<Session> {
+        let mut sessions = self.sessions.write().await;
+        let key = sessions
+            .iter()
+            .find(|(_, session)| session.id == id)
+            .map(|(key, _)| key.clone())?;
+        let session = sessions.remove(&key)?;
+        self.save_or_warn(&sessions);
+
+        tracing::info!("Revoked session {} of user {}", id, session.username);
+        Some(session)
+    }
+
+    /// Drop expired sessions and save access times recorded since the last sweep
     pub async fn cleanup_expired_sessions(&self) {
         let mut sessions = self.sessions.write().await;
         let now = SystemTime::now();
-        let timeout = Duration::from_secs(self.config.session_timeout_seconds);
+        let before = sessions.len();
 
-        sessions.retain(|_, session| {
-            let age = now
-                .duration_since(session.last_accessed)
-                .unwrap_or(Duration::ZERO);
-            age < timeout
-        });
+        sessions.retain(|_, session| !self.is_expired(session, now));
+
+        let removed = before - sessions.len();
+        if removed > 0 {
+            tracing::info!("Removed {} expired session(s)", removed);
+        }
+        if removed > 0 || self.touched.swap(false, Ordering::Relaxed) {
+            self.save_or_warn(&sessions);
+        }
     }
+
+    /// A session expires after `session_timeout_seconds` without use, and in any case
+    /// `session_max_lifetime_seconds` after sign-in
+    fn is_expired(&self, session: &Session, now: SystemTime) -> bool {
+        let idle = now
+            .duration_since(session.last_accessed)
+            .unwrap_or(Duration::ZERO);
+        let age = now
+            .duration_since(session.created_at)
+            .unwrap_or(Duration::ZERO);
+        idle.as_secs() > self.config.session_timeout_seconds
+            || age.as_secs() > self.config.session_max_lifetime_seconds
+    }
+
+    fn save(&self, sessions: &HashMap<String, Session>) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let stored: Vec<StoredSession> = sessions
+            .iter()
+            .map(|(key, session)| StoredSession {
+                key: key.clone(),
+                session: session.clone(),
+            })
+            .collect();
+        write_private_json(path, &stored)
+    }
+
+    fn save_or_warn(&self, sessions: &HashMap<String, Session>) {
+        if let Err(e) = self.save(sessions) {
+            if let Some(path) = &self.path {
+                tracing::warn!("Failed to write session file {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Sweep expired sessions for the lifetime of the server
+pub fn spawn_sweeper(store: Arc<SessionStore>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SESSION_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            store.cleanup_expired_sessions().await;
+        }
+    })
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -117,18 +466,30 @@ pub struct LoginForm {
     pub password: String,
 }
 
+/// What `auth_middleware` needs to recognise browser sessions and API clients
+#[derive(Clone)]
+pub struct AuthState {
+    pub sessions: Arc<SessionStore>,
+    pub tokens: Arc<TokenStore>,
+}
+
 pub async fn auth_middleware(
-    State(session_store): State<Arc<SessionStore>>,
+    State(auth): State<AuthState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     mut request: Request,
     next: Next,
 ) -> Response {
-    // Skip auth for health endpoints, static assets, and login/logout
+    let session_store = &auth.sessions;
+    // Skip auth for health endpoints, static assets, and login/logout (including two-factor
+    // codes and single sign-on)
     let path = request.uri().path();
     if path.starts_with("/health")
         || path.starts_with("/ready")
         || path.starts_with("/static/")
         || path == "/login"
+        || path == "/login/two-factor"
         || path == "/logout"
+        || path.starts_with("/auth/oidc/")
     {
         return next.run(request).await;
     }
@@ -138,6 +499,34 @@ pub async fn auth_middleware(
         return next.run(request).await;
     }
 
+    // Let Prometheus scrape with its bearer token instead of a session
+    if path == "/api/prometheus" && has_scrape_token(request.headers(), &session_store.config) {
+        return next.run(request).await;
+    }
+
+    // Automation clients send an API token instead of a session cookie
+    if let Some(secret) = bearer_token(request.headers()).map(str::to_string) {
+        return match token_session(&auth, &secret).await {
+            Some(session) => {
+                request.extensions_mut().insert(session);
+                next.run(request).await
+            }
+            None => (StatusCode::UNAUTHORIZED, "Invalid or expired API token").into_response(),
+        };
+    }
+
+    // Behind an authenticating proxy, the proxy's header says who is signed in
+    let peer = connect_info.map(|ConnectInfo(address)| address.ip());
+    if let Some(user) = proxy_user(&session_store.config, peer, request.headers()) {
+        return match user {
+            Ok((username, role)) => proxy_session(&auth, username, role, peer, request, next).await,
+            Err(message) => {
+                tracing::warn!("Refused proxy-authenticated request: {}", message);
+                (StatusCode::FORBIDDEN, message).into_response()
+            }
+        };
+    }
+
     // Check for session cookie
     if let Some(cookie_header) = request.headers().get("cookie") {
         if let Ok(cookie_str) = cookie_header.to_str() {
@@ -154,7 +543,6 @@ pub async fn auth_middleware(
     // No valid session - handle differently for API vs web requests
     if path.starts_with("/api/") {
         // For API endpoints, return 401 Unauthorized instead of redirecting
-        use axum::http::StatusCode;
         (StatusCode::UNAUTHORIZED, "Unauthorized").into_response()
     } else {
         // For web pages, redirect to login
@@ -162,6 +550,368 @@ pub async fn auth_middleware(
     }
 }
 
+/// The user an authenticating reverse proxy vouches for, with their role. `None` when proxy
+/// authentication is off, the header is missing, or the connection does not come from a
+/// trusted proxy (anyone else could set the header). The role is the local account's if the
+/// user has one, else the first mapped group in the groups header, else the default role.
+fn proxy_user(
+    config: &Config,
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+) -> Option<Result<(String, Role), String>> {
+    let user_header = config
+        .proxy_auth_user_header
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())?;
+    let peer = peer?;
+    if !config
+        .trusted_proxies
+        .iter()
+        .any(|network| network.contains(peer))
+    {
+        return None;
+    }
+    let username = headers
+        .get(user_header)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|username| !username.is_empty())?;
+
+    let groups = config
+        .proxy_auth_groups_header
+        .as_deref()
+        .map(|name| {
+            headers
+                .get_all(name.trim())
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|group| !group.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let role = config.role_of(username).or_else(|| {
+        role_for_groups(
+            &groups,
+            &config.proxy_auth_admin_groups,
+            &config.proxy_auth_operator_groups,
+            &config.proxy_auth_viewer_groups,
+            config.proxy_auth_default_role,
+        )
+    });
+
+    Some(match role {
+        Some(role) => Ok((username.to_string(), role)),
+        None => Err(format!("{} is not in a group with access", username)),
+    })
+}
+
+/// Continue a request as a user signed in by the proxy. The browser keeps one session while
+/// the proxy vouches for the same user and role, so its CSRF token stays valid; otherwise a
+/// new session is started and its cookie set on the response.
+async fn proxy_session(
+    auth: &AuthState,
+    username: String,
+    role: Role,
+    peer: Option<IpAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let session_store = &auth.sessions;
+    let existing = match request_session_id(request.headers()) {
+        Some(session_id) => session_store.get_session(&session_id).await,
+        None => None,
+    }
+    .filter(|session| {
+        session.method == AuthMethod::Proxy && session.username == username && session.role == role
+    });
+    if let Some(session) = existing {
+        request.extensions_mut().insert(session);
+        return next.run(request).await;
+    }
+
+    let headers = request.headers();
+    let client = login_throttle::client_ip(peer, headers, &session_store.config.trusted_proxies);
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    let session_id = session_store
+        .create_session_for(
+            &username,
+            role,
+            AuthMethod::Proxy,
+            client.map(|ip| ip.to_string()),
+            user_agent,
+        )
+        .await;
+    let Some(session) = session_store.get_session(&session_id).await else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not start a session",
+        )
+            .into_response();
+    };
+    tracing::info!("User {} signed in by the authenticating proxy", username);
+
+    request.extensions_mut().insert(session);
+    let mut response = next.run(request).await;
+    response.headers_mut().append(
+        header::SET_COOKIE,
+        session_cookie(&session_id, &session_store.config),
+    );
+    response
+}
+
+/// The `Set-Cookie` value for a new session. The browser may keep it for the session's
+/// whole lifetime; the idle timeout is enforced by the session store. Over HTTPS the
+/// cookie is marked `Secure` so it is never sent in cleartext.
+pub fn session_cookie(session_id: &str, config: &Config) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "session_id={}; HttpOnly; SameSite=Strict; Path=/; Max-Age={}{}",
+        session_id,
+        config.session_max_lifetime_seconds,
+        secure_attribute(config)
+    ))
+    .unwrap()
+}
+
+/// The `Set-Cookie` value that removes the session cookie
+pub fn clear_session_cookie(config: &Config) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "session_id=; HttpOnly; SameSite=Strict; Path=/; Max-Age=0{}",
+        secure_attribute(config)
+    ))
+    .unwrap()
+}
+
+fn secure_attribute(config: &Config) -> &'static str {
+    if config.tls_enabled() {
+        "; Secure"
+    } else {
+        ""
+    }
+}
+
+/// The session ID from the request's cookie, if it has one
+fn request_session_id(headers: &HeaderMap) -> Option<String> {
+    let cookie_str = headers.get("cookie")?.to_str().ok()?;
+    extract_session_id(cookie_str)
+}
+
+/// Reject requests from signed-in users whose role is below `required`. Applied per route
+/// group in `app_router`, after `auth_middleware` has attached the session; when
+/// authentication is disabled there is no session and every request is allowed.
+pub async fn require_role(State(required): State<Role>, request: Request, next: Next) -> Response {
+    let Some(session) = request.extensions().get::<Session>() else {
+        return next.run(request).await;
+    };
+    if session.role >= required {
+        return next.run(request).await;
+    }
+
+    tracing::warn!(
+        "Denied {} {} to {} ({} role required, has {})",
+        request.method(),
+        request.uri().path(),
+        session.username,
+        required,
+        session.role
+    );
+    if request.uri().path().starts_with("/api/") {
+        (StatusCode::FORBIDDEN, "Forbidden").into_response()
+    } else {
+        (
+            StatusCode::FORBIDDEN,
+            pages::error_page(
+                format!(
+                    "This action requires the {} role; you are signed in as {} ({})",
+                    required, session.username, session.role
+                ),
+                "/",
+            ),
+        )
+            .into_response()
+    }
+}
+
+/// Protect browser sessions against cross-site request forgery. State-changing requests
+/// must carry the session's CSRF token, either in the `csrf_token` form field or in the
+/// `X-CSRF-Token` header; pages render it into their POST forms and a `csrf-token` meta
+/// tag. Runs inside `auth_middleware`, so requests without a cookie session (API tokens,
+/// authentication disabled, the login form) pass through unchanged. `/logout` skips the
+/// middleware and checks the token itself.
+pub async fn csrf_middleware(request: Request, next: Next) -> Response {
+    let Some(session) = request
+        .extensions()
+        .get::<Session>()
+        .filter(|session| session.token_id.is_none())
+        .cloned()
+    else {
+        return next.run(request).await;
+    };
+
+    let request = if is_safe_method(request.method()) {
+        request
+    } else {
+        match verify_csrf(request, &session).await {
+            Ok(request) => request,
+            Err(response) => return response,
+        }
+    };
+    next.run(request).await
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Check the header, or else the form body, for the session's token. The body is buffered
+/// and handed back so the handler can still read it.
+pub async fn verify_csrf(request: Request, session: &Session) -> Result<Request, Response> {
+    let expected = session.csrf_token.as_bytes();
+    let matches = |token: &str| constant_time_eq(token.as_bytes(), expected);
+
+    if let Some(token) = request
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return if matches(token) {
+            Ok(request)
+        } else {
+            Err(csrf_rejection(&request, session))
+        };
+    }
+
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return Err(csrf_rejection(&request, session));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_FORM_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Err((StatusCode::PAYLOAD_TOO_LARGE, "Form too large").into_response()),
+    };
+    let submitted = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|(name, _)| name == CSRF_FIELD)
+        .map(|(_, value)| value);
+
+    let request = Request::from_parts(parts, Body::from(bytes));
+    match submitted {
+        Some(token) if matches(&token) => Ok(request),
+        _ => Err(csrf_rejection(&request, session)),
+    }
+}
+
+fn csrf_rejection(request: &Request, session: &Session) -> Response {
+    tracing::warn!(
+        "Rejected {} {} from {}: missing or invalid CSRF token",
+        request.method(),
+        request.uri().path(),
+        session.username
+    );
+    if request.uri().path().starts_with("/api/") {
+        (StatusCode::FORBIDDEN, "Missing or invalid CSRF token").into_response()
+    } else {
+        (
+            StatusCode::FORBIDDEN,
+            pages::error_page(
+                "This form has expired or did not come from this site. Reload the page and try again.",
+                "/",
+            ),
+        )
+            .into_response()
+    }
+}
+
+/// Build the session for a request carrying an API token. A token never grants more than
+/// its owner's current role, and stops working once the owner is removed from the users file.
+async fn token_session(auth: &AuthState, secret: &str) -> Option<Session> {
+    let token = auth.tokens.authenticate(secret).await?;
+    let Some(owner_role) = auth.sessions.config.role_of(&token.owner) else {
+        tracing::warn!(
+            "Rejected API token {} of unknown user {}",
+            token.id,
+            token.owner
+        );
+        return None;
+    };
+
+    let now = SystemTime::now();
+    Some(Session {
+        id: token.id.clone(),
+        username: token.owner,
+        role: token.role.min(owner_role),
+        token_id: Some(token.id),
+        csrf_token: String::new(), // Bearer tokens are never sent automatically by a browser
+        created_at: now,
+        last_accessed: now,
+        address: None,
+        user_agent: None,
+        method: AuthMethod::Password,
+    })
+}
+
+/// The token from an `Authorization: Bearer` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Check the `Authorization: Bearer` header against the configured Prometheus scrape token
+fn has_scrape_token(headers: &HeaderMap, config: &Config) -> bool {
+    let Some(expected) = config.prometheus_token.as_deref().filter(|t| !t.is_empty()) else {
+        return false;
+    };
+
+    bearer_token(headers)
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes()))
+}
+
+/// Hex Blake2s-256 of a secret, for storing secrets that only ever need to be matched
+pub fn hash_secret(secret: &str) -> String {
+    Blake2s256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Replace a JSON file atomically, readable by the owner only
+pub fn write_private_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> io::Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(value)?)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o600))?;
+    }
+
+    fs::rename(&temp_path, path)
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
 #[allow(dead_code)]
 pub async fn login_handler(State(session_store): State<Arc<SessionStore>>) -> impl IntoResponse {
     // If auth is disabled, redirect to main page
@@ -169,9 +919,10 @@ pub async fn login_handler(State(session_store): State<Arc<SessionStore>>) -> im
         return Redirect::to("/").into_response();
     }
 
-    let template = include_str!("../templates/login.html");
-    let html = template.replace("{{ERROR_MESSAGE}}", "");
-    Html(html).into_response()
+    pages::render(&LoginPage {
+        sso_provider: None,
+        error: None,
+    })
 }
 
 #[allow(dead_code)]
@@ -185,38 +936,31 @@ pub async fn login_post_handler(
     }
 
     // Verify credentials
-    if form.username == session_store.config.auth_username {
-        match session_store.config.verify_password(&form.password) {
-            Ok(true) => {
-                // Create session
-                let session_id = session_store.create_session(&form.username).await;
-
-                // Set session cookie and redirect
-                let cookie = format!(
-                    "session_id={}; HttpOnly; SameSite=Strict; Path=/; Max-Age={}",
-                    session_id, session_store.config.session_timeout_seconds
-                );
-
-                let mut response = Redirect::to("/").into_response();
-                response
-                    .headers_mut()
-                    .insert("Set-Cookie", HeaderValue::from_str(&cookie).unwrap());
-                response
-            }
-            _ => {
-                tracing::warn!("Failed login attempt for user: {}", form.username);
-                let template = include_str!("../templates/login.html");
-                let error_html = r#"<div class="error-message">Invalid username or password</div>"#;
-                let html = template.replace("{{ERROR_MESSAGE}}", error_html);
-                Html(html).into_response()
-            }
+    match session_store
+        .config
+        .authenticate(&form.username, &form.password)
+    {
+        Some(role) => {
+            // Create session
+            let session_id = session_store.create_session(&form.username, role).await;
+
+            // Set session cookie and redirect
+            let mut response = Redirect::to("/").into_response();
+            response.headers_mut().insert(
+                "Set-Cookie",
+                session_cookie(&session_id, &session_store.config),
+            );
+            response
+        }
+        None => {
+            tracing::warn!("Failed login attempt for user: {}", form.username);
+            pages::render(&LoginPage {
+                sso_provider: None,
+                error: Some(LoginError::Message(
+                    "Invalid username or password".to_string(),
+                )),
+            })
         }
-    } else {
-        tracing::warn!("Failed login attempt for unknown user: {}", form.username);
-        let template = include_str!("../templates/login.html");
-        let error_html = r#"<div class="error-message">Invalid username or password</div>"#;
-        let html = template.replace("{{ERROR_MESSAGE}}", error_html);
-        Html(html).into_response()
     }
 }
 
@@ -236,10 +980,9 @@ pub async fn logout_handler(
 
     // Clear cookie and redirect to login
     let mut response = Redirect::to("/login").into_response();
-    response.headers_mut().insert(
-        "Set-Cookie",
-        HeaderValue::from_str("session_id=; HttpOnly; SameSite=Strict; Path=/; Max-Age=0").unwrap(),
-    );
+    response
+        .headers_mut()
+        .insert("Set-Cookie", clear_session_cookie(&session_store.config));
     response
 }
 
@@ -271,7 +1014,7 @@ mod tests {
         let config = create_test_config();
         let store = SessionStore::new(config);
 
-        let session_id = store.create_session("testuser").await;
+        let session_id = store.create_session("testuser", Role::Admin).await;
 
         // Session ID should be a valid UUID
         assert!(!session_id.is_empty());
@@ -299,7 +1042,7 @@ mod tests {
         let config = create_test_config();
         let store = SessionStore::new(config);
 
-        let session_id = store.create_session("testuser").await;
+        let session_id = store.create_session("testuser", Role::Admin).await;
 
         // Session should exist
         assert!(store.get_session(&session_id).await.is_some());
@@ -331,7 +1074,7 @@ mod tests {
         });
         let store = SessionStore::new(config);
 
-        let session_id = store.create_session("testuser").await;
+        let session_id = store.create_session("testuser", Role::Admin).await;
 
         // Session should exist immediately
         assert!(store.get_session(&session_id).await.is_some());
@@ -354,8 +1097,8 @@ mod tests {
         let store = SessionStore::new(config);
 
         // Create multiple sessions
-        let session1 = store.create_session("user1").await;
-        let session2 = store.create_session("user2").await;
+        let session1 = store.create_session("user1", Role::Admin).await;
+        let session2 = store.create_session("user2", Role::Admin).await;
 
         // Both should exist
         assert!(store.get_session(&session1).await.is_some());
@@ -372,6 +1115,76 @@ mod tests {
         assert!(store.get_session(&session2).await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_absolute_session_lifetime() {
+        let config = Arc::new(Config {
+            auth_enabled: true,
+            auth_username: "testuser".to_string(),
+            session_timeout_seconds: 3600,
+            session_max_lifetime_seconds: 1,
+            ..Default::default()
+        });
+        let store = SessionStore::new(config);
+
+        let session_id = store.create_session("testuser", Role::Admin).await;
+
+        // Staying active does not extend the session past its lifetime
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(store.get_session(&session_id).await.is_some());
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(store.get_session(&session_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_revoke_sessions() {
+        let store = SessionStore::new(create_test_config());
+        let first = store.create_session("testuser", Role::Admin).await;
+        let second = store
+            .create_session_for(
+                "other",
+                Role::Viewer,
+                AuthMethod::Password,
+                Some("203.0.113.7".to_string()),
+                Some("curl/8.0"),
+            )
+            .await;
+
+        assert_eq!(store.list(None).await.len(), 2);
+        let listed = store.list(Some("other")).await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].address.as_deref(), Some("203.0.113.7"));
+        assert_ne!(listed[0].id, second);
+
+        let revoked = store.revoke(&listed[0].id).await.unwrap();
+        assert_eq!(revoked.username, "other");
+        assert!(store.revoke(&listed[0].id).await.is_none());
+        assert!(store.get_session(&second).await.is_none());
+        assert!(store.get_session(&first).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_survive_reopen_without_cookies_on_disk() {
+        let path = std::env::temp_dir().join(format!("sdm-sessions-{}.json", Uuid::new_v4()));
+        let config = create_test_config();
+        let (kept, dropped) = {
+            let store = SessionStore::open(config.clone(), &path).unwrap();
+            (
+                store.create_session("testuser", Role::Viewer).await,
+                store.create_session("removed-user", Role::Admin).await,
+            )
+        };
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&kept));
+
+        let reopened = SessionStore::open(config, &path).unwrap();
+        let session = reopened.get_session(&kept).await.unwrap();
+        assert_eq!(session.username, "testuser");
+        assert_eq!(session.role, Role::Admin); // Refreshed from the current configuration
+        assert!(reopened.get_session(&dropped).await.is_none());
+        let _ = fs::remove_file(&path);
+    }
+
     #[test]
     fn test_extract_session_id_valid() {
         let cookie_str = "session_id=abc123; other=value";
@@ -407,6 +1220,62 @@ mod tests {
         assert_eq!(session_id, None);
     }
 
+    #[test]
+    fn test_cookies_are_secure_over_https() {
+        let http = Config::default();
+        assert!(!session_cookie("abc", &http)
+            .to_str()
+            .unwrap()
+            .contains("Secure"));
+
+        let https = Config {
+            tls_cert_file: Some("cert.pem".to_string()),
+            tls_key_file: Some("key.pem".to_string()),
+            ..Config::default()
+        };
+        let cookie = session_cookie("abc", &https);
+        assert!(cookie.to_str().unwrap().starts_with("session_id=abc;"));
+        assert!(cookie.to_str().unwrap().ends_with("; Secure"));
+        assert!(clear_session_cookie(&https)
+            .to_str()
+            .unwrap()
+            .ends_with("Max-Age=0; Secure"));
+    }
+
+    #[test]
+    fn test_parse_users() {
+        let hash = Config::hash_password("secret").unwrap();
+        let users = parse_users(&format!(
+            "# comment\n\nalice:viewer:{hash}\n bob : Operator : {hash}\n"
+        ))
+        .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].username, "alice");
+        assert_eq!(users[0].role, Role::Viewer);
+        assert_eq!(users[1].username, "bob");
+        assert_eq!(users[1].role, Role::Operator);
+
+        assert!(parse_users("alice:viewer").unwrap_err().contains("line 1"));
+        assert!(parse_users(&format!("alice:root:{hash}"))
+            .unwrap_err()
+            .contains("unknown role"));
+        assert!(parse_users("alice:viewer:plaintext")
+            .unwrap_err()
+            .contains("not an Argon2 hash"));
+        assert!(
+            parse_users(&format!("alice:viewer:{hash}\nalice:admin:{hash}"))
+                .unwrap_err()
+                .contains("duplicate user")
+        );
+    }
+
+    #[test]
+    fn test_roles_are_ordered() {
+        assert!(Role::Viewer < Role::Operator);
+        assert!(Role::Operator < Role::Admin);
+        assert_eq!("ADMIN".parse::<Role>(), Ok(Role::Admin));
+    }
+
     #[test]
     fn test_login_form_deserialization() {
         let json = r#"{"username": "admin", "password": "secret"}"#;
@@ -415,4 +1284,32 @@ mod tests {
         assert_eq!(form.username, "admin");
         assert_eq!(form.password, "secret");
     }
+
+    #[test]
+    fn test_scrape_token() {
+        let config = Config {
+            prometheus_token: Some("scrape-secret".to_string()),
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        assert!(!has_scrape_token(&headers, &config));
+
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer scrape-secret"),
+        );
+        assert!(has_scrape_token(&headers, &config));
+
+        headers.insert("authorization", HeaderValue::from_static("Bearer wrong"));
+        assert!(!has_scrape_token(&headers, &config));
+
+        // No token configured means bearer access is never granted
+        let headers_with_empty = {
+            let mut h = HeaderMap::new();
+            h.insert("authorization", HeaderValue::from_static("Bearer "));
+            h
+        };
+        assert!(!has_scrape_token(&headers_with_empty, &Config::default()));
+    }
 }
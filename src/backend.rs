@@ -0,0 +1,314 @@
+use async_trait::async_trait;
+use bollard::container::LogOutput;
+use bollard::errors::Error;
+use bollard::Docker;
+use futures_util::stream::{BoxStream, StreamExt};
+
+use crate::docker::{self, ContainerIdentity, ExecSession};
+use crate::models::{
+    ContainerMetrics, ContainerRemovalOptions, ContainerStateSummary, ContainerSummary,
+    CreateContainerRequest, CreateNetworkRequest, CreateVolumeRequest, DockerEvent, ImageInfo,
+    ImagePrunePreview, ImagePruneResult, ImageRemovalOptions, ImageRemovalResult,
+    LocalImageSummary, NetworkConnectRequest, NetworkSummary, PullImageRequest, PullProgress,
+    SystemMetrics, VolumePruneResult, VolumeSummary,
+};
+
+#[cfg(test)]
+pub mod fake;
+
+/// A stream of items produced by the Docker engine, e.g. log lines or pull progress
+pub type DockerStream<T> = BoxStream<'static, Result<T, Error>>;
+
+/// Everything the web layer and the stats collector need from Docker. The server holds
+/// one `Arc<dyn DockerBackend>` in its state, so every request shares a single client.
+#[async_trait]
+pub trait DockerBackend: Send + Sync {
+    // Containers
+    async fn list_running_containers(&self) -> Result<Vec<ContainerSummary>, Error>;
+    async fn list_all_containers(&self) -> Result<Vec<ContainerStateSummary>, Error>;
+    async fn list_running_container_identities(&self) -> Result<Vec<ContainerIdentity>, Error>;
+    async fn create_and_start_container_from_image(&self, image_name: &str) -> Result<(), Error>;
+    async fn create_and_start_container_enhanced(
+        &self,
+        request: CreateContainerRequest,
+    ) -> Result<String, Error>;
+    async fn get_container_environment(
+        &self,
+        container_id_or_name: &str,
+    ) -> Result<Vec<String>, Error>;
+    async fn start_container(&self, container_id_or_name: &str) -> Result<(), Error>;
+    async fn stop_container(&self, container_id_or_name: &str) -> Result<(), Error>;
+    async fn restart_container(&self, container_id_or_name: &str) -> Result<(), Error>;
+    async fn remove_container(
+        &self,
+        container_id_or_name: &str,
+        options: &ContainerRemovalOptions,
+    ) -> Result<(), Error>;
+    async fn get_container_logs(
+        &self,
+        container_id: &str,
+        tail: Option<&str>,
+        follow: bool,
+    ) -> Result<DockerStream<LogOutput>, Error>;
+    async fn get_container_logs_recent(
+        &self,
+        container_id: &str,
+        tail: Option<&str>,
+    ) -> Result<Vec<String>, Error>;
+    async fn create_exec_session(
+        &self,
+        container_id: &str,
+        command: Option<Vec<String>>,
+    ) -> Result<ExecSession, Error>;
+    async fn resize_exec(&self, exec_id: &str, cols: u16, rows: u16) -> Result<(), Error>;
+
+    // Metrics
+    fn stream_container_metrics(
+        &self,
+        container: &ContainerIdentity,
+    ) -> Result<DockerStream<ContainerMetrics>, Error>;
+    async fn get_system_metrics(&self) -> Result<SystemMetrics, Error>;
+
+    // Events
+    fn stream_events(&self) -> Result<DockerStream<DockerEvent>, Error>;
+
+    // Images
+    async fn list_downloaded_images(&self) -> Result<Vec<LocalImageSummary>, Error>;
+    async fn get_image_info(&self, image_name: &str) -> Result<ImageInfo, Error>;
+    fn pull_image(&self, request: &PullImageRequest) -> Result<DockerStream<PullProgress>, Error>;
+    async fn remove_image(
+        &self,
+        image: &str,
+        options: &ImageRemovalOptions,
+    ) -> Result<ImageRemovalResult, Error>;
+    async fn untag_image(&self, tag: &str) -> Result<ImageRemovalResult, Error>;
+    async fn preview_dangling_image_prune(&self) -> Result<ImagePrunePreview, Error>;
+    async fn prune_dangling_images(&self) -> Result<ImagePruneResult, Error>;
+
+    // Volumes
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>, Error>;
+    async fn inspect_volume(&self, name: &str) -> Result<VolumeSummary, Error>;
+    async fn create_volume(&self, request: &CreateVolumeRequest) -> Result<VolumeSummary, Error>;
+    async fn remove_volume(&self, name: &str, force: bool) -> Result<(), Error>;
+    async fn prune_volumes(&self) -> Result<VolumePruneResult, Error>;
+
+    // Networks
+    async fn list_networks(&self) -> Result<Vec<NetworkSummary>, Error>;
+    async fn inspect_network(&self, id_or_name: &str) -> Result<NetworkSummary, Error>;
+    async fn create_network(&self, request: &CreateNetworkRequest)
+        -> Result<NetworkSummary, Error>;
+    async fn remove_network(&self, id_or_name: &str) -> Result<(), Error>;
+    async fn connect_container_to_network(
+        &self,
+        network: &str,
+        request: &NetworkConnectRequest,
+    ) -> Result<(), Error>;
+    async fn disconnect_container_from_network(
+        &self,
+        network: &str,
+        container: &str,
+        force: bool,
+    ) -> Result<(), Error>;
+}
+
+/// The real backend: a single bollard client talking to the configured Docker socket
+pub struct BollardBackend {
+    docker: Docker,
+}
+
+impl BollardBackend {
+    pub fn connect(socket_path: Option<&str>) -> Result<Self, Error> {
+        Ok(Self {
+            docker: docker::connect(socket_path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl DockerBackend for BollardBackend {
+    async fn list_running_containers(&self) -> Result<Vec<ContainerSummary>, Error> {
+        docker::list_running_containers(&self.docker).await
+    }
+
+    async fn list_all_containers(&self) -> Result<Vec<ContainerStateSummary>, Error> {
+        docker::list_all_containers(&self.docker).await
+    }
+
+    async fn list_running_container_identities(&self) -> Result<Vec<ContainerIdentity>, Error> {
+        docker::list_running_container_identities(&self.docker).await
+    }
+
+    async fn create_and_start_container_from_image(&self, image_name: &str) -> Result<(), Error> {
+        docker::create_and_start_container_from_image(&self.docker, image_name).await
+    }
+
+    async fn create_and_start_container_enhanced(
+        &self,
+        request: CreateContainerRequest,
+    ) -> Result<String, Error> {
+        docker::create_and_start_container_enhanced(&self.docker, request).await
+    }
+
+    async fn get_container_environment(
+        &self,
+        container_id_or_name: &str,
+    ) -> Result<Vec<String>, Error> {
+        docker::get_container_environment(&self.docker, container_id_or_name).await
+    }
+
+    async fn start_container(&self, container_id_or_name: &str) -> Result<(), Error> {
+        docker::start_container(&self.docker, container_id_or_name).await
+    }
+
+    async fn stop_container(&self, container_id_or_name: &str) -> Result<(), Error> {
+        docker::stop_container(&self.docker, container_id_or_name).await
+    }
+
+    async fn restart_container(&self, container_id_or_name: &str) -> Result<(), Error> {
+        docker::restart_container(&self.docker, container_id_or_name).await
+    }
+
+    async fn remove_container(
+        &self,
+        container_id_or_name: &str,
+        options: &ContainerRemovalOptions,
+    ) -> Result<(), Error> {
+        docker::remove_container(&self.docker, container_id_or_name, options).await
+    }
+
+    async fn get_container_logs(
+        &self,
+        container_id: &str,
+        tail: Option<&str>,
+        follow: bool,
+    ) -> Result<DockerStream<LogOutput>, Error> {
+        Ok(
+            docker::get_container_logs(&self.docker, container_id, tail, follow)
+                .await?
+                .boxed(),
+        )
+    }
+
+    async fn get_container_logs_recent(
+        &self,
+        container_id: &str,
+        tail: Option<&str>,
+    ) -> Result<Vec<String>, Error> {
+        docker::get_container_logs_recent(&self.docker, container_id, tail).await
+    }
+
+    async fn create_exec_session(
+        &self,
+        container_id: &str,
+        command: Option<Vec<String>>,
+    ) -> Result<ExecSession, Error> {
+        docker::create_exec_session(&self.docker, container_id, command).await
+    }
+
+    async fn resize_exec(&self, exec_id: &str, cols: u16, rows: u16) -> Result<(), Error> {
+        docker::resize_exec(&self.docker, exec_id, cols, rows).await
+    }
+
+    fn stream_container_metrics(
+        &self,
+        container: &ContainerIdentity,
+    ) -> Result<DockerStream<ContainerMetrics>, Error> {
+        Ok(docker::stream_container_metrics(&self.docker, container)?.boxed())
+    }
+
+    async fn get_system_metrics(&self) -> Result<SystemMetrics, Error> {
+        docker::get_system_metrics(&self.docker).await
+    }
+
+    fn stream_events(&self) -> Result<DockerStream<DockerEvent>, Error> {
+        Ok(docker::stream_events(&self.docker).boxed())
+    }
+
+    async fn list_downloaded_images(&self) -> Result<Vec<LocalImageSummary>, Error> {
+        docker::list_downloaded_images(&self.docker).await
+    }
+
+    async fn get_image_info(&self, image_name: &str) -> Result<ImageInfo, Error> {
+        docker::get_image_info(&self.docker, image_name).await
+    }
+
+    fn pull_image(&self, request: &PullImageRequest) -> Result<DockerStream<PullProgress>, Error> {
+        Ok(docker::pull_image(&self.docker, request)?.boxed())
+    }
+
+    async fn remove_image(
+        &self,
+        image: &str,
+        options: &ImageRemovalOptions,
+    ) -> Result<ImageRemovalResult, Error> {
+        docker::remove_image(&self.docker, image, options).await
+    }
+
+    async fn untag_image(&self, tag: &str) -> Result<ImageRemovalResult, Error> {
+        docker::untag_image(&self.docker, tag).await
+    }
+
+    async fn preview_dangling_image_prune(&self) -> Result<ImagePrunePreview, Error> {
+        docker::preview_dangling_image_prune(&self.docker).await
+    }
+
+    async fn prune_dangling_images(&self) -> Result<ImagePruneResult, Error> {
+        docker::prune_dangling_images(&self.docker).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>, Error> {
+        docker::list_volumes(&self.docker).await
+    }
+
+    async fn inspect_volume(&self, name: &str) -> Result<VolumeSummary, Error> {
+        docker::inspect_volume(&self.docker, name).await
+    }
+
+    async fn create_volume(&self, request: &CreateVolumeRequest) -> Result<VolumeSummary, Error> {
+        docker::create_volume(&self.docker, request).await
+    }
+
+    async fn remove_volume(&self, name: &str, force: bool) -> Result<(), Error> {
+        docker::remove_volume(&self.docker, name, force).await
+    }
+
+    async fn prune_volumes(&self) -> Result<VolumePruneResult, Error> {
+        docker::prune_volumes(&self.docker).await
+    }
+
+    async fn list_networks(&self) -> Result<Vec<NetworkSummary>, Error> {
+        docker::list_networks(&self.docker).await
+    }
+
+    async fn inspect_network(&self, id_or_name: &str) -> Result<NetworkSummary, Error> {
+        docker::inspect_network(&self.docker, id_or_name).await
+    }
+
+    async fn create_network(
+        &self,
+        request: &CreateNetworkRequest,
+    ) -> Result<NetworkSummary, Error> {
+        docker::create_network(&self.docker, request).await
+    }
+
+    async fn remove_network(&self, id_or_name: &str) -> Result<(), Error> {
+        docker::remove_network(&self.docker, id_or_name).await
+    }
+
+    async fn connect_container_to_network(
+        &self,
+        network: &str,
+        request: &NetworkConnectRequest,
+    ) -> Result<(), Error> {
+        docker::connect_container_to_network(&self.docker, network, request).await
+    }
+
+    async fn disconnect_container_from_network(
+        &self,
+        network: &str,
+        container: &str,
+        force: bool,
+    ) -> Result<(), Error> {
+        docker::disconnect_container_from_network(&self.docker, network, container, force).await
+    }
+}
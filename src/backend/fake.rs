@@ -0,0 +1,655 @@
+use async_trait::async_trait;
+use bollard::container::LogOutput;
+use bollard::errors::Error;
+use chrono::Utc;
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::{DockerBackend, DockerStream};
+use crate::docker::{ContainerIdentity, ExecSession};
+use crate::models::{
+    ContainerEnvironmentVariable, ContainerMetrics, ContainerRemovalOptions, ContainerStateSummary,
+    ContainerSummary, CreateContainerRequest, CreateNetworkRequest, CreateVolumeRequest,
+    DockerEvent, ImageInfo, ImagePrunePreview, ImagePruneResult, ImageRemovalOptions,
+    ImageRemovalResult, LocalImageSummary, NetworkAttachment, NetworkConnectRequest,
+    NetworkSummary, PullImageRequest, PullProgress, SystemMetrics, VolumePruneResult,
+    VolumeSummary,
+};
+
+/// In-memory Docker used by handler tests. Containers, images, volumes and networks live
+/// in plain vectors; lifecycle calls mutate them and missing objects yield a 404.
+#[derive(Default)]
+pub struct FakeBackend {
+    state: Mutex<FakeState>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    containers: Vec<ContainerStateSummary>,
+    images: Vec<LocalImageSummary>,
+    volumes: Vec<VolumeSummary>,
+    networks: Vec<NetworkSummary>,
+    logs: HashMap<String, Vec<String>>,
+    environments: HashMap<String, Vec<String>>,
+    image_environments: HashMap<String, Vec<String>>,
+    events: Vec<DockerEvent>,
+    next_id: u64,
+}
+
+impl FakeState {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:012x}", self.next_id)
+    }
+
+    fn container_mut(&mut self, id_or_name: &str) -> Result<&mut ContainerStateSummary, Error> {
+        self.containers
+            .iter_mut()
+            .find(|c| c.name == id_or_name || c.id.starts_with(id_or_name))
+            .ok_or_else(|| not_found("container", id_or_name))
+    }
+
+    fn add_container(&mut self, name: &str, image: &str, state: &str) -> String {
+        let id = self.next_id();
+        self.containers.push(ContainerStateSummary {
+            id: id.clone(),
+            name: name.to_string(),
+            image: image.to_string(),
+            state: state.to_string(),
+            status: state.to_string(),
+            exit_code: None,
+            created_at: Some(Utc::now()),
+            started_at: None,
+            finished_at: None,
+        });
+        id
+    }
+}
+
+fn not_found(kind: &str, id: &str) -> Error {
+    Error::DockerResponseServerError {
+        status_code: 404,
+        message: format!("No such {}: {}", kind, id),
+    }
+}
+
+fn conflict(message: String) -> Error {
+    Error::DockerResponseServerError {
+        status_code: 409,
+        message,
+    }
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_container(self, name: &str, image: &str, state: &str) -> Self {
+        self.state.lock().unwrap().add_container(name, image, state);
+        self
+    }
+
+    pub fn with_image(self, tag: &str) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            let id = format!("sha256:{}", state.next_id());
+            state.images.push(LocalImageSummary {
+                id,
+                repo_tags: vec![tag.to_string()],
+                size_bytes: 1024,
+            });
+        }
+        self
+    }
+
+    /// An untagged image, like one left behind when its tag moved to a newer build
+    pub fn with_dangling_image(self) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            let id = format!("sha256:{}", state.next_id());
+            state.images.push(LocalImageSummary {
+                id,
+                repo_tags: Vec::new(),
+                size_bytes: 2048,
+            });
+        }
+        self
+    }
+
+    pub fn with_volume(self, name: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .volumes
+            .push(volume(name, "local"));
+        self
+    }
+
+    pub fn with_logs(self, container: &str, lines: &[&str]) -> Self {
+        self.state.lock().unwrap().logs.insert(
+            container.to_string(),
+            lines.iter().map(|line| line.to_string()).collect(),
+        );
+        self
+    }
+
+    pub fn with_environment(self, container: &str, variables: &[&str]) -> Self {
+        self.state.lock().unwrap().environments.insert(
+            container.to_string(),
+            variables.iter().map(|var| var.to_string()).collect(),
+        );
+        self
+    }
+
+    /// Queue an engine event; each `stream_events` call replays the queued events and ends
+    pub fn with_image_environment(self, tag: &str, variables: &[&str]) -> Self {
+        self.state.lock().unwrap().image_environments.insert(
+            tag.to_string(),
+            variables.iter().map(|var| var.to_string()).collect(),
+        );
+        self
+    }
+
+    pub fn with_event(self, event: DockerEvent) -> Self {
+        self.state.lock().unwrap().events.push(event);
+        self
+    }
+
+    /// Current state of a container by name, e.g. "running" or "exited"
+    pub fn container_state(&self, name: &str) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .containers
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.state.clone())
+    }
+
+    pub fn volume_names(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.volumes.iter().map(|v| v.name.clone()).collect()
+    }
+}
+
+fn volume(name: &str, driver: &str) -> VolumeSummary {
+    VolumeSummary {
+        name: name.to_string(),
+        driver: driver.to_string(),
+        mountpoint: format!("/var/lib/docker/volumes/{}/_data", name),
+        scope: "local".to_string(),
+        created_at: Some(Utc::now()),
+        labels: HashMap::new(),
+        options: HashMap::new(),
+        used_by: Vec::new(),
+    }
+}
+
+#[async_trait]
+impl DockerBackend for FakeBackend {
+    async fn list_running_containers(&self) -> Result<Vec<ContainerSummary>, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .containers
+            .iter()
+            .filter(|c| c.state == "running")
+            .map(|c| ContainerSummary {
+                id: c.id.clone(),
+                name: c.name.clone(),
+                image: c.image.clone(),
+                status: c.status.clone(),
+                ports: Vec::new(),
+                environment: state.environments.get(&c.name).cloned().unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn list_all_containers(&self) -> Result<Vec<ContainerStateSummary>, Error> {
+        Ok(self.state.lock().unwrap().containers.clone())
+    }
+
+    async fn list_running_container_identities(&self) -> Result<Vec<ContainerIdentity>, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .containers
+            .iter()
+            .filter(|c| c.state == "running")
+            .map(|c| ContainerIdentity {
+                id: c.id.clone(),
+                name: c.name.clone(),
+                image: c.image.clone(),
+            })
+            .collect())
+    }
+
+    async fn create_and_start_container_from_image(&self, image_name: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let name = image_name
+            .split(':')
+            .next()
+            .unwrap_or("container")
+            .replace('/', "-");
+        state.add_container(&name, image_name, "running");
+        Ok(())
+    }
+
+    async fn create_and_start_container_enhanced(
+        &self,
+        request: CreateContainerRequest,
+    ) -> Result<String, Error> {
+        let mut state = self.state.lock().unwrap();
+        let name = request
+            .container_name
+            .clone()
+            .unwrap_or_else(|| request.image_name.replace([':', '/'], "-"));
+        if state.containers.iter().any(|c| c.name == name) {
+            return Err(conflict(format!(
+                "Conflict. The container name \"/{}\" is already in use",
+                name
+            )));
+        }
+        Ok(state.add_container(&name, &request.image_name, "running"))
+    }
+
+    async fn get_container_environment(
+        &self,
+        container_id_or_name: &str,
+    ) -> Result<Vec<String>, Error> {
+        let mut state = self.state.lock().unwrap();
+        let name = state.container_mut(container_id_or_name)?.name.clone();
+        Ok(state.environments.get(&name).cloned().unwrap_or_default())
+    }
+
+    async fn start_container(&self, container_id_or_name: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let container = state.container_mut(container_id_or_name)?;
+        container.state = "running".to_string();
+        container.status = "Up".to_string();
+        container.started_at = Some(Utc::now());
+        Ok(())
+    }
+
+    async fn stop_container(&self, container_id_or_name: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let container = state.container_mut(container_id_or_name)?;
+        container.state = "exited".to_string();
+        container.status = "Exited (0)".to_string();
+        container.exit_code = Some(0);
+        container.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    async fn restart_container(&self, container_id_or_name: &str) -> Result<(), Error> {
+        self.stop_container(container_id_or_name).await?;
+        self.start_container(container_id_or_name).await
+    }
+
+    async fn remove_container(
+        &self,
+        container_id_or_name: &str,
+        options: &ContainerRemovalOptions,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let container = state.container_mut(container_id_or_name)?;
+        if container.state == "running" && !options.force {
+            return Err(conflict(format!(
+                "You cannot remove a running container {}. Stop the container before attempting removal or force remove",
+                container.id
+            )));
+        }
+        let id = container.id.clone();
+        state.containers.retain(|c| c.id != id);
+        Ok(())
+    }
+
+    async fn get_container_logs(
+        &self,
+        container_id: &str,
+        _tail: Option<&str>,
+        _follow: bool,
+    ) -> Result<DockerStream<LogOutput>, Error> {
+        let lines = self.get_container_logs_recent(container_id, None).await?;
+        Ok(stream::iter(lines.into_iter().map(|line| {
+            Ok(LogOutput::StdOut {
+                message: format!("{}\n", line).into(),
+            })
+        }))
+        .boxed())
+    }
+
+    async fn get_container_logs_recent(
+        &self,
+        container_id: &str,
+        _tail: Option<&str>,
+    ) -> Result<Vec<String>, Error> {
+        let mut state = self.state.lock().unwrap();
+        let container = state.container_mut(container_id)?;
+        let (id, name) = (container.id.clone(), container.name.clone());
+        Ok(state
+            .logs
+            .get(&name)
+            .or_else(|| state.logs.get(&id))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn create_exec_session(
+        &self,
+        container_id: &str,
+        _command: Option<Vec<String>>,
+    ) -> Result<ExecSession, Error> {
+        Err(Error::DockerResponseServerError {
+            status_code: 501,
+            message: format!(
+                "Exec into {} is not supported by the fake backend",
+                container_id
+            ),
+        })
+    }
+
+    async fn resize_exec(&self, _exec_id: &str, _cols: u16, _rows: u16) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn stream_container_metrics(
+        &self,
+        _container: &ContainerIdentity,
+    ) -> Result<DockerStream<ContainerMetrics>, Error> {
+        Ok(stream::empty().boxed())
+    }
+
+    async fn get_system_metrics(&self) -> Result<SystemMetrics, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(SystemMetrics {
+            timestamp: Utc::now(),
+            total_containers: state.containers.len() as u32,
+            running_containers: state
+                .containers
+                .iter()
+                .filter(|c| c.state == "running")
+                .count() as u32,
+            total_images: state.images.len() as u32,
+            docker_version: "fake".to_string(),
+        })
+    }
+
+    fn stream_events(&self) -> Result<DockerStream<DockerEvent>, Error> {
+        let events = self.state.lock().unwrap().events.clone();
+        Ok(stream::iter(events.into_iter().map(Ok)).boxed())
+    }
+
+    async fn list_downloaded_images(&self) -> Result<Vec<LocalImageSummary>, Error> {
+        Ok(self.state.lock().unwrap().images.clone())
+    }
+
+    async fn get_image_info(&self, image_name: &str) -> Result<ImageInfo, Error> {
+        let state = self.state.lock().unwrap();
+        let image = state
+            .images
+            .iter()
+            .find(|i| i.id == image_name || i.repo_tags.iter().any(|t| t == image_name))
+            .ok_or_else(|| not_found("image", image_name))?;
+        let environment = image
+            .repo_tags
+            .iter()
+            .find_map(|tag| state.image_environments.get(tag))
+            .into_iter()
+            .flatten()
+            .filter_map(|var| var.split_once('='))
+            .map(|(key, value)| ContainerEnvironmentVariable {
+                key: key.to_string(),
+                value: Some(value.to_string()),
+                masked: false,
+            })
+            .collect();
+        Ok(ImageInfo {
+            id: image.id.clone(),
+            repo_tags: image.repo_tags.clone(),
+            exposed_ports: Vec::new(),
+            environment_variables: environment,
+        })
+    }
+
+    fn pull_image(&self, request: &PullImageRequest) -> Result<DockerStream<PullProgress>, Error> {
+        let tag = match &request.tag {
+            Some(tag) if !tag.is_empty() => format!("{}:{}", request.image, tag),
+            _ if request.image.contains(':') => request.image.clone(),
+            _ => format!("{}:latest", request.image),
+        };
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.images.iter().any(|i| i.repo_tags.contains(&tag)) {
+                let id = format!("sha256:{}", state.next_id());
+                state.images.push(LocalImageSummary {
+                    id,
+                    repo_tags: vec![tag.clone()],
+                    size_bytes: 1024,
+                });
+            }
+        }
+        Ok(stream::iter([Ok(PullProgress {
+            layer_id: None,
+            status: format!("Downloaded newer image for {}", tag),
+            current: None,
+            total: None,
+            error: None,
+            complete: false,
+        })])
+        .boxed())
+    }
+
+    async fn remove_image(
+        &self,
+        image: &str,
+        _options: &ImageRemovalOptions,
+    ) -> Result<ImageRemovalResult, Error> {
+        let mut state = self.state.lock().unwrap();
+        let position = state
+            .images
+            .iter()
+            .position(|i| i.id == image || i.repo_tags.iter().any(|t| t == image))
+            .ok_or_else(|| not_found("image", image))?;
+        let removed = state.images.remove(position);
+        Ok(ImageRemovalResult {
+            untagged: removed.repo_tags,
+            deleted: vec![removed.id],
+        })
+    }
+
+    async fn untag_image(&self, tag: &str) -> Result<ImageRemovalResult, Error> {
+        let mut state = self.state.lock().unwrap();
+        let image = state
+            .images
+            .iter_mut()
+            .find(|i| i.repo_tags.iter().any(|t| t == tag))
+            .ok_or_else(|| not_found("image", tag))?;
+        if image.repo_tags.len() <= 1 {
+            return Err(conflict(format!(
+                "{} is the image's only tag; remove the image instead of untagging it",
+                tag
+            )));
+        }
+        image.repo_tags.retain(|t| t != tag);
+        Ok(ImageRemovalResult {
+            untagged: vec![tag.to_string()],
+            deleted: Vec::new(),
+        })
+    }
+
+    async fn preview_dangling_image_prune(&self) -> Result<ImagePrunePreview, Error> {
+        let state = self.state.lock().unwrap();
+        let images: Vec<LocalImageSummary> = state
+            .images
+            .iter()
+            .filter(|i| i.repo_tags.is_empty())
+            .cloned()
+            .collect();
+        Ok(ImagePrunePreview {
+            reclaimable_bytes: images.iter().map(|i| i.size_bytes).sum(),
+            images,
+        })
+    }
+
+    async fn prune_dangling_images(&self) -> Result<ImagePruneResult, Error> {
+        let preview = self.preview_dangling_image_prune().await?;
+        let mut state = self.state.lock().unwrap();
+        state.images.retain(|i| !i.repo_tags.is_empty());
+        Ok(ImagePruneResult {
+            deleted: preview.images.into_iter().map(|i| i.id).collect(),
+            space_reclaimed_bytes: preview.reclaimable_bytes,
+        })
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>, Error> {
+        Ok(self.state.lock().unwrap().volumes.clone())
+    }
+
+    async fn inspect_volume(&self, name: &str) -> Result<VolumeSummary, Error> {
+        let state = self.state.lock().unwrap();
+        state
+            .volumes
+            .iter()
+            .find(|v| v.name == name)
+            .cloned()
+            .ok_or_else(|| not_found("volume", name))
+    }
+
+    async fn create_volume(&self, request: &CreateVolumeRequest) -> Result<VolumeSummary, Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(existing) = state.volumes.iter().find(|v| v.name == request.name) {
+            // Docker treats creating an existing volume as a no-op
+            return Ok(existing.clone());
+        }
+        let mut created = volume(&request.name, request.driver.as_deref().unwrap_or("local"));
+        created.labels = request.labels.clone();
+        state.volumes.push(created.clone());
+        Ok(created)
+    }
+
+    async fn remove_volume(&self, name: &str, force: bool) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let volume = state
+            .volumes
+            .iter()
+            .find(|v| v.name == name)
+            .ok_or_else(|| not_found("volume", name))?;
+        if !volume.used_by.is_empty() && !force {
+            return Err(conflict(format!("remove {}: volume is in use", name)));
+        }
+        state.volumes.retain(|v| v.name != name);
+        Ok(())
+    }
+
+    async fn prune_volumes(&self) -> Result<VolumePruneResult, Error> {
+        let mut state = self.state.lock().unwrap();
+        let deleted: Vec<String> = state
+            .volumes
+            .iter()
+            .filter(|v| v.used_by.is_empty())
+            .map(|v| v.name.clone())
+            .collect();
+        state.volumes.retain(|v| !v.used_by.is_empty());
+        Ok(VolumePruneResult {
+            deleted,
+            space_reclaimed_bytes: 0,
+        })
+    }
+
+    async fn list_networks(&self) -> Result<Vec<NetworkSummary>, Error> {
+        Ok(self.state.lock().unwrap().networks.clone())
+    }
+
+    async fn inspect_network(&self, id_or_name: &str) -> Result<NetworkSummary, Error> {
+        let state = self.state.lock().unwrap();
+        state
+            .networks
+            .iter()
+            .find(|n| n.name == id_or_name || n.id.starts_with(id_or_name))
+            .cloned()
+            .ok_or_else(|| not_found("network", id_or_name))
+    }
+
+    async fn create_network(
+        &self,
+        request: &CreateNetworkRequest,
+    ) -> Result<NetworkSummary, Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.networks.iter().any(|n| n.name == request.name) {
+            return Err(conflict(format!(
+                "network with name {} already exists",
+                request.name
+            )));
+        }
+        let network = NetworkSummary {
+            id: state.next_id(),
+            name: request.name.clone(),
+            driver: request
+                .driver
+                .clone()
+                .unwrap_or_else(|| "bridge".to_string()),
+            scope: "local".to_string(),
+            internal: request.internal,
+            attachable: true,
+            created_at: Some(Utc::now()),
+            subnets: Vec::new(),
+            labels: request.labels.clone(),
+            containers: Vec::new(),
+        };
+        state.networks.push(network.clone());
+        Ok(network)
+    }
+
+    async fn remove_network(&self, id_or_name: &str) -> Result<(), Error> {
+        let network = self.inspect_network(id_or_name).await?;
+        if !network.containers.is_empty() {
+            return Err(conflict(format!(
+                "error while removing network: network {} has active endpoints",
+                network.name
+            )));
+        }
+        let mut state = self.state.lock().unwrap();
+        state.networks.retain(|n| n.id != network.id);
+        Ok(())
+    }
+
+    async fn connect_container_to_network(
+        &self,
+        network: &str,
+        request: &NetworkConnectRequest,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let container = state.container_mut(&request.container)?.clone();
+        let network = state
+            .networks
+            .iter_mut()
+            .find(|n| n.name == network || n.id.starts_with(network))
+            .ok_or_else(|| not_found("network", network))?;
+        network.containers.push(NetworkAttachment {
+            container_id: container.id,
+            container_name: container.name,
+            container_state: container.state,
+            ip_address: None,
+            aliases: request.aliases.clone(),
+        });
+        Ok(())
+    }
+
+    async fn disconnect_container_from_network(
+        &self,
+        network: &str,
+        container: &str,
+        _force: bool,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let network = state
+            .networks
+            .iter_mut()
+            .find(|n| n.name == network || n.id.starts_with(network))
+            .ok_or_else(|| not_found("network", network))?;
+        network
+            .containers
+            .retain(|c| c.container_name != container && !c.container_id.starts_with(container));
+        Ok(())
+    }
+}
@@ -4,7 +4,10 @@ use serde::Deserialize;
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::auth::{self, Role, UserAccount};
+use crate::login_throttle::{self, IpNetwork};
+
+#[derive(Clone, Deserialize)]
 pub struct Config {
     /// Server host to bind to
     #[serde(default = "default_host")]
@@ -14,6 +17,20 @@ pub struct Config {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    /// PEM certificate chain to serve HTTPS with (default: unset, plain HTTP). Needs
+    /// `tls_key_file` too.
+    #[serde(default)]
+    pub tls_cert_file: Option<String>,
+
+    /// PEM private key of `tls_cert_file`
+    #[serde(default)]
+    pub tls_key_file: Option<String>,
+
+    /// PEM CA certificates that client certificates must be issued by. When set, clients
+    /// without such a certificate are refused during the TLS handshake.
+    #[serde(default)]
+    pub tls_client_ca_file: Option<String>,
+
     /// Log level for the application
     #[serde(default = "default_log_level")]
     pub log_level: String,
@@ -34,9 +51,13 @@ pub struct Config {
     #[serde(default = "default_max_chart_containers")]
     pub max_chart_containers: usize,
 
+    /// Container environment variables whose values are masked in the UI and API, as
+    /// case-insensitive name patterns where `*` matches anything
+    #[serde(default = "default_secret_env_patterns")]
+    pub secret_env_patterns: Vec<String>,
+
     /// Graceful shutdown timeout in seconds
     #[serde(default = "default_shutdown_timeout")]
-    #[allow(dead_code)]
     pub shutdown_timeout_seconds: u64,
 
     /// Authentication settings
@@ -56,9 +77,147 @@ pub struct Config {
     #[serde(default)]
     pub auth_password_hash: Option<String>,
 
+    /// File listing users and their roles; replaces the single admin account when set
+    #[serde(default)]
+    pub users_file: Option<String>,
+
+    /// Accounts loaded from `users_file` (empty when the single admin account is used)
+    #[serde(skip)]
+    pub users: Vec<UserAccount>,
+
     /// Session timeout in seconds (default: 3600 = 1 hour)
     #[serde(default = "default_session_timeout")]
     pub session_timeout_seconds: u64,
+
+    /// Longest a session lasts after sign-in, however active it is (default: 86400 = 1 day)
+    #[serde(default = "default_session_max_lifetime")]
+    pub session_max_lifetime_seconds: u64,
+
+    /// File sessions are persisted to so they survive restarts (default: unset, in memory only)
+    #[serde(default)]
+    pub sessions_file: Option<String>,
+
+    /// OpenID Connect issuer URL. Single sign-on is offered when the issuer, client ID and
+    /// redirect URL are all set.
+    #[serde(default)]
+    pub oidc_issuer: Option<String>,
+
+    /// Client ID registered with the identity provider
+    #[serde(default)]
+    pub oidc_client_id: Option<String>,
+
+    /// Client secret, for confidential clients
+    #[serde(default)]
+    pub oidc_client_secret: Option<String>,
+
+    /// Where the identity provider sends users back to, ending in `/auth/oidc/callback`
+    #[serde(default)]
+    pub oidc_redirect_url: Option<String>,
+
+    /// Scopes requested at sign-in
+    #[serde(default = "default_oidc_scopes")]
+    pub oidc_scopes: String,
+
+    /// ID token claim holding the username
+    #[serde(default = "default_oidc_username_claim")]
+    pub oidc_username_claim: String,
+
+    /// ID token claim holding the user's groups
+    #[serde(default = "default_oidc_groups_claim")]
+    pub oidc_groups_claim: String,
+
+    /// Groups whose members get the admin role
+    #[serde(default)]
+    pub oidc_admin_groups: Vec<String>,
+
+    /// Groups whose members get the operator role
+    #[serde(default)]
+    pub oidc_operator_groups: Vec<String>,
+
+    /// Groups whose members get the viewer role
+    #[serde(default)]
+    pub oidc_viewer_groups: Vec<String>,
+
+    /// Role for users in none of the groups above (default: unset, they cannot sign in)
+    #[serde(default)]
+    pub oidc_default_role: Option<Role>,
+
+    /// Name of the identity provider on the login button
+    #[serde(default = "default_oidc_provider_name")]
+    pub oidc_provider_name: String,
+
+    /// Header in which an authenticating reverse proxy passes the signed-in username, e.g.
+    /// `X-Forwarded-User`. Only believed on connections from `trusted_proxies`.
+    #[serde(default)]
+    pub proxy_auth_user_header: Option<String>,
+
+    /// Header in which the proxy passes the user's comma-separated groups
+    #[serde(default)]
+    pub proxy_auth_groups_header: Option<String>,
+
+    /// Proxy groups whose members get the admin role
+    #[serde(default)]
+    pub proxy_auth_admin_groups: Vec<String>,
+
+    /// Proxy groups whose members get the operator role
+    #[serde(default)]
+    pub proxy_auth_operator_groups: Vec<String>,
+
+    /// Proxy groups whose members get the viewer role
+    #[serde(default)]
+    pub proxy_auth_viewer_groups: Vec<String>,
+
+    /// Role for proxy users who are neither local accounts nor in a group above
+    /// (default: unset, they are refused)
+    #[serde(default)]
+    pub proxy_auth_default_role: Option<Role>,
+
+    /// Where logging out sends the browser so the proxy ends its session too, e.g.
+    /// `/oauth2/sign_out`
+    #[serde(default)]
+    pub proxy_auth_logout_url: Option<String>,
+
+    /// Users with this role or a higher one must set up two-factor authentication before
+    /// they can sign in with a password (default: unset, two-factor is optional)
+    #[serde(default)]
+    pub totp_required_role: Option<Role>,
+
+    /// File two-factor enrollments are persisted to (default: under /data when mounted)
+    #[serde(default)]
+    pub totp_file: Option<String>,
+
+    /// Failed sign-ins from one address or for one username before logins are locked out
+    #[serde(default = "default_login_max_failures")]
+    pub login_max_failures: u32,
+
+    /// First lockout in seconds; each further failure doubles it
+    #[serde(default = "default_login_lockout")]
+    pub login_lockout_seconds: u64,
+
+    /// Longest lockout in seconds
+    #[serde(default = "default_login_max_lockout")]
+    pub login_max_lockout_seconds: u64,
+
+    /// Reverse proxies (addresses or CIDR ranges) whose `X-Forwarded-For` header, and
+    /// authentication headers when `proxy_auth_user_header` is set, are trusted
+    #[serde(default, deserialize_with = "login_throttle::deserialize_networks")]
+    pub trusted_proxies: Vec<IpNetwork>,
+
+    /// Bearer token that lets Prometheus scrape `/api/prometheus` without a session
+    #[serde(default)]
+    pub prometheus_token: Option<String>,
+
+    /// File issued API tokens are persisted to (default: under /data when mounted)
+    #[serde(default)]
+    pub tokens_file: Option<String>,
+
+    /// File the event timeline is persisted to (default: under /data when mounted)
+    #[serde(default)]
+    pub events_file: Option<String>,
+
+    /// Maximum number of timeline entries to keep
+    #[serde(default = "default_events_retention")]
+    pub events_retention: usize,
 }
 
 impl Config {
@@ -69,6 +228,7 @@ impl Config {
 
         // Use envy to deserialize from environment variables with SDM_ prefix
         let mut config = envy::prefixed("SDM_").from_env::<Config>()?;
+        config.validate_tls()?;
 
         // Handle authentication setup
         if config.auth_enabled {
@@ -88,6 +248,15 @@ impl Config {
 
     /// Set up authentication by handling password and hashing
     fn setup_authentication(&mut self) -> Result<(), String> {
+        if let Some(users_file) = self.users_file.clone().filter(|f| !f.is_empty()) {
+            self.users = Self::load_users_file(&users_file)?;
+            tracing::info!("🔐 ================================");
+            tracing::info!("🔐 AUTHENTICATION ENABLED");
+            tracing::info!("🔐 Loaded {} user(s) from {}", self.users.len(), users_file);
+            tracing::info!("🔐 ================================");
+            return Ok(());
+        }
+
         match (&self.auth_password, &self.auth_password_hash) {
             (Some(password), None) => {
                 // Hash the provided password
@@ -150,6 +319,18 @@ impl Config {
         Ok(())
     }
 
+    /// Load the accounts from a users file, refusing to start with an empty or invalid one
+    fn load_users_file(path: &str) -> Result<Vec<UserAccount>, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read users file {}: {}", path, e))?;
+        let users = auth::parse_users(&contents)
+            .map_err(|e| format!("Invalid users file {}: {}", path, e))?;
+        if users.is_empty() {
+            return Err(format!("Users file {} does not define any users", path));
+        }
+        Ok(users)
+    }
+
     /// Load password from the password file
     fn load_password_from_file() -> Option<String> {
         let password_file = Self::get_password_file_path();
@@ -242,7 +423,7 @@ impl Config {
     }
 
     /// Hash a password using Argon2
-    fn hash_password(password: &str) -> Result<String, String> {
+    pub fn hash_password(password: &str) -> Result<String, String> {
         let salt = SaltString::generate(&mut OsRng);
         let argon2 = Argon2::default();
         let password_hash = argon2
@@ -254,21 +435,110 @@ impl Config {
     /// Verify a password against the stored hash
     pub fn verify_password(&self, password: &str) -> Result<bool, String> {
         if let Some(hash) = &self.auth_password_hash {
-            let parsed_hash = PasswordHash::new(hash)
-                .map_err(|e| format!("Failed to parse password hash: {}", e))?;
-            let argon2 = Argon2::default();
-            Ok(argon2
-                .verify_password(password.as_bytes(), &parsed_hash)
-                .is_ok())
+            Self::verify_hash(hash, password)
         } else {
             Ok(false)
         }
     }
 
+    fn verify_hash(hash: &str, password: &str) -> Result<bool, String> {
+        let parsed_hash =
+            PasswordHash::new(hash).map_err(|e| format!("Failed to parse password hash: {}", e))?;
+        let argon2 = Argon2::default();
+        Ok(argon2
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok())
+    }
+
+    /// Check a login against the users file, or the single admin account when there is
+    /// none, returning the user's role on success
+    pub fn authenticate(&self, username: &str, password: &str) -> Option<Role> {
+        if self.users.is_empty() {
+            return (username == self.auth_username && self.verify_password(password) == Ok(true))
+                .then_some(Role::Admin);
+        }
+
+        let user = self.users.iter().find(|user| user.username == username)?;
+        match Self::verify_hash(&user.password_hash, password) {
+            Ok(true) => Some(user.role),
+            _ => None,
+        }
+    }
+
+    /// Role of a user that may sign in, e.g. the owner of an API token
+    pub fn role_of(&self, username: &str) -> Option<Role> {
+        if self.users.is_empty() {
+            return (username == self.auth_username).then_some(Role::Admin);
+        }
+        self.users
+            .iter()
+            .find(|user| user.username == username)
+            .map(|user| user.role)
+    }
+
+    /// Path of the event timeline file, preferring the data volume used by the container image
+    pub fn events_file_path(&self) -> String {
+        Self::data_file_path(self.events_file.as_deref(), "sdm_events.jsonl")
+    }
+
+    /// Path of the API token file, preferring the data volume used by the container image
+    pub fn tokens_file_path(&self) -> String {
+        Self::data_file_path(self.tokens_file.as_deref(), "sdm_tokens.json")
+    }
+
+    /// Path of the two-factor enrollment file, preferring the data volume used by the
+    /// container image
+    pub fn totp_file_path(&self) -> String {
+        Self::data_file_path(self.totp_file.as_deref(), "sdm_totp.json")
+    }
+
+    /// Whether users with this role must use two-factor authentication
+    pub fn totp_required_for(&self, role: Role) -> bool {
+        self.totp_required_role
+            .is_some_and(|required| role >= required)
+    }
+
+    fn data_file_path(configured: Option<&str>, file_name: &str) -> String {
+        if let Some(path) = configured.filter(|p| !p.is_empty()) {
+            return path.to_string();
+        }
+        if Path::new("/data").is_dir() {
+            format!("/data/{}", file_name)
+        } else {
+            file_name.to_string()
+        }
+    }
+
     /// Get the full bind address
     pub fn bind_address(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
+
+    /// The certificate and key files, when HTTPS is configured
+    pub fn tls_files(&self) -> Option<(&str, &str)> {
+        let cert = self.tls_cert_file.as_deref().filter(|f| !f.is_empty())?;
+        let key = self.tls_key_file.as_deref().filter(|f| !f.is_empty())?;
+        Some((cert, key))
+    }
+
+    /// Whether the server speaks HTTPS, so cookies can be marked `Secure`
+    pub fn tls_enabled(&self) -> bool {
+        self.tls_files().is_some()
+    }
+
+    /// Refuse half-configured HTTPS rather than quietly serving plain HTTP
+    fn validate_tls(&self) -> Result<(), String> {
+        let is_set = |file: &Option<String>| file.as_deref().is_some_and(|f| !f.is_empty());
+        if is_set(&self.tls_cert_file) != is_set(&self.tls_key_file) {
+            return Err("SDM_TLS_CERT_FILE and SDM_TLS_KEY_FILE must be set together".to_string());
+        }
+        if is_set(&self.tls_client_ca_file) && !self.tls_enabled() {
+            return Err(
+                "SDM_TLS_CLIENT_CA_FILE needs SDM_TLS_CERT_FILE and SDM_TLS_KEY_FILE".to_string(),
+            );
+        }
+        Ok(())
+    }
 }
 
 // Default values following 12-Factor principles
@@ -296,6 +566,14 @@ fn default_max_chart_containers() -> usize {
     5
 }
 
+fn default_secret_env_patterns() -> Vec<String> {
+    vec![
+        "*PASSWORD*".to_string(),
+        "*TOKEN*".to_string(),
+        "*SECRET*".to_string(),
+    ]
+}
+
 fn default_shutdown_timeout() -> u64 {
     30
 }
@@ -312,29 +590,219 @@ fn default_session_timeout() -> u64 {
     3600
 }
 
+fn default_session_max_lifetime() -> u64 {
+    86400
+}
+
+fn default_oidc_scopes() -> String {
+    "openid profile email".to_string()
+}
+
+fn default_oidc_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_oidc_groups_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_oidc_provider_name() -> String {
+    "single sign-on".to_string()
+}
+
+fn default_login_max_failures() -> u32 {
+    5
+}
+
+fn default_login_lockout() -> u64 {
+    30
+}
+
+fn default_login_max_lockout() -> u64 {
+    900
+}
+
+fn default_events_retention() -> usize {
+    10000
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             host: default_host(),
             port: default_port(),
+            tls_cert_file: None,
+            tls_key_file: None,
+            tls_client_ca_file: None,
             log_level: default_log_level(),
             docker_socket: None,
             metrics_interval_seconds: default_metrics_interval(),
             metrics_history_limit: default_metrics_history(),
             max_chart_containers: default_max_chart_containers(),
+            secret_env_patterns: default_secret_env_patterns(),
             shutdown_timeout_seconds: default_shutdown_timeout(),
             auth_enabled: default_auth_enabled(),
             auth_username: default_auth_username(),
             auth_password: None,
             auth_password_hash: None,
+            users_file: None,
+            users: Vec::new(),
             session_timeout_seconds: default_session_timeout(),
+            session_max_lifetime_seconds: default_session_max_lifetime(),
+            sessions_file: None,
+            oidc_issuer: None,
+            oidc_client_id: None,
+            oidc_client_secret: None,
+            oidc_redirect_url: None,
+            oidc_scopes: default_oidc_scopes(),
+            oidc_username_claim: default_oidc_username_claim(),
+            oidc_groups_claim: default_oidc_groups_claim(),
+            oidc_admin_groups: Vec::new(),
+            oidc_operator_groups: Vec::new(),
+            oidc_viewer_groups: Vec::new(),
+            oidc_default_role: None,
+            oidc_provider_name: default_oidc_provider_name(),
+            proxy_auth_user_header: None,
+            proxy_auth_groups_header: None,
+            proxy_auth_admin_groups: Vec::new(),
+            proxy_auth_operator_groups: Vec::new(),
+            proxy_auth_viewer_groups: Vec::new(),
+            proxy_auth_default_role: None,
+            proxy_auth_logout_url: None,
+            totp_required_role: None,
+            totp_file: None,
+            login_max_failures: default_login_max_failures(),
+            login_lockout_seconds: default_login_lockout(),
+            login_max_lockout_seconds: default_login_max_lockout(),
+            trusted_proxies: Vec::new(),
+            prometheus_token: None,
+            tokens_file: None,
+            events_file: None,
+            events_retention: default_events_retention(),
         }
     }
 }
 
+/// Secrets are redacted, as the configuration is logged at startup. Destructuring makes
+/// a new field fail to compile until it is added here.
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Config {
+            host,
+            port,
+            tls_cert_file,
+            tls_key_file,
+            tls_client_ca_file,
+            log_level,
+            docker_socket,
+            metrics_interval_seconds,
+            metrics_history_limit,
+            max_chart_containers,
+            secret_env_patterns,
+            shutdown_timeout_seconds,
+            auth_enabled,
+            auth_username,
+            auth_password,
+            auth_password_hash,
+            users_file,
+            users,
+            session_timeout_seconds,
+            session_max_lifetime_seconds,
+            sessions_file,
+            oidc_issuer,
+            oidc_client_id,
+            oidc_client_secret,
+            oidc_redirect_url,
+            oidc_scopes,
+            oidc_username_claim,
+            oidc_groups_claim,
+            oidc_admin_groups,
+            oidc_operator_groups,
+            oidc_viewer_groups,
+            oidc_default_role,
+            oidc_provider_name,
+            proxy_auth_user_header,
+            proxy_auth_groups_header,
+            proxy_auth_admin_groups,
+            proxy_auth_operator_groups,
+            proxy_auth_viewer_groups,
+            proxy_auth_default_role,
+            proxy_auth_logout_url,
+            totp_required_role,
+            totp_file,
+            login_max_failures,
+            login_lockout_seconds,
+            login_max_lockout_seconds,
+            trusted_proxies,
+            prometheus_token,
+            tokens_file,
+            events_file,
+            events_retention,
+        } = self;
+        f.debug_struct("Config")
+            .field("host", host)
+            .field("port", port)
+            .field("tls_cert_file", tls_cert_file)
+            .field("tls_key_file", tls_key_file)
+            .field("tls_client_ca_file", tls_client_ca_file)
+            .field("log_level", log_level)
+            .field("docker_socket", docker_socket)
+            .field("metrics_interval_seconds", metrics_interval_seconds)
+            .field("metrics_history_limit", metrics_history_limit)
+            .field("max_chart_containers", max_chart_containers)
+            .field("secret_env_patterns", secret_env_patterns)
+            .field("shutdown_timeout_seconds", shutdown_timeout_seconds)
+            .field("auth_enabled", auth_enabled)
+            .field("auth_username", auth_username)
+            .field("auth_password", &redacted(auth_password))
+            .field("auth_password_hash", &redacted(auth_password_hash))
+            .field("users_file", users_file)
+            .field("users", users)
+            .field("session_timeout_seconds", session_timeout_seconds)
+            .field("session_max_lifetime_seconds", session_max_lifetime_seconds)
+            .field("sessions_file", sessions_file)
+            .field("oidc_issuer", oidc_issuer)
+            .field("oidc_client_id", oidc_client_id)
+            .field("oidc_client_secret", &redacted(oidc_client_secret))
+            .field("oidc_redirect_url", oidc_redirect_url)
+            .field("oidc_scopes", oidc_scopes)
+            .field("oidc_username_claim", oidc_username_claim)
+            .field("oidc_groups_claim", oidc_groups_claim)
+            .field("oidc_admin_groups", oidc_admin_groups)
+            .field("oidc_operator_groups", oidc_operator_groups)
+            .field("oidc_viewer_groups", oidc_viewer_groups)
+            .field("oidc_default_role", oidc_default_role)
+            .field("oidc_provider_name", oidc_provider_name)
+            .field("proxy_auth_user_header", proxy_auth_user_header)
+            .field("proxy_auth_groups_header", proxy_auth_groups_header)
+            .field("proxy_auth_admin_groups", proxy_auth_admin_groups)
+            .field("proxy_auth_operator_groups", proxy_auth_operator_groups)
+            .field("proxy_auth_viewer_groups", proxy_auth_viewer_groups)
+            .field("proxy_auth_default_role", proxy_auth_default_role)
+            .field("proxy_auth_logout_url", proxy_auth_logout_url)
+            .field("totp_required_role", totp_required_role)
+            .field("totp_file", totp_file)
+            .field("login_max_failures", login_max_failures)
+            .field("login_lockout_seconds", login_lockout_seconds)
+            .field("login_max_lockout_seconds", login_max_lockout_seconds)
+            .field("trusted_proxies", trusted_proxies)
+            .field("prometheus_token", &redacted(prometheus_token))
+            .field("tokens_file", tokens_file)
+            .field("events_file", events_file)
+            .field("events_retention", events_retention)
+            .finish()
+    }
+}
+
+/// Shown in place of a secret that is set
+fn redacted(secret: &Option<String>) -> Option<&'static str> {
+    secret.as_ref().map(|_| "<redacted>")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::secrets::SecretMasker;
 
     #[test]
     fn test_config_defaults() {
@@ -349,6 +817,172 @@ mod tests {
         assert!(config.auth_enabled);
         assert_eq!(config.auth_username, "admin");
         assert_eq!(config.session_timeout_seconds, 3600);
+        assert_eq!(config.session_max_lifetime_seconds, 86400);
+        assert_eq!(config.login_max_failures, 5);
+        assert!(config.trusted_proxies.is_empty());
+        assert_eq!(config.events_retention, 10000);
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let config = Config {
+            auth_password: Some("admin-pw".to_string()),
+            prometheus_token: Some("scrape-token".to_string()),
+            oidc_client_secret: Some("client-secret".to_string()),
+            auth_password_hash: Some(Config::hash_password("admin-pw").unwrap()),
+            users: auth::parse_users(&format!(
+                "alice:operator:{}",
+                Config::hash_password("alice-pw").unwrap()
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+        let debug = format!("{:#?}", config);
+        assert!(!debug.contains("$argon2"));
+        assert!(debug.contains("alice:operator"));
+        assert!(!debug.contains("admin-pw"));
+        assert!(!debug.contains("scrape-token"));
+        assert!(!debug.contains("client-secret"));
+        assert!(debug.contains("<redacted>"));
+        assert!(debug.contains("auth_username"));
+    }
+
+    #[test]
+    fn test_events_file_path_override() {
+        let config = Config {
+            events_file: Some("/tmp/timeline.jsonl".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.events_file_path(), "/tmp/timeline.jsonl");
+        assert!(Config::default()
+            .events_file_path()
+            .ends_with("sdm_events.jsonl"));
+        assert!(Config::default()
+            .tokens_file_path()
+            .ends_with("sdm_tokens.json"));
+        assert!(Config::default()
+            .totp_file_path()
+            .ends_with("sdm_totp.json"));
+    }
+
+    #[test]
+    fn test_totp_required_role() {
+        let config: Config = envy::prefixed("SDM_")
+            .from_iter([("SDM_TOTP_REQUIRED_ROLE".to_string(), "operator".to_string())])
+            .unwrap();
+        assert!(!config.totp_required_for(Role::Viewer));
+        assert!(config.totp_required_for(Role::Operator));
+        assert!(config.totp_required_for(Role::Admin));
+        assert!(!Config::default().totp_required_for(Role::Admin));
+    }
+
+    #[test]
+    fn test_trusted_proxies_from_env() {
+        let config: Config = envy::prefixed("SDM_")
+            .from_iter([(
+                "SDM_TRUSTED_PROXIES".to_string(),
+                "127.0.0.1, 10.0.0.0/8".to_string(),
+            )])
+            .unwrap();
+        assert_eq!(config.trusted_proxies.len(), 2);
+        assert!(config.trusted_proxies[1].contains("10.20.30.40".parse().unwrap()));
+
+        let invalid = envy::prefixed("SDM_").from_iter::<_, Config>([(
+            "SDM_TRUSTED_PROXIES".to_string(),
+            "proxy.local".to_string(),
+        )]);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_oidc_settings_from_env() {
+        let config: Config = envy::prefixed("SDM_")
+            .from_iter([
+                (
+                    "SDM_OIDC_ISSUER".to_string(),
+                    "https://idp.example.com".to_string(),
+                ),
+                (
+                    "SDM_OIDC_ADMIN_GROUPS".to_string(),
+                    "platform,sre".to_string(),
+                ),
+                ("SDM_OIDC_DEFAULT_ROLE".to_string(), "viewer".to_string()),
+            ])
+            .unwrap();
+        assert_eq!(config.oidc_admin_groups, vec!["platform", "sre"]);
+        assert_eq!(config.oidc_default_role, Some(Role::Viewer));
+        assert_eq!(config.oidc_username_claim, "preferred_username");
+    }
+
+    #[test]
+    fn test_proxy_auth_settings_from_env() {
+        let config: Config = envy::prefixed("SDM_")
+            .from_iter([
+                (
+                    "SDM_PROXY_AUTH_USER_HEADER".to_string(),
+                    "X-Forwarded-User".to_string(),
+                ),
+                (
+                    "SDM_PROXY_AUTH_OPERATOR_GROUPS".to_string(),
+                    "ops,oncall".to_string(),
+                ),
+                (
+                    "SDM_PROXY_AUTH_DEFAULT_ROLE".to_string(),
+                    "viewer".to_string(),
+                ),
+            ])
+            .unwrap();
+        assert_eq!(
+            config.proxy_auth_user_header.as_deref(),
+            Some("X-Forwarded-User")
+        );
+        assert_eq!(config.proxy_auth_operator_groups, vec!["ops", "oncall"]);
+        assert!(config.proxy_auth_admin_groups.is_empty());
+        assert_eq!(config.proxy_auth_default_role, Some(Role::Viewer));
+    }
+
+    #[test]
+    fn test_secret_env_patterns_from_env() {
+        assert_eq!(Config::default().secret_env_patterns.len(), 3);
+        let config: Config = envy::prefixed("SDM_")
+            .from_iter([(
+                "SDM_SECRET_ENV_PATTERNS".to_string(),
+                "*_KEY,AWS_*".to_string(),
+            )])
+            .unwrap();
+        assert_eq!(config.secret_env_patterns, vec!["*_KEY", "AWS_*"]);
+
+        let disabled: Config = envy::prefixed("SDM_")
+            .from_iter([("SDM_SECRET_ENV_PATTERNS".to_string(), String::new())])
+            .unwrap();
+        assert!(!SecretMasker::from_config(&disabled).is_secret("DB_PASSWORD"));
+    }
+
+    #[test]
+    fn test_tls_files_must_be_set_together() {
+        let config = Config {
+            tls_cert_file: Some("/certs/cert.pem".to_string()),
+            tls_key_file: Some("/certs/key.pem".to_string()),
+            ..Config::default()
+        };
+        assert!(config.validate_tls().is_ok());
+        assert_eq!(
+            config.tls_files(),
+            Some(("/certs/cert.pem", "/certs/key.pem"))
+        );
+        assert!(!Config::default().tls_enabled());
+        assert!(Config::default().validate_tls().is_ok());
+
+        let without_key = Config {
+            tls_key_file: None,
+            ..config.clone()
+        };
+        assert!(without_key.validate_tls().is_err());
+        let client_ca_only = Config {
+            tls_client_ca_file: Some("/certs/ca.pem".to_string()),
+            ..Config::default()
+        };
+        assert!(client_ca_only.validate_tls().is_err());
     }
 
     #[test]
@@ -391,6 +1025,38 @@ mod tests {
         assert!(!config.verify_password("wrong_password").unwrap());
     }
 
+    #[test]
+    fn test_authenticate_with_users_file() {
+        let contents = format!(
+            "# viewers only see dashboards\nalice:viewer:{}\nbob:operator:{}\n",
+            Config::hash_password("alice-pw").unwrap(),
+            Config::hash_password("bob-pw").unwrap()
+        );
+        let config = Config {
+            users: auth::parse_users(&contents).unwrap(),
+            auth_password_hash: Some(Config::hash_password("admin-pw").unwrap()),
+            ..Config::default()
+        };
+
+        assert_eq!(config.authenticate("alice", "alice-pw"), Some(Role::Viewer));
+        assert_eq!(config.authenticate("bob", "bob-pw"), Some(Role::Operator));
+        assert_eq!(config.authenticate("bob", "alice-pw"), None);
+        // The single admin account is replaced by the users file
+        assert_eq!(config.authenticate("admin", "admin-pw"), None);
+    }
+
+    #[test]
+    fn test_authenticate_single_admin() {
+        let config = Config {
+            auth_password_hash: Some(Config::hash_password("admin-pw").unwrap()),
+            ..Config::default()
+        };
+
+        assert_eq!(config.authenticate("admin", "admin-pw"), Some(Role::Admin));
+        assert_eq!(config.authenticate("admin", "wrong"), None);
+        assert_eq!(config.authenticate("other", "admin-pw"), None);
+    }
+
     #[test]
     fn test_password_verification_without_hash() {
         let config = Config::default();
@@ -442,5 +1108,6 @@ mod tests {
         assert!(default_auth_enabled());
         assert_eq!(default_auth_username(), "admin");
         assert_eq!(default_session_timeout(), 3600);
+        assert_eq!(default_events_retention(), 10000);
     }
 }
@@ -1,26 +1,56 @@
 use super::models::{
-    ContainerMetrics, ContainerPortMapping, ContainerSummary, CreateContainerRequest,
-    EnvironmentVariable, ImageInfo, LocalImageSummary, MetricsResponse, PortMapping, SystemMetrics,
+    ContainerEnvironmentVariable, ContainerMetrics, ContainerMount, ContainerMountType,
+    ContainerPortMapping, ContainerRemovalOptions, ContainerStateSummary, ContainerSummary,
+    CreateContainerRequest, CreateNetworkRequest, CreateVolumeRequest, DockerEvent, ImageInfo,
+    ImagePrunePreview, ImagePruneResult, ImageRemovalOptions, ImageRemovalResult,
+    LocalImageSummary, NetworkAttachment, NetworkConnectRequest, NetworkSummary, PortMapping,
+    PullImageRequest, PullProgress, SystemMetrics, VolumePruneResult, VolumeSummary, VolumeUsage,
 };
+use bollard::auth::DockerCredentials;
 use bollard::container::{
-    Config, CreateContainerOptions, ListContainersOptions, LogsOptions, RestartContainerOptions,
-    StartContainerOptions, StatsOptions, StopContainerOptions,
+    Config, CreateContainerOptions, ListContainersOptions, LogsOptions, RemoveContainerOptions,
+    RestartContainerOptions, StartContainerOptions, StatsOptions, StopContainerOptions,
+};
+use bollard::exec::{CreateExecOptions, ResizeExecOptions, StartExecOptions, StartExecResults};
+use bollard::image::{
+    CreateImageOptions, ListImagesOptions, PruneImagesOptions, RemoveImageOptions,
+};
+use bollard::models::{
+    EventMessageTypeEnum, ImageSummary, Mount, MountTmpfsOptions, MountTypeEnum,
+};
+use bollard::network::{
+    ConnectNetworkOptions, CreateNetworkOptions, DisconnectNetworkOptions, InspectNetworkOptions,
+    ListNetworksOptions,
+};
+use bollard::system::EventsOptions;
+use bollard::volume::{
+    CreateVolumeOptions, ListVolumesOptions, PruneVolumesOptions, RemoveVolumeOptions,
 };
-use bollard::image::ListImagesOptions;
 use bollard::Docker;
-use chrono::Utc;
+use chrono::{DateTime, Datelike, Utc};
 use futures_util::stream::StreamExt;
 use std::collections::HashMap;
 use std::default::Default;
+use std::pin::Pin;
 
-/// Get a Docker client with optional custom socket configuration
-fn get_docker_client(socket_path: Option<&str>) -> Result<Docker, bollard::errors::Error> {
+/// Create the Docker client, optionally for a custom socket. The connection is made lazily,
+/// so one client is created at startup and shared by every request.
+pub fn connect(socket_path: Option<&str>) -> Result<Docker, bollard::errors::Error> {
     match socket_path {
         Some(path) => Docker::connect_with_socket(path, 120, bollard::API_DEFAULT_VERSION),
         None => Docker::connect_with_local_defaults(),
     }
 }
 
+/// Human-readable message for a Docker error, including the detail carried by stream errors
+pub fn error_message(error: &bollard::errors::Error) -> String {
+    match error {
+        bollard::errors::Error::DockerStreamError { error } => error.clone(),
+        bollard::errors::Error::DockerResponseServerError { message, .. } => message.clone(),
+        other => other.to_string(),
+    }
+}
+
 fn is_image_id(image_name: &str) -> bool {
     // Simple check: a common image ID is a 64-character hex string, or prefixed with sha256:
     (image_name.len() == 64 && image_name.chars().all(|c| c.is_ascii_hexdigit()))
@@ -28,15 +58,13 @@ fn is_image_id(image_name: &str) -> bool {
         || image_name.contains("<none>")
 }
 
-// Returns only RUNNING containers
-pub async fn list_running_containers() -> Result<Vec<ContainerSummary>, bollard::errors::Error> {
-    list_running_containers_with_config(None).await
-}
+/// How many containers are inspected at once when listing them
+const INSPECT_CONCURRENCY: usize = 8;
 
-pub async fn list_running_containers_with_config(
-    socket_path: Option<&str>,
+// Returns only RUNNING containers
+pub async fn list_running_containers(
+    docker: &Docker,
 ) -> Result<Vec<ContainerSummary>, bollard::errors::Error> {
-    let docker = get_docker_client(socket_path)?;
     let options = Some(ListContainersOptions::<String> {
         all: false, // Only running
         filters: std::collections::HashMap::from([(
@@ -46,35 +74,36 @@ pub async fn list_running_containers_with_config(
         ..Default::default()
     });
 
-    let containers = docker.list_containers(options).await?;
-    let mut detailed_containers = Vec::new();
-
-    for container in containers {
-        let image = container.image.clone().unwrap_or_default();
-        if is_image_id(&image) {
-            continue;
-        }
-
-        let container_id = container.id.clone().unwrap_or_default();
-        let container_name = container
-            .names
-            .unwrap_or_default()
-            .first()
-            .unwrap_or(&"".to_string())
-            .trim_start_matches('/')
-            .to_string();
-
-        // Get detailed information for each container
-        match docker.inspect_container(&container_id, None).await {
-            Ok(inspect_result) => {
-                // Extract ports
-                let mut ports = Vec::new();
-                if let Some(network_settings) = &inspect_result.network_settings {
-                    if let Some(port_map) = &network_settings.ports {
-                        for (port_key, port_bindings) in port_map {
-                            if let Some(bindings) = port_bindings {
-                                let (container_port, protocol) =
-                                    if let Some(slash_pos) = port_key.find('/') {
+    let containers = docker
+        .list_containers(options)
+        .await?
+        .into_iter()
+        .filter(|container| !is_image_id(container.image.as_deref().unwrap_or_default()));
+
+    let detailed_containers = futures_util::stream::iter(containers)
+        .map(|container| async move {
+            let image = container.image.clone().unwrap_or_default();
+            let container_id = container.id.clone().unwrap_or_default();
+            let container_name = container
+                .names
+                .unwrap_or_default()
+                .first()
+                .unwrap_or(&"".to_string())
+                .trim_start_matches('/')
+                .to_string();
+
+            // Get detailed information for each container
+            match docker.inspect_container(&container_id, None).await {
+                Ok(inspect_result) => {
+                    // Extract ports
+                    let mut ports = Vec::new();
+                    if let Some(network_settings) = &inspect_result.network_settings {
+                        if let Some(port_map) = &network_settings.ports {
+                            for (port_key, port_bindings) in port_map {
+                                if let Some(bindings) = port_bindings {
+                                    let (container_port, protocol) = if let Some(slash_pos) =
+                                        port_key.find('/')
+                                    {
                                         let port_str = &port_key[..slash_pos];
                                         let protocol_str = &port_key[slash_pos + 1..];
                                         (
@@ -85,105 +114,673 @@ pub async fn list_running_containers_with_config(
                                         (port_key.parse::<u16>().unwrap_or(0), "tcp".to_string())
                                     };
 
-                                for binding in bindings {
-                                    let host_port = binding
-                                        .host_port
-                                        .as_ref()
-                                        .and_then(|p| p.parse::<u16>().ok());
-
-                                    ports.push(PortMapping {
-                                        container_port,
-                                        host_port,
-                                        protocol: protocol.clone(),
-                                    });
+                                    for binding in bindings {
+                                        let host_port = binding
+                                            .host_port
+                                            .as_ref()
+                                            .and_then(|p| p.parse::<u16>().ok());
+
+                                        ports.push(PortMapping {
+                                            container_port,
+                                            host_port,
+                                            protocol: protocol.clone(),
+                                        });
+                                    }
                                 }
                             }
                         }
                     }
+
+                    // Sort ports for consistent display order
+                    // Sort by container port first, then by protocol
+                    ports.sort_by(|a, b| {
+                        a.container_port
+                            .cmp(&b.container_port)
+                            .then_with(|| a.protocol.cmp(&b.protocol))
+                    });
+
+                    // Extract environment variables
+                    let mut environment = if let Some(config) = &inspect_result.config {
+                        config.env.clone().unwrap_or_default()
+                    } else {
+                        Vec::new()
+                    };
+
+                    // Sort environment variables for consistent display order
+                    environment.sort();
+
+                    ContainerSummary {
+                        id: container_id,
+                        name: container_name,
+                        image,
+                        status: container.state.unwrap_or_default(),
+                        ports,
+                        environment,
+                    }
                 }
+                Err(_) => {
+                    // Fallback to basic info if inspection fails
+                    ContainerSummary {
+                        id: container_id,
+                        name: container_name,
+                        image,
+                        status: container.state.unwrap_or_default(),
+                        ports: Vec::new(),
+                        environment: Vec::new(),
+                    }
+                }
+            }
+        })
+        .buffered(INSPECT_CONCURRENCY)
+        .collect()
+        .await;
 
-                // Sort ports for consistent display order
-                // Sort by container port first, then by protocol
-                ports.sort_by(|a, b| {
-                    a.container_port
-                        .cmp(&b.container_port)
-                        .then_with(|| a.protocol.cmp(&b.protocol))
-                });
+    Ok(detailed_containers)
+}
 
-                // Extract environment variables
-                let mut environment = if let Some(config) = &inspect_result.config {
-                    config.env.clone().unwrap_or_default()
-                } else {
-                    Vec::new()
-                };
+/// Parse a Docker RFC 3339 timestamp, treating Docker's zero value ("0001-01-01T00:00:00Z") as unset
+fn parse_docker_timestamp(timestamp: Option<&str>) -> Option<DateTime<Utc>> {
+    let parsed = DateTime::parse_from_rfc3339(timestamp?).ok()?;
+    if parsed.year() <= 1 {
+        None
+    } else {
+        Some(parsed.with_timezone(&Utc))
+    }
+}
 
-                // Sort environment variables for consistent display order
-                environment.sort();
+// Returns ALL containers (running, exited, created, paused, ...) with lifecycle details
+pub async fn list_all_containers(
+    docker: &Docker,
+) -> Result<Vec<ContainerStateSummary>, bollard::errors::Error> {
+    let options = Some(ListContainersOptions::<String> {
+        all: true,
+        ..Default::default()
+    });
 
-                detailed_containers.push(ContainerSummary {
-                    id: container_id,
-                    name: container_name,
-                    image,
-                    status: container.state.unwrap_or_default(),
-                    ports,
-                    environment,
-                });
-            }
-            Err(_) => {
-                // Fallback to basic info if inspection fails
-                detailed_containers.push(ContainerSummary {
-                    id: container_id,
-                    name: container_name,
-                    image,
-                    status: container.state.unwrap_or_default(),
-                    ports: Vec::new(),
-                    environment: Vec::new(),
-                });
+    let containers = docker.list_containers(options).await?;
+    let mut summaries: Vec<ContainerStateSummary> = futures_util::stream::iter(containers)
+        .map(|container| async move {
+            let container_id = container.id.clone().unwrap_or_default();
+            let container_name = container
+                .names
+                .unwrap_or_default()
+                .first()
+                .unwrap_or(&"".to_string())
+                .trim_start_matches('/')
+                .to_string();
+
+            let created_at = container
+                .created
+                .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0));
+
+            // Exit code and start/finish times are only available via inspect, so containers
+            // are inspected a few at a time rather than one after another
+            let (exit_code, started_at, finished_at) =
+                match docker.inspect_container(&container_id, None).await {
+                    Ok(inspect_result) => match inspect_result.state {
+                        Some(state) => (
+                            state.exit_code,
+                            parse_docker_timestamp(state.started_at.as_deref()),
+                            parse_docker_timestamp(state.finished_at.as_deref()),
+                        ),
+                        None => (None, None, None),
+                    },
+                    Err(_) => (None, None, None),
+                };
+
+            ContainerStateSummary {
+                id: container_id,
+                name: container_name,
+                image: container.image.unwrap_or_default(),
+                state: container.state.unwrap_or_default(),
+                status: container.status.unwrap_or_default(),
+                exit_code,
+                created_at,
+                started_at,
+                finished_at,
             }
-        }
-    }
+        })
+        .buffered(INSPECT_CONCURRENCY)
+        .collect()
+        .await;
 
-    Ok(detailed_containers)
-}
+    // Sort by name for consistent display order
+    summaries.sort_by(|a, b| a.name.cmp(&b.name));
 
-#[allow(dead_code)]
-pub async fn list_downloaded_images() -> Result<Vec<LocalImageSummary>, bollard::errors::Error> {
-    list_downloaded_images_with_config(None).await
+    Ok(summaries)
 }
 
-pub async fn list_downloaded_images_with_config(
-    socket_path: Option<&str>,
+pub async fn list_downloaded_images(
+    docker: &Docker,
 ) -> Result<Vec<LocalImageSummary>, bollard::errors::Error> {
-    let docker = get_docker_client(socket_path)?;
     let options = Some(ListImagesOptions::<String> {
         all: false, // Set to true if you want intermediate layers too
         digests: false,
-        filters: std::collections::HashMap::new(), // Add filters if needed, e.g. dangling=false
+        filters: std::collections::HashMap::new(), // Dangling images too, so they can be removed
     });
 
     let images = docker.list_images(options).await?;
 
-    Ok(images
+    Ok(images.into_iter().map(local_image_summary).collect())
+}
+
+fn local_image_summary(img: ImageSummary) -> LocalImageSummary {
+    LocalImageSummary {
+        id: img.id,
+        // Dangling images report "<none>:<none>" (or nothing) as their tag
+        repo_tags: img
+            .repo_tags
+            .into_iter()
+            .filter(|tag| !tag.contains("<none>"))
+            .collect(),
+        size_bytes: img.size,
+    }
+}
+
+fn dangling_filter() -> HashMap<String, Vec<String>> {
+    HashMap::from([("dangling".to_string(), vec!["true".to_string()])])
+}
+
+/// Preview what pruning dangling (<none>:<none>) images would delete
+pub async fn preview_dangling_image_prune(
+    docker: &Docker,
+) -> Result<ImagePrunePreview, bollard::errors::Error> {
+    let images = docker
+        .list_images(Some(ListImagesOptions::<String> {
+            all: false,
+            digests: false,
+            filters: dangling_filter(),
+        }))
+        .await?;
+
+    let images: Vec<LocalImageSummary> = images.into_iter().map(local_image_summary).collect();
+
+    let reclaimable_bytes = images.iter().map(|img| img.size_bytes.max(0)).sum();
+
+    Ok(ImagePrunePreview {
+        images,
+        reclaimable_bytes,
+    })
+}
+
+/// Delete all dangling (<none>:<none>) images
+pub async fn prune_dangling_images(
+    docker: &Docker,
+) -> Result<ImagePruneResult, bollard::errors::Error> {
+    let response = docker
+        .prune_images(Some(PruneImagesOptions {
+            filters: dangling_filter(),
+        }))
+        .await?;
+
+    Ok(ImagePruneResult {
+        deleted: response
+            .images_deleted
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|item| item.deleted)
+            .collect(),
+        space_reclaimed_bytes: response.space_reclaimed.unwrap_or(0),
+    })
+}
+
+/// Remove an image by ID or tag
+pub async fn remove_image(
+    docker: &Docker,
+    image: &str,
+    options: &ImageRemovalOptions,
+) -> Result<ImageRemovalResult, bollard::errors::Error> {
+    let items = docker
+        .remove_image(
+            image,
+            Some(RemoveImageOptions {
+                force: options.force,
+                noprune: options.no_prune,
+            }),
+            None,
+        )
+        .await?;
+
+    let mut result = ImageRemovalResult::default();
+    for item in items {
+        if let Some(untagged) = item.untagged {
+            result.untagged.push(untagged);
+        }
+        if let Some(deleted) = item.deleted {
+            result.deleted.push(deleted);
+        }
+    }
+    Ok(result)
+}
+
+/// Remove a single tag from an image. Refuses to remove an image's last tag, since
+/// Docker would delete the image itself; use `remove_image` for that.
+pub async fn untag_image(
+    docker: &Docker,
+    tag: &str,
+) -> Result<ImageRemovalResult, bollard::errors::Error> {
+    let image = docker.inspect_image(tag).await?;
+
+    if image.repo_tags.unwrap_or_default().len() <= 1 {
+        return Err(bollard::errors::Error::DockerResponseServerError {
+            status_code: 409,
+            message: format!(
+                "{} is the image's only tag; remove the image instead of untagging it",
+                tag
+            ),
+        });
+    }
+
+    remove_image(
+        docker,
+        tag,
+        &ImageRemovalOptions {
+            force: false,
+            no_prune: true,
+        },
+    )
+    .await
+}
+
+/// Map each volume name to the containers (in any state) that mount it
+async fn volume_usage_by_name(
+    docker: &Docker,
+) -> Result<HashMap<String, Vec<VolumeUsage>>, bollard::errors::Error> {
+    let containers = docker
+        .list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        }))
+        .await?;
+
+    let mut usage: HashMap<String, Vec<VolumeUsage>> = HashMap::new();
+    for container in containers {
+        let container_name = container
+            .names
+            .as_ref()
+            .and_then(|names| names.first())
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_default();
+
+        for mount in container.mounts.unwrap_or_default() {
+            if mount.typ != Some(bollard::models::MountPointTypeEnum::VOLUME) {
+                continue;
+            }
+            if let Some(volume_name) = mount.name {
+                usage.entry(volume_name).or_default().push(VolumeUsage {
+                    container_id: container.id.clone().unwrap_or_default(),
+                    container_name: container_name.clone(),
+                    container_state: container.state.clone().unwrap_or_default(),
+                    destination: mount.destination.unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    Ok(usage)
+}
+
+fn to_volume_summary(volume: bollard::models::Volume, used_by: Vec<VolumeUsage>) -> VolumeSummary {
+    VolumeSummary {
+        name: volume.name,
+        driver: volume.driver,
+        mountpoint: volume.mountpoint,
+        scope: volume.scope.map(|s| s.to_string()).unwrap_or_default(),
+        created_at: parse_docker_timestamp(volume.created_at.as_deref()),
+        labels: volume.labels,
+        options: volume.options,
+        used_by,
+    }
+}
+
+pub async fn list_volumes(docker: &Docker) -> Result<Vec<VolumeSummary>, bollard::errors::Error> {
+    let response = docker
+        .list_volumes(None::<ListVolumesOptions<String>>)
+        .await?;
+    let mut usage = volume_usage_by_name(docker).await?;
+
+    let mut volumes: Vec<VolumeSummary> = response
+        .volumes
+        .unwrap_or_default()
         .into_iter()
-        .filter_map(|img| {
-            // We only want images with actual tags, not <none>:<none>
-            if img.repo_tags.iter().any(|tag| !tag.contains("<none>")) {
-                Some(LocalImageSummary {
-                    id: img.id,
-                    repo_tags: img.repo_tags,
-                })
-            } else {
-                None
+        .map(|volume| {
+            let used_by = usage.remove(&volume.name).unwrap_or_default();
+            to_volume_summary(volume, used_by)
+        })
+        .collect();
+
+    // Sort by name for consistent display order
+    volumes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(volumes)
+}
+
+pub async fn inspect_volume(
+    docker: &Docker,
+    name: &str,
+) -> Result<VolumeSummary, bollard::errors::Error> {
+    let volume = docker.inspect_volume(name).await?;
+    let used_by = volume_usage_by_name(docker)
+        .await?
+        .remove(name)
+        .unwrap_or_default();
+
+    Ok(to_volume_summary(volume, used_by))
+}
+
+pub async fn create_volume(
+    docker: &Docker,
+    request: &CreateVolumeRequest,
+) -> Result<VolumeSummary, bollard::errors::Error> {
+    let volume = docker
+        .create_volume(CreateVolumeOptions {
+            name: request.name.clone(),
+            driver: request
+                .driver
+                .clone()
+                .filter(|d| !d.trim().is_empty())
+                .unwrap_or_else(|| "local".to_string()),
+            driver_opts: HashMap::new(),
+            labels: request.labels.clone(),
+        })
+        .await?;
+
+    Ok(to_volume_summary(volume, Vec::new()))
+}
+
+/// Remove a volume; `force` ignores volume driver errors (in-use volumes are still refused)
+pub async fn remove_volume(
+    docker: &Docker,
+    name: &str,
+    force: bool,
+) -> Result<(), bollard::errors::Error> {
+    docker
+        .remove_volume(name, Some(RemoveVolumeOptions { force }))
+        .await
+}
+
+/// Delete unused anonymous volumes (named volumes are kept, matching `docker volume prune`)
+pub async fn prune_volumes(docker: &Docker) -> Result<VolumePruneResult, bollard::errors::Error> {
+    let response = docker
+        .prune_volumes(None::<PruneVolumesOptions<String>>)
+        .await?;
+
+    Ok(VolumePruneResult {
+        deleted: response.volumes_deleted.unwrap_or_default(),
+        space_reclaimed_bytes: response.space_reclaimed.unwrap_or(0),
+    })
+}
+
+/// Map network ID to the containers attached to it, built from one container listing
+/// so aliases and container state come along without inspecting every network
+async fn network_attachments_by_id(
+    docker: &Docker,
+) -> Result<HashMap<String, Vec<NetworkAttachment>>, bollard::errors::Error> {
+    let containers = docker
+        .list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        }))
+        .await?;
+
+    let mut attachments: HashMap<String, Vec<NetworkAttachment>> = HashMap::new();
+    for container in containers {
+        let container_name = container
+            .names
+            .as_ref()
+            .and_then(|names| names.first())
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_default();
+        let networks = container
+            .network_settings
+            .and_then(|settings| settings.networks)
+            .unwrap_or_default();
+
+        for endpoint in networks.into_values() {
+            if let Some(network_id) = endpoint.network_id.filter(|id| !id.is_empty()) {
+                attachments
+                    .entry(network_id)
+                    .or_default()
+                    .push(NetworkAttachment {
+                        container_id: container.id.clone().unwrap_or_default(),
+                        container_name: container_name.clone(),
+                        container_state: container.state.clone().unwrap_or_default(),
+                        ip_address: endpoint.ip_address.filter(|ip| !ip.is_empty()),
+                        aliases: endpoint.aliases.unwrap_or_default(),
+                    });
             }
+        }
+    }
+
+    Ok(attachments)
+}
+
+fn to_network_summary(
+    network: bollard::models::Network,
+    containers: Vec<NetworkAttachment>,
+) -> NetworkSummary {
+    let subnets = network
+        .ipam
+        .and_then(|ipam| ipam.config)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|config| config.subnet)
+        .collect();
+
+    NetworkSummary {
+        id: network.id.unwrap_or_default(),
+        name: network.name.unwrap_or_default(),
+        driver: network.driver.unwrap_or_default(),
+        scope: network.scope.unwrap_or_default(),
+        internal: network.internal.unwrap_or(false),
+        attachable: network.attachable.unwrap_or(false),
+        created_at: parse_docker_timestamp(network.created.as_deref()),
+        subnets,
+        labels: network.labels.unwrap_or_default(),
+        containers,
+    }
+}
+
+pub async fn list_networks(docker: &Docker) -> Result<Vec<NetworkSummary>, bollard::errors::Error> {
+    let networks = docker
+        .list_networks(None::<ListNetworksOptions<String>>)
+        .await?;
+    let mut attachments = network_attachments_by_id(docker).await?;
+
+    let mut networks: Vec<NetworkSummary> = networks
+        .into_iter()
+        .map(|network| {
+            let containers = network
+                .id
+                .as_ref()
+                .and_then(|id| attachments.remove(id))
+                .unwrap_or_default();
+            to_network_summary(network, containers)
         })
-        .collect())
+        .collect();
+
+    // Sort by name for consistent display order
+    networks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(networks)
+}
+
+pub async fn inspect_network(
+    docker: &Docker,
+    id_or_name: &str,
+) -> Result<NetworkSummary, bollard::errors::Error> {
+    let network = docker
+        .inspect_network(id_or_name, None::<InspectNetworkOptions<String>>)
+        .await?;
+    let containers = match network.id.as_ref() {
+        Some(id) => network_attachments_by_id(docker)
+            .await?
+            .remove(id)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    Ok(to_network_summary(network, containers))
+}
+
+pub async fn create_network(
+    docker: &Docker,
+    request: &CreateNetworkRequest,
+) -> Result<NetworkSummary, bollard::errors::Error> {
+    let response = docker
+        .create_network(CreateNetworkOptions {
+            name: request.name.clone(),
+            check_duplicate: true,
+            driver: request
+                .driver
+                .clone()
+                .filter(|d| !d.trim().is_empty())
+                .unwrap_or_else(|| "bridge".to_string()),
+            internal: request.internal,
+            attachable: true,
+            labels: request.labels.clone(),
+            ..Default::default()
+        })
+        .await?;
+
+    let id = response.id.unwrap_or_else(|| request.name.clone());
+    let network = docker
+        .inspect_network(&id, None::<InspectNetworkOptions<String>>)
+        .await?;
+
+    Ok(to_network_summary(network, Vec::new()))
+}
+
+pub async fn remove_network(
+    docker: &Docker,
+    id_or_name: &str,
+) -> Result<(), bollard::errors::Error> {
+    docker.remove_network(id_or_name).await
+}
+
+/// Attach a container to a network, optionally registering DNS aliases for it
+pub async fn connect_container_to_network(
+    docker: &Docker,
+    network: &str,
+    request: &NetworkConnectRequest,
+) -> Result<(), bollard::errors::Error> {
+    docker
+        .connect_network(
+            network,
+            ConnectNetworkOptions {
+                container: request.container.clone(),
+                endpoint_config: endpoint_settings(&request.aliases),
+            },
+        )
+        .await
+}
+
+/// Detach a container from a network; `force` detaches even if the container is not running
+pub async fn disconnect_container_from_network(
+    docker: &Docker,
+    network: &str,
+    container: &str,
+    force: bool,
+) -> Result<(), bollard::errors::Error> {
+    docker
+        .disconnect_network(
+            network,
+            DisconnectNetworkOptions {
+                container: container.to_string(),
+                force,
+            },
+        )
+        .await
+}
+
+/// Endpoint settings carrying the given aliases, ignoring blank entries
+fn endpoint_settings(aliases: &[String]) -> bollard::models::EndpointSettings {
+    let aliases: Vec<String> = aliases
+        .iter()
+        .map(|alias| alias.trim())
+        .filter(|alias| !alias.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    bollard::models::EndpointSettings {
+        aliases: if aliases.is_empty() {
+            None
+        } else {
+            Some(aliases)
+        },
+        ..Default::default()
+    }
+}
+
+/// Split an image reference into repository and tag, defaulting the tag to "latest".
+/// Registry ports (e.g. "localhost:5000/app") are not mistaken for tags, and digest
+/// references ("app@sha256:...") are returned untouched with an empty tag.
+fn split_image_reference(image: &str) -> (String, String) {
+    if image.contains('@') {
+        return (image.to_string(), String::new());
+    }
+
+    let last_slash = image.rfind('/').map(|pos| pos + 1).unwrap_or(0);
+    match image[last_slash..].rfind(':') {
+        Some(colon) => {
+            let colon = last_slash + colon;
+            (image[..colon].to_string(), image[colon + 1..].to_string())
+        }
+        None => (image.to_string(), "latest".to_string()),
+    }
+}
+
+/// Pull an image from a registry, streaming per-layer progress updates
+pub fn pull_image(
+    docker: &Docker,
+    request: &PullImageRequest,
+) -> Result<
+    impl futures_util::Stream<Item = Result<PullProgress, bollard::errors::Error>>,
+    bollard::errors::Error,
+> {
+    let (repository, tag_from_image) = split_image_reference(request.image.trim());
+    let tag = request
+        .tag
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .unwrap_or(tag_from_image);
+
+    let credentials = match (&request.username, &request.password) {
+        (Some(username), Some(password)) if !username.is_empty() => Some(DockerCredentials {
+            username: Some(username.clone()),
+            password: Some(password.clone()),
+            serveraddress: request.server_address.clone().filter(|s| !s.is_empty()),
+            ..Default::default()
+        }),
+        _ => None,
+    };
+
+    let options = Some(CreateImageOptions {
+        from_image: repository,
+        tag,
+        ..Default::default()
+    });
+
+    Ok(docker
+        .create_image(options, None, credentials)
+        .map(|result| {
+            result.map(|info| PullProgress {
+                layer_id: info.id,
+                status: info.status.unwrap_or_default(),
+                current: info.progress_detail.as_ref().and_then(|p| p.current),
+                total: info.progress_detail.as_ref().and_then(|p| p.total),
+                error: None,
+                complete: false,
+            })
+        }))
 }
 
 pub async fn create_and_start_container_from_image(
+    docker: &Docker,
     image_name: &str,
 ) -> Result<(), bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
-
     // Generate a simple name for the new container, e.g., "my-image-timestamp"
     // You might want a more robust naming strategy in a real application
     let container_name = format!(
@@ -222,11 +819,79 @@ pub async fn create_and_start_container_from_image(
         .await
 }
 
-/// Enhanced container creation with environment variables, port mappings, and restart policies
+/// Validate requested mounts and convert them to Docker's mount format.
+/// Bind mount sources are paths on the Docker host, so only the daemon can check they exist.
+fn build_mounts(mounts: &[ContainerMount]) -> Result<Vec<Mount>, String> {
+    let mut targets = std::collections::HashSet::new();
+    let mut docker_mounts = Vec::with_capacity(mounts.len());
+
+    for mount in mounts {
+        let target = mount.target.trim();
+        if !target.starts_with('/') {
+            return Err(format!(
+                "Mount target '{}' must be an absolute path",
+                mount.target
+            ));
+        }
+        if !targets.insert(target.to_string()) {
+            return Err(format!("Duplicate mount target '{}'", target));
+        }
+
+        let source = mount
+            .source
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+
+        let (typ, source, tmpfs_options) = match mount.mount_type {
+            ContainerMountType::Volume => (MountTypeEnum::VOLUME, source, None),
+            ContainerMountType::Bind => {
+                let host_path = source
+                    .ok_or_else(|| format!("Bind mount for '{}' needs a host path", target))?;
+                if !std::path::Path::new(host_path).is_absolute() {
+                    return Err(format!(
+                        "Bind mount host path '{}' must be an absolute path",
+                        host_path
+                    ));
+                }
+                (MountTypeEnum::BIND, Some(host_path), None)
+            }
+            ContainerMountType::Tmpfs => {
+                if source.is_some() {
+                    return Err(format!("tmpfs mount for '{}' cannot have a source", target));
+                }
+                let options = mount.tmpfs_size_bytes.map(|size| MountTmpfsOptions {
+                    size_bytes: Some(size),
+                    ..Default::default()
+                });
+                (MountTypeEnum::TMPFS, None, options)
+            }
+        };
+
+        docker_mounts.push(Mount {
+            target: Some(target.to_string()),
+            source: source.map(str::to_string),
+            typ: Some(typ),
+            read_only: Some(mount.read_only),
+            tmpfs_options,
+            ..Default::default()
+        });
+    }
+
+    Ok(docker_mounts)
+}
+
+/// Enhanced container creation with environment variables, port mappings, mounts, networks and restart policies
 pub async fn create_and_start_container_enhanced(
+    docker: &Docker,
     request: CreateContainerRequest,
 ) -> Result<String, bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
+    let mounts = build_mounts(&request.mounts).map_err(|message| {
+        bollard::errors::Error::DockerResponseServerError {
+            status_code: 400,
+            message,
+        }
+    })?;
 
     // Generate container name if not provided
     let container_name = request.container_name.unwrap_or_else(|| {
@@ -308,7 +973,25 @@ pub async fn create_and_start_container_enhanced(
         }
     });
 
-    let host_config = if !port_bindings.is_empty() || restart_policy.is_some() {
+    // Attach to the requested network at creation time so the container never
+    // lands on the default bridge first
+    let network = request
+        .network
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+    let networking_config = network.map(|network| bollard::container::NetworkingConfig {
+        endpoints_config: HashMap::from([(
+            network.to_string(),
+            endpoint_settings(&request.network_aliases),
+        )]),
+    });
+
+    let host_config = if !port_bindings.is_empty()
+        || restart_policy.is_some()
+        || !mounts.is_empty()
+        || network.is_some()
+    {
         Some(bollard::models::HostConfig {
             port_bindings: if port_bindings.is_empty() {
                 None
@@ -316,6 +999,12 @@ pub async fn create_and_start_container_enhanced(
                 Some(port_bindings)
             },
             restart_policy,
+            mounts: if mounts.is_empty() {
+                None
+            } else {
+                Some(mounts)
+            },
+            network_mode: network.map(str::to_string),
             ..Default::default()
         })
     } else {
@@ -335,6 +1024,7 @@ pub async fn create_and_start_container_enhanced(
             Some(exposed_ports)
         },
         host_config,
+        networking_config,
         attach_stdout: Some(true),
         attach_stderr: Some(true),
         tty: Some(false),
@@ -350,149 +1040,211 @@ pub async fn create_and_start_container_enhanced(
     Ok(response.id)
 }
 
-pub async fn start_container(container_id_or_name: &str) -> Result<(), bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
+/// The environment of a container, as `KEY=value` strings
+pub async fn get_container_environment(
+    docker: &Docker,
+    container_id_or_name: &str,
+) -> Result<Vec<String>, bollard::errors::Error> {
+    let inspect_result = docker.inspect_container(container_id_or_name, None).await?;
+    Ok(inspect_result
+        .config
+        .and_then(|config| config.env)
+        .unwrap_or_default())
+}
+
+pub async fn start_container(
+    docker: &Docker,
+    container_id_or_name: &str,
+) -> Result<(), bollard::errors::Error> {
     docker
         .start_container(container_id_or_name, None::<StartContainerOptions<String>>)
         .await
 }
 
-pub async fn stop_container(container_id_or_name: &str) -> Result<(), bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
+pub async fn stop_container(
+    docker: &Docker,
+    container_id_or_name: &str,
+) -> Result<(), bollard::errors::Error> {
     docker
         .stop_container(container_id_or_name, None::<StopContainerOptions>)
         .await
 }
 
-pub async fn restart_container(container_id_or_name: &str) -> Result<(), bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
+pub async fn restart_container(
+    docker: &Docker,
+    container_id_or_name: &str,
+) -> Result<(), bollard::errors::Error> {
     docker
         .restart_container(container_id_or_name, None::<RestartContainerOptions>)
         .await
 }
 
-#[allow(dead_code)]
-pub async fn get_container_metrics(
-    container_id: &str,
-) -> Result<Option<ContainerMetrics>, bollard::errors::Error> {
-    get_container_metrics_with_config(container_id, None).await
+/// Remove a container, optionally killing it first and removing its anonymous volumes
+pub async fn remove_container(
+    docker: &Docker,
+    container_id_or_name: &str,
+    options: &ContainerRemovalOptions,
+) -> Result<(), bollard::errors::Error> {
+    docker
+        .remove_container(
+            container_id_or_name,
+            Some(RemoveContainerOptions {
+                v: options.remove_volumes,
+                force: options.force,
+                link: false,
+            }),
+        )
+        .await
 }
 
-pub async fn get_container_metrics_with_config(
+/// Convert one raw Docker stats sample into a metrics record
+fn container_metrics_from_stats(
     container_id: &str,
-    socket_path: Option<&str>,
-) -> Result<Option<ContainerMetrics>, bollard::errors::Error> {
-    let docker = get_docker_client(socket_path)?;
-
-    // Get container info for name
-    let container_info = docker.inspect_container(container_id, None).await?;
-    let container_name = container_info
-        .name
-        .unwrap_or_default()
-        .trim_start_matches('/')
-        .to_string();
-
-    let stats_options = Some(StatsOptions {
-        stream: false,
-        one_shot: true,
-    });
-
-    let mut stats_stream = docker.stats(container_id, stats_options);
-
-    if let Some(stats_result) = stats_stream.next().await {
-        let stats = stats_result?;
-
-        // Calculate CPU usage percentage
-        let cpu_usage_percent = {
-            let cpu_delta =
-                stats.cpu_stats.cpu_usage.total_usage - stats.precpu_stats.cpu_usage.total_usage;
-            let system_delta = stats.cpu_stats.system_cpu_usage.unwrap_or(0)
-                - stats.precpu_stats.system_cpu_usage.unwrap_or(0);
-            let number_cpus = stats.cpu_stats.online_cpus.unwrap_or(1) as f64;
-
-            if system_delta > 0 {
-                (cpu_delta as f64 / system_delta as f64) * number_cpus * 100.0
-            } else {
-                0.0
-            }
-        };
-
-        // Memory stats
-        let (memory_usage_mb, memory_limit_mb, memory_usage_percent) = {
-            let usage = stats.memory_stats.usage.unwrap_or(0) as f64 / 1024.0 / 1024.0; // Convert to MB
-            let limit = stats.memory_stats.limit.unwrap_or(0) as f64 / 1024.0 / 1024.0; // Convert to MB
-            let usage_percent = if limit > 0.0 {
-                (usage / limit) * 100.0
-            } else {
-                0.0
-            };
-            (usage, limit, usage_percent)
-        };
+    container_name: &str,
+    image: &str,
+    stats: &bollard::container::Stats,
+) -> ContainerMetrics {
+    // Calculate CPU usage percentage
+    let cpu_usage_percent = {
+        let cpu_delta =
+            stats.cpu_stats.cpu_usage.total_usage - stats.precpu_stats.cpu_usage.total_usage;
+        let system_delta = stats.cpu_stats.system_cpu_usage.unwrap_or(0)
+            - stats.precpu_stats.system_cpu_usage.unwrap_or(0);
+        let number_cpus = stats.cpu_stats.online_cpus.unwrap_or(1) as f64;
+
+        if system_delta > 0 {
+            (cpu_delta as f64 / system_delta as f64) * number_cpus * 100.0
+        } else {
+            0.0
+        }
+    };
 
-        // Network stats
-        let (network_rx_bytes, network_tx_bytes) = if let Some(networks) = &stats.networks {
-            let (mut rx_total, mut tx_total) = (0u64, 0u64);
-            for network_stats in networks.values() {
-                rx_total += network_stats.rx_bytes;
-                tx_total += network_stats.tx_bytes;
-            }
-            (rx_total, tx_total)
+    // Memory stats
+    let (memory_usage_mb, memory_limit_mb, memory_usage_percent) = {
+        let usage = stats.memory_stats.usage.unwrap_or(0) as f64 / 1024.0 / 1024.0; // Convert to MB
+        let limit = stats.memory_stats.limit.unwrap_or(0) as f64 / 1024.0 / 1024.0; // Convert to MB
+        let usage_percent = if limit > 0.0 {
+            (usage / limit) * 100.0
         } else {
-            (0, 0)
+            0.0
         };
+        (usage, limit, usage_percent)
+    };
 
-        // Block I/O stats
-        let (block_read_bytes, block_write_bytes) = {
-            let read_bytes = stats
-                .blkio_stats
-                .io_service_bytes_recursive
-                .as_ref()
-                .and_then(|ios| ios.iter().find(|io| io.op == "read"))
-                .map(|io| io.value)
-                .unwrap_or(0);
+    // Network stats
+    let (network_rx_bytes, network_tx_bytes) = if let Some(networks) = &stats.networks {
+        let (mut rx_total, mut tx_total) = (0u64, 0u64);
+        for network_stats in networks.values() {
+            rx_total += network_stats.rx_bytes;
+            tx_total += network_stats.tx_bytes;
+        }
+        (rx_total, tx_total)
+    } else {
+        (0, 0)
+    };
 
-            let write_bytes = stats
-                .blkio_stats
-                .io_service_bytes_recursive
-                .as_ref()
-                .and_then(|ios| ios.iter().find(|io| io.op == "write"))
-                .map(|io| io.value)
-                .unwrap_or(0);
+    // Block I/O stats
+    let (block_read_bytes, block_write_bytes) = {
+        let read_bytes = stats
+            .blkio_stats
+            .io_service_bytes_recursive
+            .as_ref()
+            .and_then(|ios| ios.iter().find(|io| io.op == "read"))
+            .map(|io| io.value)
+            .unwrap_or(0);
+
+        let write_bytes = stats
+            .blkio_stats
+            .io_service_bytes_recursive
+            .as_ref()
+            .and_then(|ios| ios.iter().find(|io| io.op == "write"))
+            .map(|io| io.value)
+            .unwrap_or(0);
+
+        (read_bytes, write_bytes)
+    };
 
-            (read_bytes, write_bytes)
-        };
+    let pids = stats.pids_stats.current.unwrap_or(0);
 
-        let pids = stats.pids_stats.current.unwrap_or(0);
-
-        Ok(Some(ContainerMetrics {
-            container_id: container_id.to_string(),
-            container_name,
-            timestamp: Utc::now(),
-            cpu_usage_percent,
-            memory_usage_mb,
-            memory_limit_mb,
-            memory_usage_percent,
-            network_rx_bytes,
-            network_tx_bytes,
-            block_read_bytes,
-            block_write_bytes,
-            pids,
-        }))
-    } else {
-        Ok(None)
+    ContainerMetrics {
+        container_id: container_id.to_string(),
+        container_name: container_name.to_string(),
+        image: image.to_string(),
+        timestamp: Utc::now(),
+        cpu_usage_percent,
+        memory_usage_mb,
+        memory_limit_mb,
+        memory_usage_percent,
+        network_rx_bytes,
+        network_tx_bytes,
+        block_read_bytes,
+        block_write_bytes,
+        pids,
     }
 }
 
-#[allow(dead_code)]
-pub async fn get_system_metrics() -> Result<SystemMetrics, bollard::errors::Error> {
-    get_system_metrics_with_config(None).await
+/// A running container as seen by a single list call, without the per-container inspect
+pub struct ContainerIdentity {
+    pub id: String,
+    pub name: String,
+    pub image: String,
 }
 
-pub async fn get_system_metrics_with_config(
-    socket_path: Option<&str>,
-) -> Result<SystemMetrics, bollard::errors::Error> {
-    let docker = get_docker_client(socket_path)?;
+pub async fn list_running_container_identities(
+    docker: &Docker,
+) -> Result<Vec<ContainerIdentity>, bollard::errors::Error> {
+    let containers = docker
+        .list_containers(Some(ListContainersOptions::<String> {
+            all: false,
+            ..Default::default()
+        }))
+        .await?;
+
+    Ok(containers
+        .into_iter()
+        .filter_map(|container| {
+            Some(ContainerIdentity {
+                id: container.id?,
+                name: container
+                    .names
+                    .and_then(|names| names.into_iter().next())
+                    .map(|name| name.trim_start_matches('/').to_string())
+                    .unwrap_or_default(),
+                image: container.image.unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/// Subscribe to a container's live stats. Docker pushes a sample roughly once a second
+/// until the container stops, at which point the stream ends.
+pub fn stream_container_metrics(
+    docker: &Docker,
+    container: &ContainerIdentity,
+) -> Result<
+    impl futures_util::Stream<Item = Result<ContainerMetrics, bollard::errors::Error>>,
+    bollard::errors::Error,
+> {
+    let id = container.id.clone();
+    let name = container.name.clone();
+    let image = container.image.clone();
+
+    let stream = docker
+        .stats(
+            &container.id,
+            Some(StatsOptions {
+                stream: true,
+                one_shot: false,
+            }),
+        )
+        .map(move |stats| {
+            stats.map(|stats| container_metrics_from_stats(&id, &name, &image, &stats))
+        });
+
+    Ok(stream)
+}
 
+pub async fn get_system_metrics(docker: &Docker) -> Result<SystemMetrics, bollard::errors::Error> {
     // Get version info
     let version_info = docker.version().await?;
     let docker_version = version_info
@@ -535,34 +1287,9 @@ pub async fn get_system_metrics_with_config(
     })
 }
 
-#[allow(dead_code)]
-pub async fn get_all_metrics() -> Result<MetricsResponse, bollard::errors::Error> {
-    get_all_metrics_with_config(None).await
-}
-
-pub async fn get_all_metrics_with_config(
-    socket_path: Option<&str>,
-) -> Result<MetricsResponse, bollard::errors::Error> {
-    let system_metrics = get_system_metrics_with_config(socket_path).await?;
-    let running_containers = list_running_containers_with_config(socket_path).await?;
-
-    let mut container_metrics = Vec::new();
-    for container in running_containers {
-        if let Ok(Some(metrics)) =
-            get_container_metrics_with_config(&container.id, socket_path).await
-        {
-            container_metrics.push(metrics);
-        }
-    }
-
-    Ok(MetricsResponse {
-        system: system_metrics,
-        containers: container_metrics,
-    })
-}
-
 /// Get logs for a specific container
 pub async fn get_container_logs(
+    docker: &Docker,
     container_id: &str,
     tail: Option<&str>,
     follow: bool,
@@ -570,8 +1297,6 @@ pub async fn get_container_logs(
     impl futures_util::Stream<Item = Result<bollard::container::LogOutput, bollard::errors::Error>>,
     bollard::errors::Error,
 > {
-    let docker = get_docker_client(None)?;
-
     let logs_options = Some(LogsOptions::<String> {
         follow,
         stdout: true,
@@ -587,11 +1312,10 @@ pub async fn get_container_logs(
 
 /// Get recent logs for a specific container as a vector of strings
 pub async fn get_container_logs_recent(
+    docker: &Docker,
     container_id: &str,
     tail: Option<&str>,
 ) -> Result<Vec<String>, bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
-
     let logs_options = Some(LogsOptions::<String> {
         follow: false,
         stdout: true,
@@ -622,10 +1346,98 @@ pub async fn get_container_logs_recent(
     Ok(log_lines)
 }
 
-/// Get detailed information about a Docker image including exposed ports and environment variables
-pub async fn get_image_info(image_name: &str) -> Result<ImageInfo, bollard::errors::Error> {
-    let docker = get_docker_client(None)?;
+/// Default command for interactive shells: prefer bash, fall back to sh
+const DEFAULT_EXEC_SHELL: &str =
+    "if command -v bash >/dev/null 2>&1; then exec bash; else exec sh; fi";
+
+/// An interactive exec session attached to a running container with a TTY
+pub struct ExecSession {
+    pub exec_id: String,
+    pub output: Pin<
+        Box<
+            dyn futures_util::Stream<
+                    Item = Result<bollard::container::LogOutput, bollard::errors::Error>,
+                > + Send,
+        >,
+    >,
+    pub input: Pin<Box<dyn tokio::io::AsyncWrite + Send>>,
+}
+
+/// Create an exec instance with a TTY in a running container and attach to its stdin/stdout
+pub async fn create_exec_session(
+    docker: &Docker,
+    container_id: &str,
+    command: Option<Vec<String>>,
+) -> Result<ExecSession, bollard::errors::Error> {
+    let cmd = command.unwrap_or_else(|| {
+        vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            DEFAULT_EXEC_SHELL.to_string(),
+        ]
+    });
+
+    let exec = docker
+        .create_exec(
+            container_id,
+            CreateExecOptions {
+                attach_stdin: Some(true),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                tty: Some(true),
+                env: Some(vec!["TERM=xterm-256color".to_string()]),
+                cmd: Some(cmd),
+                ..Default::default()
+            },
+        )
+        .await?;
 
+    match docker
+        .start_exec(
+            &exec.id,
+            Some(StartExecOptions {
+                detach: false,
+                tty: true,
+                output_capacity: None,
+            }),
+        )
+        .await?
+    {
+        StartExecResults::Attached { output, input } => Ok(ExecSession {
+            exec_id: exec.id,
+            output,
+            input,
+        }),
+        StartExecResults::Detached => Err(bollard::errors::Error::DockerResponseServerError {
+            status_code: 500,
+            message: "Exec session started detached".to_string(),
+        }),
+    }
+}
+
+/// Resize the TTY of a running exec session
+pub async fn resize_exec(
+    docker: &Docker,
+    exec_id: &str,
+    cols: u16,
+    rows: u16,
+) -> Result<(), bollard::errors::Error> {
+    docker
+        .resize_exec(
+            exec_id,
+            ResizeExecOptions {
+                height: rows,
+                width: cols,
+            },
+        )
+        .await
+}
+
+/// Get detailed information about a Docker image including exposed ports and environment variables
+pub async fn get_image_info(
+    docker: &Docker,
+    image_name: &str,
+) -> Result<ImageInfo, bollard::errors::Error> {
     // Inspect the image
     let image_inspect = docker.inspect_image(image_name).await?;
 
@@ -660,9 +1472,10 @@ pub async fn get_image_info(image_name: &str) -> Result<ImageInfo, bollard::erro
                 if let Some(eq_pos) = env_var.find('=') {
                     let (key, value) = env_var.split_at(eq_pos);
                     let value = &value[1..]; // Skip the '=' character
-                    environment_variables.push(EnvironmentVariable {
+                    environment_variables.push(ContainerEnvironmentVariable {
                         key: key.to_string(),
-                        value: value.to_string(),
+                        value: Some(value.to_string()),
+                        masked: false,
                     });
                 }
             }
@@ -679,3 +1492,295 @@ pub async fn get_image_info(image_name: &str) -> Result<ImageInfo, bollard::erro
         environment_variables,
     })
 }
+
+/// Engine actions relayed to the browser. Exec and attach events are left out: health
+/// checks alone produce several of those every interval.
+const RELAYED_ACTIONS: [&str; 15] = [
+    "create",
+    "start",
+    "restart",
+    "stop",
+    "kill",
+    "die",
+    "oom",
+    "pause",
+    "unpause",
+    "rename",
+    "destroy",
+    "health_status",
+    "pull",
+    "tag",
+    "delete",
+];
+
+/// Convert an engine event into a `DockerEvent`, or `None` for events we don't relay
+fn to_docker_event(message: bollard::models::EventMessage) -> Option<DockerEvent> {
+    let kind = match message.typ? {
+        EventMessageTypeEnum::CONTAINER => "container",
+        EventMessageTypeEnum::IMAGE => "image",
+        _ => return None,
+    };
+    let action = message.action?;
+    // Health events carry the new status in the action, e.g. "health_status: healthy"
+    let base_action = action.split(':').next().unwrap_or_default().trim();
+    if !RELAYED_ACTIONS.contains(&base_action) {
+        return None;
+    }
+
+    let actor = message.actor.unwrap_or_default();
+    let mut attributes = actor.attributes.unwrap_or_default();
+    let timestamp = message
+        .time_nano
+        .map(DateTime::from_timestamp_nanos)
+        .or_else(|| message.time.and_then(|t| DateTime::from_timestamp(t, 0)))
+        .unwrap_or_else(Utc::now);
+
+    Some(DockerEvent {
+        timestamp,
+        kind: kind.to_string(),
+        action,
+        id: actor.id.unwrap_or_default(),
+        name: attributes.remove("name"),
+        image: attributes.remove("image"),
+        exit_code: attributes
+            .get("exitCode")
+            .and_then(|code| code.parse().ok()),
+    })
+}
+
+/// Subscribe to container and image events from the engine. The stream stays open until
+/// the connection to Docker drops.
+pub fn stream_events(
+    docker: &Docker,
+) -> impl futures_util::Stream<Item = Result<DockerEvent, bollard::errors::Error>> {
+    let options = EventsOptions::<String> {
+        filters: HashMap::from([(
+            "type".to_string(),
+            vec!["container".to_string(), "image".to_string()],
+        )]),
+        ..Default::default()
+    };
+
+    docker
+        .events(Some(options))
+        .filter_map(|message| async move {
+            match message {
+                Ok(message) => to_docker_event(message).map(Ok),
+                Err(e) => Some(Err(e)),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker_timestamp() {
+        let parsed = parse_docker_timestamp(Some("2024-01-01T12:00:00.123456789Z"));
+        assert!(parsed.is_some());
+        assert_eq!(parsed.unwrap().year(), 2024);
+    }
+
+    #[test]
+    fn test_parse_docker_timestamp_zero_value() {
+        // Docker reports never-started/never-finished containers with the zero time
+        assert!(parse_docker_timestamp(Some("0001-01-01T00:00:00Z")).is_none());
+        assert!(parse_docker_timestamp(Some("not a timestamp")).is_none());
+        assert!(parse_docker_timestamp(None).is_none());
+    }
+
+    #[test]
+    fn test_split_image_reference() {
+        assert_eq!(
+            split_image_reference("nginx"),
+            ("nginx".to_string(), "latest".to_string())
+        );
+        assert_eq!(
+            split_image_reference("nginx:1.25-alpine"),
+            ("nginx".to_string(), "1.25-alpine".to_string())
+        );
+        assert_eq!(
+            split_image_reference("localhost:5000/team/app"),
+            ("localhost:5000/team/app".to_string(), "latest".to_string())
+        );
+        assert_eq!(
+            split_image_reference("localhost:5000/team/app:v2"),
+            ("localhost:5000/team/app".to_string(), "v2".to_string())
+        );
+        assert_eq!(
+            split_image_reference("app@sha256:abc"),
+            ("app@sha256:abc".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn test_error_message_uses_stream_detail() {
+        let error = bollard::errors::Error::DockerStreamError {
+            error: "manifest unknown".to_string(),
+        };
+        assert_eq!(error_message(&error), "manifest unknown");
+    }
+
+    #[test]
+    fn test_is_image_id() {
+        assert!(is_image_id("sha256:abc123"));
+        assert!(is_image_id(&"a".repeat(64)));
+        assert!(!is_image_id("nginx:latest"));
+    }
+
+    fn mount(
+        mount_type: ContainerMountType,
+        source: Option<&str>,
+        target: &str,
+        read_only: bool,
+    ) -> ContainerMount {
+        ContainerMount {
+            mount_type,
+            source: source.map(str::to_string),
+            target: target.to_string(),
+            read_only,
+            tmpfs_size_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_build_mounts_converts_each_type() {
+        let host_dir = std::env::temp_dir();
+        let mounts = build_mounts(&[
+            mount(ContainerMountType::Volume, Some("pgdata"), "/data", false),
+            mount(ContainerMountType::Bind, host_dir.to_str(), "/config", true),
+            ContainerMount {
+                tmpfs_size_bytes: Some(1024),
+                ..mount(ContainerMountType::Tmpfs, None, "/tmp", false)
+            },
+        ])
+        .expect("Mounts should be valid");
+
+        assert_eq!(mounts.len(), 3);
+        assert_eq!(mounts[0].typ, Some(MountTypeEnum::VOLUME));
+        assert_eq!(mounts[0].source.as_deref(), Some("pgdata"));
+        assert_eq!(mounts[1].typ, Some(MountTypeEnum::BIND));
+        assert_eq!(mounts[1].read_only, Some(true));
+        assert_eq!(mounts[2].typ, Some(MountTypeEnum::TMPFS));
+        assert_eq!(mounts[2].source, None);
+        assert_eq!(
+            mounts[2].tmpfs_options.as_ref().and_then(|o| o.size_bytes),
+            Some(1024)
+        );
+    }
+
+    #[test]
+    fn test_build_mounts_allows_anonymous_volume() {
+        let mounts = build_mounts(&[mount(ContainerMountType::Volume, Some(""), "/data", false)])
+            .expect("Anonymous volume should be valid");
+        assert_eq!(mounts[0].source, None);
+    }
+
+    #[test]
+    fn test_build_mounts_rejects_invalid_mounts() {
+        let cases = [
+            mount(ContainerMountType::Volume, Some("data"), "data", false),
+            mount(ContainerMountType::Bind, None, "/config", false),
+            mount(
+                ContainerMountType::Bind,
+                Some("relative/path"),
+                "/config",
+                false,
+            ),
+            mount(ContainerMountType::Tmpfs, Some("/host"), "/tmp", false),
+        ];
+
+        for case in cases {
+            assert!(
+                build_mounts(std::slice::from_ref(&case)).is_err(),
+                "{:?} should be rejected",
+                case
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_mounts_leaves_bind_sources_to_the_daemon() {
+        // The manager often runs in a container that cannot see the host's filesystem
+        let mounts = build_mounts(&[mount(
+            ContainerMountType::Bind,
+            Some("/srv/simple-docker-manager-missing-path"),
+            "/config",
+            false,
+        )])
+        .expect("Bind sources are checked by the daemon");
+        assert_eq!(
+            mounts[0].source.as_deref(),
+            Some("/srv/simple-docker-manager-missing-path")
+        );
+    }
+
+    #[test]
+    fn test_build_mounts_rejects_duplicate_targets() {
+        let result = build_mounts(&[
+            mount(ContainerMountType::Volume, Some("a"), "/data", false),
+            mount(ContainerMountType::Volume, Some("b"), "/data", false),
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_endpoint_settings_skips_blank_aliases() {
+        let settings =
+            endpoint_settings(&["db".to_string(), " ".to_string(), " cache ".to_string()]);
+        assert_eq!(
+            settings.aliases,
+            Some(vec!["db".to_string(), "cache".to_string()])
+        );
+
+        assert_eq!(endpoint_settings(&[]).aliases, None);
+    }
+
+    fn event_message(typ: EventMessageTypeEnum, action: &str) -> bollard::models::EventMessage {
+        bollard::models::EventMessage {
+            typ: Some(typ),
+            action: Some(action.to_string()),
+            actor: Some(bollard::models::EventActor {
+                id: Some("abc123".to_string()),
+                attributes: Some(HashMap::from([
+                    ("name".to_string(), "web".to_string()),
+                    ("image".to_string(), "nginx:latest".to_string()),
+                    ("exitCode".to_string(), "137".to_string()),
+                ])),
+            }),
+            time: Some(1_700_000_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_to_docker_event_maps_container_events() {
+        let event = to_docker_event(event_message(EventMessageTypeEnum::CONTAINER, "die"))
+            .expect("die events should be relayed");
+        assert_eq!(event.kind, "container");
+        assert_eq!(event.id, "abc123");
+        assert_eq!(event.name.as_deref(), Some("web"));
+        assert_eq!(event.image.as_deref(), Some("nginx:latest"));
+        assert_eq!(event.exit_code, Some(137));
+        assert_eq!(event.timestamp.timestamp(), 1_700_000_000);
+
+        let health = to_docker_event(event_message(
+            EventMessageTypeEnum::CONTAINER,
+            "health_status: unhealthy",
+        ))
+        .expect("health events should be relayed");
+        assert_eq!(health.action, "health_status: unhealthy");
+    }
+
+    #[test]
+    fn test_to_docker_event_skips_noise() {
+        assert!(to_docker_event(event_message(
+            EventMessageTypeEnum::CONTAINER,
+            "exec_create: /bin/sh -c healthcheck"
+        ))
+        .is_none());
+        assert!(to_docker_event(event_message(EventMessageTypeEnum::NETWORK, "connect")).is_none());
+        assert!(to_docker_event(event_message(EventMessageTypeEnum::IMAGE, "pull")).is_some());
+    }
+}
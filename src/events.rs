@@ -0,0 +1,106 @@
+use futures_util::StreamExt;
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::backend::DockerBackend;
+use crate::models::DockerEvent;
+
+/// How long to wait before resubscribing after the Docker event stream drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Fans Docker engine events out to every connected browser. Subscribers that fall more
+/// than `capacity` events behind skip the backlog rather than slowing the feed down.
+#[derive(Debug)]
+pub struct EventHub {
+    sender: broadcast::Sender<DockerEvent>,
+}
+
+impl EventHub {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DockerEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Send an event to current subscribers; it is dropped when nobody is listening
+    pub fn publish(&self, event: DockerEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Relay Docker events into the hub for the lifetime of the server, resubscribing
+/// whenever the stream ends or fails (e.g. the daemon restarted)
+pub fn spawn(hub: Arc<EventHub>, backend: Arc<dyn DockerBackend>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match backend.stream_events() {
+                Ok(mut stream) => {
+                    tracing::debug!("Subscribed to Docker events");
+                    while let Some(event) = stream.next().await {
+                        match event {
+                            Ok(event) => hub.publish(event),
+                            Err(e) => {
+                                tracing::warn!("Docker event stream failed: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to subscribe to Docker events: {}", e),
+            }
+
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use chrono::Utc;
+
+    fn event(action: &str) -> DockerEvent {
+        DockerEvent {
+            timestamp: Utc::now(),
+            kind: "container".to_string(),
+            action: action.to_string(),
+            id: "abc123".to_string(),
+            name: Some("web".to_string()),
+            image: Some("nginx:latest".to_string()),
+            exit_code: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hub_fans_out_to_every_subscriber() {
+        let hub = EventHub::new(16);
+        let mut first = hub.subscribe();
+        let mut second = hub.subscribe();
+
+        hub.publish(event("start"));
+
+        assert_eq!(first.recv().await.unwrap().action, "start");
+        assert_eq!(second.recv().await.unwrap().action, "start");
+    }
+
+    #[tokio::test]
+    async fn test_spawn_relays_backend_events() {
+        let hub = Arc::new(EventHub::new(16));
+        let mut receiver = hub.subscribe();
+        let backend = Arc::new(
+            FakeBackend::new()
+                .with_event(event("start"))
+                .with_event(event("die")),
+        );
+
+        let handle = spawn(hub.clone(), backend);
+
+        assert_eq!(receiver.recv().await.unwrap().action, "start");
+        assert_eq!(receiver.recv().await.unwrap().action, "die");
+        handle.abort();
+    }
+}
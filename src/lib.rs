@@ -2,7 +2,20 @@
 // This allows integration tests and other crates to use the modules
 
 pub mod auth;
+pub mod backend;
 pub mod config;
 pub mod docker;
+pub mod events;
+pub mod login_throttle;
+pub mod metrics_history;
 pub mod models;
+pub mod oidc;
+pub mod pages;
+pub mod prometheus;
+pub mod secrets;
+pub mod stats_collector;
+pub mod timeline;
+pub mod tls;
+pub mod tokens;
+pub mod totp;
 pub mod web;
@@ -0,0 +1,409 @@
+use axum::http::HeaderMap;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+
+/// How long failures are remembered after the last one, on top of the longest lockout
+const FAILURE_MEMORY: Duration = Duration::from_secs(3600);
+
+/// Most usernames and addresses tracked at once. Usernames come from unauthenticated
+/// requests, so without a limit random ones could grow the map without bound.
+/// Forgetting a key would hand its attempts back, so new keys are locked out instead.
+const MAX_TRACKED: usize = 10_000;
+
+/// An address or CIDR range, e.g. `10.0.0.0/8` or `::1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let address = address
+            .trim()
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid IP address '{}'", s))?
+            .to_canonical();
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("invalid prefix length in '{}'", s))?,
+            None => max_prefix,
+        };
+        Ok(Self { address, prefix })
+    }
+}
+
+/// Parse a comma-separated list of networks, as given in `SDM_TRUSTED_PROXIES`
+pub fn deserialize_networks<'de, D>(deserializer: D) -> Result<Vec<IpNetwork>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| entry.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// The address of the client behind a request. `X-Forwarded-For` is only believed when the
+/// connection comes from a trusted proxy; the client is then the right-most address in the
+/// header that is not itself a trusted proxy.
+pub fn client_ip(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted: &[IpNetwork],
+) -> Option<IpAddr> {
+    let peer = peer?.to_canonical();
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|network| network.contains(ip));
+    if !is_trusted(peer) {
+        return Some(peer);
+    }
+
+    let mut client = peer;
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+    for hop in forwarded.iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break; // Anything left of a malformed entry cannot be trusted
+        };
+        client = ip.to_canonical();
+        if !is_trusted(client) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Tracks failed sign-ins per client address and per username. After `max_failures`
+/// failures in a row, further attempts are refused for a lockout that doubles with every
+/// additional failure, up to `max_lockout`. A username's failures are forgotten when it
+/// signs in; otherwise failures are forgotten an hour after the longest lockout would end.
+/// IPv6 clients are counted per /64 network, since one client usually holds all of it.
+/// Once `max_tracked` keys are tracked, any other username or address is locked out for
+/// `base_lockout` until older failures are forgotten.
+#[derive(Debug)]
+pub struct LoginThrottle {
+    failures: Mutex<HashMap<String, Failures>>,
+    max_tracked: usize,
+    max_failures: u32,
+    base_lockout: Duration,
+    max_lockout: Duration,
+}
+
+impl LoginThrottle {
+    pub fn new(max_failures: u32, base_lockout: Duration, max_lockout: Duration) -> Self {
+        Self {
+            failures: Mutex::new(HashMap::new()),
+            max_tracked: MAX_TRACKED,
+            max_failures: max_failures.max(1),
+            base_lockout,
+            max_lockout: max_lockout.max(base_lockout),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.login_max_failures,
+            Duration::from_secs(config.login_lockout_seconds),
+            Duration::from_secs(config.login_max_lockout_seconds),
+        )
+    }
+
+    /// Remaining lockout for this client or username, if either is locked out
+    pub async fn locked_for(&self, ip: Option<IpAddr>, username: &str) -> Option<Duration> {
+        self.locked_for_at(ip, username, Instant::now()).await
+    }
+
+    /// Count a failed sign-in, returning the lockout it triggered, if any
+    pub async fn record_failure(&self, ip: Option<IpAddr>, username: &str) -> Option<Duration> {
+        self.record_failure_at(ip, username, Instant::now()).await
+    }
+
+    /// Clear the failures of a user who just signed in. The client address keeps its
+    /// count, so signing in to one account does not reset guessing at another.
+    pub async fn record_success(&self, username: &str) {
+        let mut failures = self.failures.lock().await;
+        failures.remove(&user_key(username));
+    }
+
+    async fn locked_for_at(
+        &self,
+        ip: Option<IpAddr>,
+        username: &str,
+        now: Instant,
+    ) -> Option<Duration> {
+        let mut failures = self.failures.lock().await;
+        self.forget_old_failures(&mut failures, now);
+        let keys = keys(ip, username);
+        if keys.iter().any(|key| self.is_untrackable(&failures, key)) {
+            return Some(self.base_lockout).filter(|lockout| !lockout.is_zero());
+        }
+        keys.iter()
+            .filter_map(|key| failures.get(key)?.locked_until)
+            .filter_map(|until| until.checked_duration_since(now))
+            .filter(|remaining| !remaining.is_zero())
+            .max()
+    }
+
+    async fn record_failure_at(
+        &self,
+        ip: Option<IpAddr>,
+        username: &str,
+        now: Instant,
+    ) -> Option<Duration> {
+        let mut failures = self.failures.lock().await;
+        self.forget_old_failures(&mut failures, now);
+
+        let mut lockout = None;
+        for key in keys(ip, username) {
+            if self.is_untrackable(&failures, &key) {
+                lockout = lockout.max(Some(self.base_lockout));
+                continue;
+            }
+            let entry = failures.entry(key).or_insert(Failures {
+                count: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+            entry.count += 1;
+            entry.last_failure = now;
+            if entry.count >= self.max_failures {
+                let duration = self.lockout_after(entry.count);
+                entry.locked_until = Some(now + duration);
+                lockout = lockout.max(Some(duration));
+            }
+        }
+        lockout
+    }
+
+    fn forget_old_failures(&self, failures: &mut HashMap<String, Failures>, now: Instant) {
+        let memory = self.max_lockout + FAILURE_MEMORY;
+        failures.retain(|_, entry| now.saturating_duration_since(entry.last_failure) < memory);
+    }
+
+    /// Whether the key is new while the map is full
+    fn is_untrackable(&self, failures: &HashMap<String, Failures>, key: &str) -> bool {
+        !failures.contains_key(key) && failures.len() >= self.max_tracked
+    }
+
+    fn lockout_after(&self, count: u32) -> Duration {
+        let doublings = (count - self.max_failures).min(31);
+        self.base_lockout
+            .saturating_mul(1 << doublings)
+            .min(self.max_lockout)
+    }
+}
+
+fn user_key(username: &str) -> String {
+    format!("user:{}", username)
+}
+
+fn keys(ip: Option<IpAddr>, username: &str) -> Vec<String> {
+    let mut keys = vec![user_key(username)];
+    match ip.map(|ip| ip.to_canonical()) {
+        Some(IpAddr::V4(ip)) => keys.push(format!("ip:{}", ip)),
+        Some(IpAddr::V6(ip)) => {
+            let network = Ipv6Addr::from(u128::from(ip) & !u128::from(u64::MAX));
+            keys.push(format!("ip:{}/64", network));
+        }
+        None => {}
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_network_contains() {
+        let network: IpNetwork = "10.1.0.0/16".parse().unwrap();
+        assert!(network.contains(ip("10.1.200.3")));
+        assert!(!network.contains(ip("10.2.0.1")));
+        assert!(network.contains(ip("::ffff:10.1.0.1")));
+
+        let single: IpNetwork = "::1".parse().unwrap();
+        assert!(single.contains(ip("::1")));
+        assert!(!single.contains(ip("127.0.0.1")));
+
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains(ip("203.0.113.9")));
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("proxy.local".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_forwarded_for_from_proxies() {
+        let trusted = vec!["10.0.0.0/8".parse().unwrap()];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.1, 203.0.113.7, 10.0.0.5"),
+        );
+
+        // A direct client cannot choose its address
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.50")), &headers, &trusted),
+            Some(ip("203.0.113.50"))
+        );
+        // Behind the proxy, the spoofable left-most entry is ignored
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.2")), &headers, &trusted),
+            Some(ip("203.0.113.7"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.2")), &HeaderMap::new(), &trusted),
+            Some(ip("10.0.0.2"))
+        );
+        assert_eq!(client_ip(None, &headers, &trusted), None);
+    }
+
+    #[tokio::test]
+    async fn test_lockout_doubles_and_resets_on_success() {
+        let throttle = LoginThrottle::new(3, Duration::from_secs(30), Duration::from_secs(100));
+        let client = Some(ip("203.0.113.7"));
+        let now = Instant::now();
+
+        assert_eq!(throttle.record_failure_at(client, "admin", now).await, None);
+        assert_eq!(throttle.record_failure_at(client, "admin", now).await, None);
+        assert_eq!(throttle.locked_for_at(client, "admin", now).await, None);
+        assert_eq!(
+            throttle.record_failure_at(client, "admin", now).await,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            throttle.locked_for_at(client, "admin", now).await,
+            Some(Duration::from_secs(30))
+        );
+
+        // Both the address and the username are locked out
+        assert!(throttle.locked_for_at(None, "admin", now).await.is_some());
+        assert!(throttle.locked_for_at(client, "other", now).await.is_some());
+        assert!(throttle
+            .locked_for_at(Some(ip("198.51.100.1")), "other", now)
+            .await
+            .is_none());
+
+        let later = now + Duration::from_secs(31);
+        assert_eq!(throttle.locked_for_at(client, "admin", later).await, None);
+        assert_eq!(
+            throttle.record_failure_at(client, "admin", later).await,
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            throttle.record_failure_at(client, "admin", later).await,
+            Some(Duration::from_secs(100))
+        );
+
+        throttle.record_success("admin").await;
+        assert_eq!(throttle.locked_for_at(None, "admin", later).await, None);
+        assert!(throttle
+            .locked_for_at(client, "admin", later)
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_clients_are_counted_per_network() {
+        let throttle = LoginThrottle::new(2, Duration::from_secs(30), Duration::from_secs(30));
+        let now = Instant::now();
+
+        throttle
+            .record_failure_at(Some(ip("2001:db8:1:2::1")), "a", now)
+            .await;
+        throttle
+            .record_failure_at(Some(ip("2001:db8:1:2:ffff::9")), "b", now)
+            .await;
+        let neighbour = Some(ip("2001:db8:1:2::abcd"));
+        assert!(throttle.locked_for_at(neighbour, "c", now).await.is_some());
+        assert!(throttle
+            .locked_for_at(Some(ip("2001:db8:1:3::1")), "c", now)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_new_keys_are_locked_out_once_tracking_is_full() {
+        let mut throttle = LoginThrottle::new(3, Duration::from_secs(30), Duration::from_secs(30));
+        throttle.max_tracked = 4;
+        let now = Instant::now();
+
+        // A locked-out user, then guesses at random usernames without an address
+        for _ in 0..3 {
+            throttle.record_failure_at(None, "admin", now).await;
+        }
+        for username in ["a", "b", "c"] {
+            throttle.record_failure_at(None, username, now).await;
+        }
+        assert_eq!(throttle.failures.lock().await.len(), 4);
+
+        // Nothing is forgotten to make room, and the next new key is refused
+        assert_eq!(
+            throttle.record_failure_at(None, "d", now).await,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            throttle.locked_for_at(None, "d", now).await,
+            Some(Duration::from_secs(30))
+        );
+        assert!(throttle.locked_for_at(None, "admin", now).await.is_some());
+        assert!(throttle.locked_for_at(None, "a", now).await.is_none());
+        assert_eq!(throttle.failures.lock().await.len(), 4);
+
+        // Once the old failures are forgotten, new keys are tracked again
+        let later = now + Duration::from_secs(30) + FAILURE_MEMORY;
+        assert!(throttle.locked_for_at(None, "d", later).await.is_none());
+        assert_eq!(throttle.record_failure_at(None, "d", later).await, None);
+    }
+}
@@ -1,18 +1,57 @@
+use axum_server::{tls_rustls::RustlsConfig, Handle};
 use tokio::signal;
 use tower_http::trace::TraceLayer;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 mod auth;
+mod backend;
 mod config;
 mod docker;
+mod events;
+mod login_throttle;
+mod metrics_history;
 mod models;
+mod oidc;
+mod pages;
+mod prometheus;
+mod secrets;
+mod stats_collector;
+mod timeline;
+mod tls;
+mod tokens;
+mod totp;
 mod web;
 
+use auth::SessionStore;
+use backend::{BollardBackend, DockerBackend};
 use config::Config;
+use events::EventHub;
+use stats_collector::StatsCollector;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use timeline::Timeline;
+use tokens::TokenStore;
+use totp::TotpStore;
+
+/// Events a slow browser may fall behind by before it is told to reload
+const EVENT_BUFFER_SIZE: usize = 256;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // `simple-docker-manager hash-password` reads a password from stdin and prints the
+    // Argon2 hash to put in the users file
+    if std::env::args().nth(1).as_deref() == Some("hash-password") {
+        let mut password = String::new();
+        std::io::stdin().read_line(&mut password)?;
+        println!(
+            "{}",
+            Config::hash_password(password.trim_end_matches(['\r', '\n']))?
+        );
+        return Ok(());
+    }
+
     // Load configuration first
     let config = Config::from_env().map_err(|e| {
         eprintln!("Failed to load configuration: {}", e);
@@ -26,8 +65,91 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("🐳 Simple Docker Manager starting up");
     info!("Configuration: {:#?}", config);
 
+    // Without trusted proxies the authentication header is never believed
+    if config.proxy_auth_user_header.is_some() && config.trusted_proxies.is_empty() {
+        warn!("SDM_PROXY_AUTH_USER_HEADER is set but SDM_TRUSTED_PROXIES is empty; proxy authentication is disabled");
+    }
+
+    // One Docker client for the whole process, shared by every request and the collector
+    let backend: Arc<dyn DockerBackend> = Arc::new(
+        BollardBackend::connect(config.docker_socket.as_deref()).map_err(|e| {
+            error!("Failed to create Docker client: {}", e);
+            e
+        })?,
+    );
+
+    // Stream container stats in the background; requests are served from its cache
+    let stats = Arc::new(StatsCollector::new(config.metrics_history_limit));
+    stats_collector::spawn(stats.clone(), backend.clone(), &config);
+
+    // Keep a persistent timeline of Docker events and user actions
+    let events_file = config.events_file_path();
+    let timeline = Arc::new(
+        Timeline::open(&events_file, config.events_retention).unwrap_or_else(|e| {
+            warn!(
+                "Cannot open event timeline {}: {}. Events will not survive restarts",
+                events_file, e
+            );
+            Timeline::in_memory(config.events_retention)
+        }),
+    );
+
+    // Relay Docker engine events to browsers as they happen; the timeline subscribes
+    // before the relay starts so it sees every event
+    let events = Arc::new(EventHub::new(EVENT_BUFFER_SIZE));
+    timeline::spawn_recorder(timeline.clone(), events.subscribe());
+    events::spawn(events.clone(), backend.clone());
+
+    // API tokens issued to automation clients
+    let tokens_file = config.tokens_file_path();
+    let tokens = Arc::new(TokenStore::open(&tokens_file).unwrap_or_else(|e| {
+        warn!(
+            "Cannot open API token file {}: {}. Issued tokens will not survive restarts",
+            tokens_file, e
+        );
+        TokenStore::in_memory()
+    }));
+
+    // Two-factor enrollments. Running without an existing file would quietly turn
+    // two-factor off for everyone enrolled, so one that cannot be read stops the server.
+    let totp_file = config.totp_file_path();
+    let totp = Arc::new(match TotpStore::open(&totp_file) {
+        Ok(store) => store,
+        Err(e) if !std::path::Path::new(&totp_file).exists() => {
+            warn!(
+                "Cannot create two-factor file {}: {}. Two-factor enrollments will not survive restarts",
+                totp_file, e
+            );
+            TotpStore::in_memory()
+        }
+        Err(e) => {
+            error!("Cannot read two-factor file {}: {}", totp_file, e);
+            return Err(e.into());
+        }
+    });
+
+    // Browser sessions, optionally saved so users stay signed in across restarts
+    let session_config = Arc::new(config.clone());
+    let sessions = Arc::new(
+        match config.sessions_file.as_deref().filter(|f| !f.is_empty()) {
+            Some(sessions_file) => SessionStore::open(session_config.clone(), sessions_file)
+                .unwrap_or_else(|e| {
+                    warn!(
+                        "Cannot open session file {}: {}. Sessions will not survive restarts",
+                        sessions_file, e
+                    );
+                    SessionStore::new(session_config)
+                }),
+            None => SessionStore::new(session_config),
+        },
+    );
+    auth::spawn_sweeper(sessions.clone());
+
     // Build the application with middleware
-    let app = web::app_router(&config).layer(TraceLayer::new_for_http());
+    let app = web::app_router(
+        &config, backend, stats, events, timeline, tokens, sessions, totp,
+    )
+    .layer(TraceLayer::new_for_http());
 
     // Bind to the configured address
     let bind_addr = config.bind_address();
@@ -41,10 +163,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             )
         })?;
 
+    // Serve HTTPS when a certificate is configured
+    let tls = if config.tls_enabled() {
+        let server_config = tls::server_config(&config).map_err(|e| {
+            error!("Cannot load TLS certificate: {}", e);
+            e
+        })?;
+        let tls = RustlsConfig::from_config(Arc::new(server_config));
+        tls::spawn_reloader(tls.clone(), Arc::new(config.clone()));
+        if config.tls_client_ca_file.is_some() {
+            info!("🔒 Clients must present a certificate issued by the configured CA");
+        }
+        Some(tls)
+    } else {
+        None
+    };
+    let scheme = if tls.is_some() { "https" } else { "http" };
+
     let local_addr = listener.local_addr()?;
-    info!("🚀 Server listening on http://{}", local_addr);
-    info!("📊 Dashboard: http://{}/metrics", local_addr);
-    info!("🏠 Management: http://{}/", local_addr);
+    info!("🚀 Server listening on {}://{}", scheme, local_addr);
+    info!("📊 Dashboard: {}://{}/metrics", scheme, local_addr);
+    info!("🏠 Management: {}://{}/", scheme, local_addr);
 
     // Set up graceful shutdown signal handling
     let shutdown_signal = shutdown_signal();
@@ -52,11 +191,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Start the server with graceful shutdown
     info!("✅ Server ready! Press Ctrl+C to stop");
 
-    // Start the server and wait for shutdown signal
-    let server_handle = axum::serve(listener, app).with_graceful_shutdown(shutdown_signal);
-
     // Run the server until it completes (either by shutdown signal or error)
-    server_handle.await.map_err(|e| {
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let result = match tls {
+        Some(tls) => {
+            // Stop accepting connections on shutdown, giving open ones time to finish
+            let handle = Handle::new();
+            let shutdown_handle = handle.clone();
+            let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_seconds);
+            tokio::spawn(async move {
+                shutdown_signal.await;
+                shutdown_handle.graceful_shutdown(Some(shutdown_timeout));
+            });
+            axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                .handle(handle)
+                .serve(make_service)
+                .await
+        }
+        None => {
+            axum::serve(listener, make_service)
+                .with_graceful_shutdown(shutdown_signal)
+                .await
+        }
+    };
+    result.map_err(|e| {
         error!("Server error: {}", e);
         e
     })?;
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use tokio::sync::RwLock;
+
+use crate::models::{MetricsHistoryResponse, MetricsResponse};
+
+/// Bounded ring buffer of metrics snapshots shared by every dashboard tab and API client.
+/// Each snapshot holds the system metrics and per-container metrics from one sample.
+#[derive(Debug)]
+pub struct MetricsHistory {
+    samples: RwLock<VecDeque<MetricsResponse>>,
+    limit: usize,
+}
+
+impl MetricsHistory {
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            samples: RwLock::new(VecDeque::with_capacity(limit)),
+            limit,
+        }
+    }
+
+    /// Append a snapshot, evicting the oldest one once the buffer is full
+    pub async fn record(&self, snapshot: MetricsResponse) {
+        let mut samples = self.samples.write().await;
+        while samples.len() >= self.limit {
+            samples.pop_front();
+        }
+        samples.push_back(snapshot);
+    }
+
+    /// Snapshots taken within `[from, to]`, oldest first. When `container` is given, each
+    /// snapshot only keeps the metrics of containers whose ID (or ID prefix) or name matches.
+    pub async fn query(
+        &self,
+        container: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<MetricsResponse> {
+        let samples = self.samples.read().await;
+        samples
+            .iter()
+            .filter(|sample| from.is_none_or(|from| sample.system.timestamp >= from))
+            .filter(|sample| to.is_none_or(|to| sample.system.timestamp <= to))
+            .map(|sample| match container {
+                Some(container) => MetricsResponse {
+                    system: sample.system.clone(),
+                    containers: sample
+                        .containers
+                        .iter()
+                        .filter(|metrics| {
+                            metrics.container_name == container
+                                || metrics.container_id.starts_with(container)
+                        })
+                        .cloned()
+                        .collect(),
+                },
+                None => sample.clone(),
+            })
+            .collect()
+    }
+
+    pub async fn response(
+        &self,
+        container: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> MetricsHistoryResponse {
+        MetricsHistoryResponse {
+            limit: self.limit,
+            samples: self.query(container, from, to).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContainerMetrics, SystemMetrics};
+
+    fn snapshot(timestamp: DateTime<Utc>, containers: &[(&str, &str)]) -> MetricsResponse {
+        MetricsResponse {
+            system: SystemMetrics {
+                timestamp,
+                total_containers: containers.len() as u32,
+                running_containers: containers.len() as u32,
+                total_images: 1,
+                docker_version: "24.0.0".to_string(),
+            },
+            containers: containers
+                .iter()
+                .map(|(id, name)| ContainerMetrics {
+                    container_id: id.to_string(),
+                    container_name: name.to_string(),
+                    image: "nginx:latest".to_string(),
+                    timestamp,
+                    cpu_usage_percent: 1.0,
+                    memory_usage_mb: 10.0,
+                    memory_limit_mb: 100.0,
+                    memory_usage_percent: 10.0,
+                    network_rx_bytes: 0,
+                    network_tx_bytes: 0,
+                    block_read_bytes: 0,
+                    block_write_bytes: 0,
+                    pids: 1,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_evicts_oldest_samples() {
+        let history = MetricsHistory::new(2);
+        let start = Utc::now();
+
+        for offset in 0..3 {
+            history
+                .record(snapshot(start + chrono::Duration::seconds(offset), &[]))
+                .await;
+        }
+
+        let samples = history.query(None, None, None).await;
+        assert_eq!(samples.len(), 2);
+        assert_eq!(
+            samples[0].system.timestamp,
+            start + chrono::Duration::seconds(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_filters_by_time_range() {
+        let history = MetricsHistory::new(10);
+        let start = Utc::now();
+
+        for offset in 0..5 {
+            history
+                .record(snapshot(start + chrono::Duration::seconds(offset), &[]))
+                .await;
+        }
+
+        let samples = history
+            .query(
+                None,
+                Some(start + chrono::Duration::seconds(1)),
+                Some(start + chrono::Duration::seconds(3)),
+            )
+            .await;
+        assert_eq!(samples.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_history_filters_by_container() {
+        let history = MetricsHistory::new(10);
+        history
+            .record(snapshot(
+                Utc::now(),
+                &[("abc123def", "web"), ("987zyx", "db")],
+            ))
+            .await;
+
+        for filter in ["web", "abc123"] {
+            let samples = history.query(Some(filter), None, None).await;
+            assert_eq!(samples[0].containers.len(), 1);
+            assert_eq!(samples[0].containers[0].container_name, "web");
+        }
+
+        let samples = history.query(Some("missing"), None, None).await;
+        assert!(samples[0].containers.is_empty());
+    }
+}
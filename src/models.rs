@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PortMapping {
@@ -18,10 +19,53 @@ pub struct ContainerSummary {
     pub environment: Vec<String>,
 }
 
+// Lifecycle view of a container in any state (running, exited, created, paused, ...)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContainerStateSummary {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub state: String,  // e.g., "running", "exited", "created"
+    pub status: String, // e.g., "Exited (0) 2 hours ago"
+    pub exit_code: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LocalImageSummary {
     pub id: String,
     pub repo_tags: Vec<String>, // e.g., ["ubuntu:latest", "ubuntu:22.04"]
+    #[serde(default)]
+    pub size_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImageRemovalOptions {
+    #[serde(default)]
+    pub force: bool, // Remove even if tagged multiple times or used by stopped containers
+    #[serde(default)]
+    pub no_prune: bool, // Keep untagged parent images
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImageRemovalResult {
+    pub untagged: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+// Dangling (<none>:<none>) images that a prune would delete
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImagePrunePreview {
+    pub images: Vec<LocalImageSummary>,
+    pub reclaimable_bytes: i64, // Upper bound: layers shared with other images are not freed
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImagePruneResult {
+    pub deleted: Vec<String>,
+    pub space_reclaimed_bytes: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,7 +73,7 @@ pub struct ImageInfo {
     pub id: String,
     pub repo_tags: Vec<String>,
     pub exposed_ports: Vec<ContainerPortMapping>,
-    pub environment_variables: Vec<EnvironmentVariable>,
+    pub environment_variables: Vec<ContainerEnvironmentVariable>,
 }
 
 // New structures for enhanced container creation
@@ -46,6 +90,20 @@ pub struct EnvironmentVariable {
     pub value: String,
 }
 
+/// A container's or image's environment variable as shown to users. The values of
+/// variables that look like secrets are withheld unless an admin reveals them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContainerEnvironmentVariable {
+    pub key: String,
+    pub value: Option<String>,
+    pub masked: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevealEnvironmentRequest {
+    pub key: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateContainerRequest {
     pub image_name: String,
@@ -53,12 +111,158 @@ pub struct CreateContainerRequest {
     pub environment_variables: Vec<EnvironmentVariable>,
     pub port_mappings: Vec<ContainerPortMapping>,
     pub restart_policy: Option<String>, // "no", "always", "unless-stopped", "on-failure"
+    #[serde(default)]
+    pub mounts: Vec<ContainerMount>,
+    #[serde(default)]
+    pub network: Option<String>, // Network to attach to instead of the default bridge
+    #[serde(default)]
+    pub network_aliases: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerMountType {
+    Volume, // Named volume (created on demand) or anonymous volume when no source is given
+    Bind,   // Host path; must exist and be absolute
+    Tmpfs,  // In-memory filesystem, no source
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContainerMount {
+    #[serde(rename = "type")]
+    pub mount_type: ContainerMountType,
+    #[serde(default)]
+    pub source: Option<String>, // Volume name or host path
+    pub target: String, // Absolute path inside the container
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub tmpfs_size_bytes: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ContainerRemovalOptions {
+    #[serde(default)]
+    pub force: bool, // Kill the container first if it is running
+    #[serde(default)]
+    pub remove_volumes: bool, // Also remove anonymous volumes attached to the container
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VolumeSummary {
+    pub name: String,
+    pub driver: String,
+    pub mountpoint: String,
+    pub scope: String, // "local" or "global"
+    pub created_at: Option<DateTime<Utc>>,
+    pub labels: HashMap<String, String>,
+    pub options: HashMap<String, String>,
+    pub used_by: Vec<VolumeUsage>, // Containers (in any state) that mount this volume
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VolumeUsage {
+    pub container_id: String,
+    pub container_name: String,
+    pub container_state: String,
+    pub destination: String, // Mount path inside the container
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateVolumeRequest {
+    pub name: String,
+    pub driver: Option<String>, // defaults to "local"
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VolumePruneResult {
+    pub deleted: Vec<String>,
+    pub space_reclaimed_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkSummary {
+    pub id: String,
+    pub name: String,
+    pub driver: String, // e.g., "bridge", "overlay", "host"
+    pub scope: String,
+    pub internal: bool,
+    pub attachable: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub subnets: Vec<String>,
+    pub labels: HashMap<String, String>,
+    pub containers: Vec<NetworkAttachment>, // Containers (in any state) attached to this network
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkAttachment {
+    pub container_id: String,
+    pub container_name: String,
+    pub container_state: String,
+    pub ip_address: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateNetworkRequest {
+    pub name: String,
+    pub driver: Option<String>, // defaults to "bridge"
+    #[serde(default)]
+    pub internal: bool, // No external connectivity
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkConnectRequest {
+    pub container: String, // Container ID or name
+    #[serde(default)]
+    pub aliases: Vec<String>, // Extra DNS names for the container on this network
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkDisconnectRequest {
+    pub container: String, // Container ID or name
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PullImageRequest {
+    pub image: String,       // e.g., "nginx", "localhost:5000/my-app"
+    pub tag: Option<String>, // defaults to the tag in `image`, or "latest"
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub server_address: Option<String>, // Registry to authenticate against
+}
+
+// One progress update from an image pull, usually scoped to a single layer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PullProgress {
+    pub layer_id: Option<String>,
+    pub status: String, // e.g., "Downloading", "Pull complete"
+    pub current: Option<i64>,
+    pub total: Option<i64>,
+    pub error: Option<String>,
+    pub complete: bool,
+}
+
+// Messages sent by the browser terminal over the exec WebSocket
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ExecClientMessage {
+    Input { data: String },
+    Resize { cols: u16, rows: u16 },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ContainerMetrics {
     pub container_id: String,
     pub container_name: String,
+    #[serde(default)]
+    pub image: String,
     pub timestamp: DateTime<Utc>,
     pub cpu_usage_percent: f64,
     pub memory_usage_mb: f64,
@@ -86,6 +290,46 @@ pub struct MetricsResponse {
     pub containers: Vec<ContainerMetrics>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetricsHistoryResponse {
+    pub limit: usize,                  // Maximum number of samples kept server-side
+    pub samples: Vec<MetricsResponse>, // Oldest first
+}
+
+// A Docker engine event relayed to browsers, e.g. a container starting or an image pull
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DockerEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: String,   // "container" or "image"
+    pub action: String, // e.g., "start", "die", "destroy", "health_status: healthy", "pull"
+    pub id: String,     // Container ID, or the image reference for image events
+    pub name: Option<String>,
+    pub image: Option<String>,
+    pub exit_code: Option<i64>, // Set on "die" events
+}
+
+// Who caused a timeline entry: the Docker engine, or a user acting through the UI or API
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineSource {
+    Docker,
+    User,
+}
+
+// One entry in the persistent event timeline
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    pub timestamp: DateTime<Utc>,
+    pub source: TimelineSource,
+    pub kind: String,   // "container", "image", "volume" or "network"
+    pub action: String, // e.g., "die", "stop", "remove", "prune"
+    pub target: String, // ID or name the action applied to
+    pub name: Option<String>,
+    pub username: Option<String>, // Set for user actions when authentication is enabled
+    pub error: Option<String>,    // Set when a user action failed
+    pub details: Option<String>,  // e.g., the exit code of a "die" event
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,6 +381,31 @@ mod tests {
         assert_eq!(deserialized.environment.len(), 1);
     }
 
+    #[test]
+    fn test_container_state_summary_serialization() {
+        let summary = ContainerStateSummary {
+            id: "def456".to_string(),
+            name: "stopped-app".to_string(),
+            image: "redis:7".to_string(),
+            state: "exited".to_string(),
+            status: "Exited (137) 5 minutes ago".to_string(),
+            exit_code: Some(137),
+            created_at: Some(Utc::now()),
+            started_at: Some(Utc::now()),
+            finished_at: None,
+        };
+
+        let json = serde_json::to_string(&summary).expect("Should serialize");
+        let deserialized: ContainerStateSummary =
+            serde_json::from_str(&json).expect("Should deserialize");
+
+        assert_eq!(deserialized.id, "def456");
+        assert_eq!(deserialized.state, "exited");
+        assert_eq!(deserialized.exit_code, Some(137));
+        assert!(deserialized.created_at.is_some());
+        assert!(deserialized.finished_at.is_none());
+    }
+
     #[test]
     fn test_environment_variable() {
         let env_var = EnvironmentVariable {
@@ -167,6 +436,9 @@ mod tests {
                 protocol: "tcp".to_string(),
             }],
             restart_policy: Some("unless-stopped".to_string()),
+            mounts: vec![],
+            network: Some("app-net".to_string()),
+            network_aliases: vec!["web".to_string()],
         };
 
         let json = serde_json::to_string(&request).expect("Should serialize");
@@ -181,6 +453,83 @@ mod tests {
             deserialized.restart_policy,
             Some("unless-stopped".to_string())
         );
+        assert_eq!(deserialized.network, Some("app-net".to_string()));
+        assert_eq!(deserialized.network_aliases, vec!["web".to_string()]);
+    }
+
+    #[test]
+    fn test_container_removal_options_defaults() {
+        // Missing fields (e.g. unchecked form checkboxes) default to false
+        let options: ContainerRemovalOptions =
+            serde_json::from_str("{}").expect("Should deserialize");
+        assert!(!options.force);
+        assert!(!options.remove_volumes);
+
+        let options: ContainerRemovalOptions =
+            serde_urlencoded::from_str("force=true&remove_volumes=true")
+                .expect("Should deserialize");
+        assert!(options.force);
+        assert!(options.remove_volumes);
+    }
+
+    #[test]
+    fn test_pull_image_request_minimal() {
+        let request: PullImageRequest =
+            serde_json::from_str(r#"{"image": "localhost:5000/my-app"}"#)
+                .expect("Should deserialize");
+
+        assert_eq!(request.image, "localhost:5000/my-app");
+        assert!(request.tag.is_none());
+        assert!(request.username.is_none());
+        assert!(request.password.is_none());
+        assert!(request.server_address.is_none());
+    }
+
+    #[test]
+    fn test_pull_progress_serialization() {
+        let progress = PullProgress {
+            layer_id: Some("a1b2c3d4".to_string()),
+            status: "Downloading".to_string(),
+            current: Some(1024),
+            total: Some(4096),
+            error: None,
+            complete: false,
+        };
+
+        let json = serde_json::to_string(&progress).expect("Should serialize");
+        let deserialized: PullProgress = serde_json::from_str(&json).expect("Should deserialize");
+
+        assert_eq!(deserialized.layer_id, Some("a1b2c3d4".to_string()));
+        assert_eq!(deserialized.status, "Downloading");
+        assert_eq!(deserialized.current, Some(1024));
+        assert_eq!(deserialized.total, Some(4096));
+        assert!(!deserialized.complete);
+    }
+
+    #[test]
+    fn test_exec_client_message() {
+        let input: ExecClientMessage =
+            serde_json::from_str(r#"{"type": "input", "data": "ls -la\r"}"#)
+                .expect("Should deserialize");
+        assert_eq!(
+            input,
+            ExecClientMessage::Input {
+                data: "ls -la\r".to_string()
+            }
+        );
+
+        let resize: ExecClientMessage =
+            serde_json::from_str(r#"{"type": "resize", "cols": 120, "rows": 40}"#)
+                .expect("Should deserialize");
+        assert_eq!(
+            resize,
+            ExecClientMessage::Resize {
+                cols: 120,
+                rows: 40
+            }
+        );
+
+        assert!(serde_json::from_str::<ExecClientMessage>(r#"{"type": "unknown"}"#).is_err());
     }
 
     #[test]
@@ -188,6 +537,7 @@ mod tests {
         let metrics = ContainerMetrics {
             container_id: "container123".to_string(),
             container_name: "test-app".to_string(),
+            image: "nginx:latest".to_string(),
             timestamp: Utc::now(),
             cpu_usage_percent: 25.5,
             memory_usage_mb: 512.0,
@@ -245,6 +595,7 @@ mod tests {
             containers: vec![ContainerMetrics {
                 container_id: "test123".to_string(),
                 container_name: "app".to_string(),
+                image: "nginx:latest".to_string(),
                 timestamp: Utc::now(),
                 cpu_usage_percent: 10.0,
                 memory_usage_mb: 256.0,
@@ -272,6 +623,7 @@ mod tests {
         let image = LocalImageSummary {
             id: "sha256:abc123".to_string(),
             repo_tags: vec!["ubuntu:latest".to_string(), "ubuntu:22.04".to_string()],
+            size_bytes: 77_000_000,
         };
 
         let json = serde_json::to_string(&image).expect("Should serialize");
@@ -280,8 +632,236 @@ mod tests {
 
         assert_eq!(deserialized.id, "sha256:abc123");
         assert_eq!(deserialized.repo_tags.len(), 2);
+        assert_eq!(deserialized.size_bytes, 77_000_000);
         assert!(deserialized
             .repo_tags
             .contains(&"ubuntu:latest".to_string()));
     }
+
+    #[test]
+    fn test_image_removal_options_defaults() {
+        let options: ImageRemovalOptions = serde_json::from_str("{}").expect("Should deserialize");
+        assert!(!options.force);
+        assert!(!options.no_prune);
+
+        let options: ImageRemovalOptions =
+            serde_urlencoded::from_str("force=true").expect("Should deserialize");
+        assert!(options.force);
+        assert!(!options.no_prune);
+    }
+
+    #[test]
+    fn test_image_prune_preview() {
+        let preview = ImagePrunePreview {
+            images: vec![LocalImageSummary {
+                id: "sha256:dangling".to_string(),
+                repo_tags: vec![],
+                size_bytes: 1024,
+            }],
+            reclaimable_bytes: 1024,
+        };
+
+        let json = serde_json::to_string(&preview).expect("Should serialize");
+        let deserialized: ImagePrunePreview =
+            serde_json::from_str(&json).expect("Should deserialize");
+
+        assert_eq!(deserialized.images.len(), 1);
+        assert!(deserialized.images[0].repo_tags.is_empty());
+        assert_eq!(deserialized.reclaimable_bytes, 1024);
+    }
+
+    #[test]
+    fn test_volume_summary_serialization() {
+        let volume = VolumeSummary {
+            name: "pgdata".to_string(),
+            driver: "local".to_string(),
+            mountpoint: "/var/lib/docker/volumes/pgdata/_data".to_string(),
+            scope: "local".to_string(),
+            created_at: Some(Utc::now()),
+            labels: HashMap::from([("app".to_string(), "db".to_string())]),
+            options: HashMap::new(),
+            used_by: vec![VolumeUsage {
+                container_id: "abc123".to_string(),
+                container_name: "postgres".to_string(),
+                container_state: "running".to_string(),
+                destination: "/var/lib/postgresql/data".to_string(),
+            }],
+        };
+
+        let json = serde_json::to_string(&volume).expect("Should serialize");
+        let deserialized: VolumeSummary = serde_json::from_str(&json).expect("Should deserialize");
+
+        assert_eq!(deserialized.name, "pgdata");
+        assert_eq!(deserialized.labels.get("app"), Some(&"db".to_string()));
+        assert_eq!(deserialized.used_by.len(), 1);
+        assert_eq!(deserialized.used_by[0].container_name, "postgres");
+    }
+
+    #[test]
+    fn test_create_volume_request_minimal() {
+        let request: CreateVolumeRequest =
+            serde_json::from_str(r#"{"name": "cache"}"#).expect("Should deserialize");
+
+        assert_eq!(request.name, "cache");
+        assert!(request.driver.is_none());
+        assert!(request.labels.is_empty());
+    }
+
+    #[test]
+    fn test_container_mount_deserialization() {
+        let json = r#"{
+            "image_name": "postgres:16",
+            "container_name": null,
+            "environment_variables": [],
+            "port_mappings": [],
+            "restart_policy": null,
+            "mounts": [
+                {"type": "volume", "source": "pgdata", "target": "/var/lib/postgresql/data"},
+                {"type": "bind", "source": "/etc/pg", "target": "/etc/postgresql", "read_only": true},
+                {"type": "tmpfs", "target": "/tmp", "tmpfs_size_bytes": 67108864}
+            ]
+        }"#;
+
+        let request: CreateContainerRequest =
+            serde_json::from_str(json).expect("Should deserialize");
+
+        assert_eq!(request.mounts.len(), 3);
+        assert_eq!(request.mounts[0].mount_type, ContainerMountType::Volume);
+        assert!(!request.mounts[0].read_only);
+        assert_eq!(request.mounts[1].mount_type, ContainerMountType::Bind);
+        assert!(request.mounts[1].read_only);
+        assert_eq!(request.mounts[2].mount_type, ContainerMountType::Tmpfs);
+        assert_eq!(request.mounts[2].source, None);
+        assert_eq!(request.mounts[2].tmpfs_size_bytes, Some(67108864));
+    }
+
+    #[test]
+    fn test_create_container_request_without_mounts() {
+        let json = r#"{
+            "image_name": "nginx",
+            "container_name": null,
+            "environment_variables": [],
+            "port_mappings": [],
+            "restart_policy": null
+        }"#;
+
+        let request: CreateContainerRequest =
+            serde_json::from_str(json).expect("Should deserialize");
+        assert!(request.mounts.is_empty());
+        assert_eq!(request.network, None);
+        assert!(request.network_aliases.is_empty());
+    }
+
+    #[test]
+    fn test_network_summary_serialization() {
+        let network = NetworkSummary {
+            id: "net123".to_string(),
+            name: "app-net".to_string(),
+            driver: "bridge".to_string(),
+            scope: "local".to_string(),
+            internal: false,
+            attachable: true,
+            created_at: Some(Utc::now()),
+            subnets: vec!["172.20.0.0/16".to_string()],
+            labels: HashMap::new(),
+            containers: vec![NetworkAttachment {
+                container_id: "abc123".to_string(),
+                container_name: "postgres".to_string(),
+                container_state: "running".to_string(),
+                ip_address: Some("172.20.0.2".to_string()),
+                aliases: vec!["db".to_string()],
+            }],
+        };
+
+        let json = serde_json::to_string(&network).expect("Should serialize");
+        let deserialized: NetworkSummary = serde_json::from_str(&json).expect("Should deserialize");
+
+        assert_eq!(deserialized.name, "app-net");
+        assert_eq!(deserialized.subnets, vec!["172.20.0.0/16".to_string()]);
+        assert_eq!(deserialized.containers[0].aliases, vec!["db".to_string()]);
+    }
+
+    #[test]
+    fn test_network_requests_minimal() {
+        let create: CreateNetworkRequest =
+            serde_json::from_str(r#"{"name": "app-net", "driver": null}"#)
+                .expect("Should deserialize");
+        assert!(!create.internal);
+        assert!(create.labels.is_empty());
+
+        let connect: NetworkConnectRequest =
+            serde_json::from_str(r#"{"container": "web"}"#).expect("Should deserialize");
+        assert_eq!(connect.container, "web");
+        assert!(connect.aliases.is_empty());
+
+        let disconnect: NetworkDisconnectRequest =
+            serde_json::from_str(r#"{"container": "web"}"#).expect("Should deserialize");
+        assert!(!disconnect.force);
+    }
+
+    #[test]
+    fn test_metrics_history_response_serialization() {
+        let response = MetricsHistoryResponse {
+            limit: 20,
+            samples: vec![MetricsResponse {
+                system: SystemMetrics {
+                    timestamp: Utc::now(),
+                    total_containers: 1,
+                    running_containers: 1,
+                    total_images: 2,
+                    docker_version: "24.0.0".to_string(),
+                },
+                containers: vec![],
+            }],
+        };
+
+        let json = serde_json::to_string(&response).expect("Should serialize");
+        let deserialized: MetricsHistoryResponse =
+            serde_json::from_str(&json).expect("Should deserialize");
+
+        assert_eq!(deserialized.limit, 20);
+        assert_eq!(deserialized.samples.len(), 1);
+        assert_eq!(deserialized.samples[0].system.total_images, 2);
+    }
+
+    #[test]
+    fn test_docker_event_serialization() {
+        let event = DockerEvent {
+            timestamp: Utc::now(),
+            kind: "container".to_string(),
+            action: "die".to_string(),
+            id: "abc123".to_string(),
+            name: Some("web".to_string()),
+            image: Some("nginx:latest".to_string()),
+            exit_code: Some(137),
+        };
+
+        let json = serde_json::to_string(&event).expect("Should serialize");
+        assert!(json.contains("\"action\":\"die\""));
+        assert!(json.contains("\"exit_code\":137"));
+
+        let parsed: DockerEvent = serde_json::from_str(&json).expect("Should deserialize");
+        assert_eq!(parsed, event);
+    }
+
+    #[test]
+    fn test_timeline_entry_serialization() {
+        let entry = TimelineEntry {
+            timestamp: Utc::now(),
+            source: TimelineSource::User,
+            kind: "container".to_string(),
+            action: "stop".to_string(),
+            target: "web".to_string(),
+            name: None,
+            username: Some("admin".to_string()),
+            error: None,
+            details: None,
+        };
+
+        let json = serde_json::to_string(&entry).expect("Should serialize");
+        assert!(json.contains("\"source\":\"user\""));
+
+        let parsed: TimelineEntry = serde_json::from_str(&json).expect("Should deserialize");
+        assert_eq!(parsed, entry);
+    }
 }
@@ -0,0 +1,467 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::http::{HeaderMap, HeaderValue};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::auth::{constant_time_eq, role_for_groups, Role};
+use crate::config::Config;
+
+/// How long a user has to finish signing in at the identity provider
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Holds a hash of the sign-in's state, tying the callback to the browser that started it
+const STATE_COOKIE: &str = "oidc_state";
+
+/// Sign-ins in progress kept at once, so unauthenticated requests cannot exhaust memory
+const MAX_PENDING_LOGINS: usize = 1000;
+
+/// Allowed clock difference when checking ID token expiry
+const CLOCK_SKEW_SECONDS: i64 = 60;
+
+/// Timeout for requests to the identity provider
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The endpoints published in the provider's discovery document
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug)]
+struct PendingLogin {
+    verifier: String, // PKCE code verifier, proving the callback belongs to this sign-in
+    nonce: String,
+    started: Instant,
+}
+
+/// A user the identity provider has vouched for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcIdentity {
+    pub username: String,
+    pub role: Role,
+}
+
+/// OpenID Connect sign-in using the authorization code flow with PKCE. The provider's
+/// endpoints are discovered on first use, so the manager starts even if the provider is
+/// briefly unreachable.
+#[derive(Debug)]
+pub struct OidcClient {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_url: String,
+    scopes: String,
+    username_claim: String,
+    groups_claim: String,
+    admin_groups: Vec<String>,
+    operator_groups: Vec<String>,
+    viewer_groups: Vec<String>,
+    default_role: Option<Role>,
+    pub provider_name: String,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    pending: Mutex<HashMap<String, PendingLogin>>, // Keyed by the `state` parameter
+}
+
+impl OidcClient {
+    /// The configured client, or `None` when single sign-on is not set up
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let non_empty = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+        let issuer = non_empty(&config.oidc_issuer)?;
+        let client_id = non_empty(&config.oidc_client_id)?;
+        let redirect_url = non_empty(&config.oidc_redirect_url)?;
+
+        let http = reqwest::Client::builder()
+            .timeout(PROVIDER_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        Some(Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id,
+            client_secret: non_empty(&config.oidc_client_secret),
+            redirect_url,
+            scopes: config.oidc_scopes.clone(),
+            username_claim: config.oidc_username_claim.clone(),
+            groups_claim: config.oidc_groups_claim.clone(),
+            admin_groups: config.oidc_admin_groups.clone(),
+            operator_groups: config.oidc_operator_groups.clone(),
+            viewer_groups: config.oidc_viewer_groups.clone(),
+            default_role: config.oidc_default_role,
+            provider_name: config.oidc_provider_name.clone(),
+            http,
+            metadata: RwLock::new(None),
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Start a sign-in, returning the provider URL to send the browser to and the state the
+    /// callback will carry
+    pub async fn authorization_url(&self) -> Result<(String, String), String> {
+        let metadata = self.metadata().await?;
+        let state = random_token();
+        let nonce = random_token();
+        let verifier = random_token();
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+        {
+            let mut pending = self.pending.lock().await;
+            pending.retain(|_, login| login.started.elapsed() < LOGIN_TIMEOUT);
+            if pending.len() >= MAX_PENDING_LOGINS {
+                return Err("Too many sign-ins in progress; try again shortly".to_string());
+            }
+            pending.insert(
+                state.clone(),
+                PendingLogin {
+                    verifier,
+                    nonce: nonce.clone(),
+                    started: Instant::now(),
+                },
+            );
+        }
+
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("scope", self.scopes.as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| format!("Invalid authorization endpoint: {}", e))?;
+        Ok((url.into(), state))
+    }
+
+    /// Finish a sign-in from the provider's callback: exchange the code for an ID token and
+    /// work out who the user is and which role they get
+    pub async fn complete(&self, code: &str, state: &str) -> Result<OidcIdentity, String> {
+        let login = self
+            .pending
+            .lock()
+            .await
+            .remove(state)
+            .filter(|login| login.started.elapsed() < LOGIN_TIMEOUT)
+            .ok_or_else(|| "This sign-in has expired or was already used".to_string())?;
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_url.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", login.verifier.as_str()),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("Token request failed: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "Token request failed with {}: {}",
+                status,
+                body.chars().take(200).collect::<String>()
+            ));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid token response: {}", e))?;
+
+        let claims = self.validate_id_token(&tokens.id_token, &metadata, &login.nonce)?;
+        self.identity_from_claims(&claims)
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata, String> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Cannot reach identity provider: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid discovery document at {}: {}", url, e))?;
+        if metadata.issuer.trim_end_matches('/') != self.issuer {
+            return Err(format!(
+                "Identity provider reports issuer {}, expected {}",
+                metadata.issuer, self.issuer
+            ));
+        }
+
+        tracing::info!("Discovered OpenID Connect provider {}", self.issuer);
+        *self.metadata.write().await = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Check the ID token's claims. The token comes straight from the token endpoint over a
+    /// connection the manager opened itself, so, as the OpenID Connect spec allows, the
+    /// connection rather than the token signature establishes who issued it.
+    fn validate_id_token(
+        &self,
+        id_token: &str,
+        metadata: &ProviderMetadata,
+        nonce: &str,
+    ) -> Result<Value, String> {
+        let payload = id_token
+            .split('.')
+            .nth(1)
+            .ok_or_else(|| "ID token is not a JWT".to_string())?;
+        let claims: Value = URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| "ID token payload is not valid JSON".to_string())?;
+
+        if claims["iss"].as_str() != Some(metadata.issuer.as_str()) {
+            return Err("ID token was issued by a different provider".to_string());
+        }
+        let audience_ok = match &claims["aud"] {
+            Value::String(aud) => *aud == self.client_id,
+            Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(&self.client_id)),
+            _ => false,
+        };
+        let azp_ok = claims["azp"]
+            .as_str()
+            .is_none_or(|azp| azp == self.client_id);
+        if !audience_ok || !azp_ok {
+            return Err("ID token was issued to a different client".to_string());
+        }
+        let expires = claims["exp"].as_i64().unwrap_or(0);
+        if expires + CLOCK_SKEW_SECONDS < Utc::now().timestamp() {
+            return Err("ID token has expired".to_string());
+        }
+        if claims["nonce"].as_str() != Some(nonce) {
+            return Err("ID token does not belong to this sign-in".to_string());
+        }
+        Ok(claims)
+    }
+
+    /// The user's name and role from their claims. The highest role any of their groups
+    /// maps to wins; users in no mapped group get the default role, if one is configured.
+    fn identity_from_claims(&self, claims: &Value) -> Result<OidcIdentity, String> {
+        let username = claims[self.username_claim.as_str()]
+            .as_str()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "The identity provider did not send the '{}' claim",
+                    self.username_claim
+                )
+            })?
+            .to_string();
+
+        let groups: Vec<&str> = match &claims[self.groups_claim.as_str()] {
+            Value::String(group) => vec![group.as_str()],
+            Value::Array(groups) => groups.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let role = role_for_groups(
+            &groups,
+            &self.admin_groups,
+            &self.operator_groups,
+            &self.viewer_groups,
+            self.default_role,
+        );
+
+        match role {
+            Some(role) => Ok(OidcIdentity { username, role }),
+            None => Err(format!(
+                "{} is not in any group that may use this manager",
+                username
+            )),
+        }
+    }
+}
+
+/// The `Set-Cookie` value that ties a sign-in to this browser. Without it, anyone could
+/// start a sign-in and get a victim to open its callback link, signing them in as the
+/// attacker. `SameSite=Lax` lets the cookie come along on the redirect from the provider.
+pub fn state_cookie(state: &str, config: &Config) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "{}={}; HttpOnly; SameSite=Lax; Path=/auth/oidc; Max-Age={}{}",
+        STATE_COOKIE,
+        state_digest(state),
+        LOGIN_TIMEOUT.as_secs(),
+        if config.tls_enabled() { "; Secure" } else { "" }
+    ))
+    .unwrap()
+}
+
+/// The `Set-Cookie` value that removes the state cookie once the sign-in is over
+pub fn clear_state_cookie(config: &Config) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "{}=; HttpOnly; SameSite=Lax; Path=/auth/oidc; Max-Age=0{}",
+        STATE_COOKIE,
+        if config.tls_enabled() { "; Secure" } else { "" }
+    ))
+    .unwrap()
+}
+
+/// Whether the request carries the state cookie of the sign-in it completes
+pub fn state_cookie_matches(headers: &HeaderMap, state: &str) -> bool {
+    let expected = state_digest(state);
+    headers
+        .get_all("cookie")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))
+        .any(|value| constant_time_eq(value.as_bytes(), expected.as_bytes()))
+}
+
+fn state_digest(state: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(state.as_bytes()))
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn client() -> OidcClient {
+        OidcClient::from_config(&Config {
+            oidc_issuer: Some("https://idp.example.com/".to_string()),
+            oidc_client_id: Some("sdm".to_string()),
+            oidc_redirect_url: Some("https://sdm.example.com/auth/oidc/callback".to_string()),
+            oidc_admin_groups: vec!["platform".to_string()],
+            oidc_operator_groups: vec!["developers".to_string()],
+            ..Config::default()
+        })
+        .unwrap()
+    }
+
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata {
+            issuer: "https://idp.example.com".to_string(),
+            authorization_endpoint: "https://idp.example.com/authorize".to_string(),
+            token_endpoint: "https://idp.example.com/token".to_string(),
+        }
+    }
+
+    fn id_token(claims: Value) -> String {
+        format!(
+            "eyJhbGciOiJSUzI1NiJ9.{}.signature",
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    #[test]
+    fn test_requires_issuer_client_and_redirect() {
+        assert!(OidcClient::from_config(&Config::default()).is_none());
+        assert!(OidcClient::from_config(&Config {
+            oidc_issuer: Some("https://idp.example.com".to_string()),
+            oidc_client_id: Some("sdm".to_string()),
+            ..Config::default()
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn test_groups_map_to_highest_role() {
+        let client = client();
+        let identity = client
+            .identity_from_claims(&json!({
+                "preferred_username": "carol",
+                "groups": ["developers", "platform"],
+            }))
+            .unwrap();
+        assert_eq!(identity.username, "carol");
+        assert_eq!(identity.role, Role::Admin);
+
+        let identity = client
+            .identity_from_claims(&json!({"preferred_username": "dave", "groups": "developers"}))
+            .unwrap();
+        assert_eq!(identity.role, Role::Operator);
+
+        // No mapped group and no default role
+        assert!(client
+            .identity_from_claims(&json!({"preferred_username": "eve", "groups": ["sales"]}))
+            .is_err());
+        assert!(client
+            .identity_from_claims(&json!({"groups": ["platform"]}))
+            .is_err());
+
+        let with_default = OidcClient {
+            default_role: Some(Role::Viewer),
+            ..client
+        };
+        let identity = with_default
+            .identity_from_claims(&json!({"preferred_username": "eve"}))
+            .unwrap();
+        assert_eq!(identity.role, Role::Viewer);
+    }
+
+    #[test]
+    fn test_id_token_claims_are_checked() {
+        let client = client();
+        let valid = json!({
+            "iss": "https://idp.example.com",
+            "aud": ["sdm", "other"],
+            "azp": "sdm",
+            "exp": Utc::now().timestamp() + 300,
+            "nonce": "n-1",
+            "preferred_username": "carol",
+        });
+        assert!(client
+            .validate_id_token(&id_token(valid.clone()), &metadata(), "n-1")
+            .is_ok());
+
+        let with = |key: &str, value: Value| {
+            let mut claims = valid.clone();
+            claims[key] = value;
+            id_token(claims)
+        };
+        for token in [
+            with("iss", json!("https://evil.example.com")),
+            with("aud", json!("other")),
+            with("azp", json!("other")),
+            with("exp", json!(Utc::now().timestamp() - 3600)),
+            with("nonce", json!("n-2")),
+            "not-a-jwt".to_string(),
+        ] {
+            assert!(client
+                .validate_id_token(&token, &metadata(), "n-1")
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use askama::Template;
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::time::SystemTime;
+
+use crate::auth::{Role, Session};
+use crate::config::Config;
+use crate::models::{
+    ContainerEnvironmentVariable, ContainerStateSummary, ContainerSummary, ImagePrunePreview,
+    LocalImageSummary, NetworkSummary, TimelineEntry, VolumeSummary,
+};
+use crate::tokens::ApiToken;
+use crate::totp::TotpSetup;
+
+/// Render a page. Templates are checked at compile time, so this only fails if a value
+/// cannot be formatted.
+pub fn render(page: &impl Template) -> Response {
+    match page.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to render page: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to render page").into_response()
+        }
+    }
+}
+
+/// A page explaining why a form submission failed, linking back to where it came from
+pub fn error_page(message: impl Into<String>, back: &'static str) -> Response {
+    render(&ErrorPage {
+        message: message.into(),
+        back,
+    })
+}
+
+/// The navigation bar shared by the pages behind the login, and the CSRF token their
+/// POST forms and scripts send back
+pub struct Nav {
+    pub auth_enabled: bool,                   // Shows the logout button
+    pub link: Option<(String, &'static str)>, // Page-specific link after the shared ones
+    pub csrf_token: Option<String>,           // Set for browser sessions
+}
+
+impl Nav {
+    pub fn new(config: &Config, session: Option<&Session>) -> Self {
+        Self {
+            auth_enabled: config.auth_enabled,
+            link: None,
+            csrf_token: session
+                .filter(|session| session.token_id.is_none())
+                .map(|session| session.csrf_token.clone()),
+        }
+    }
+
+    pub fn with_link(mut self, href: impl Into<String>, label: &'static str) -> Self {
+        self.link = Some((href.into(), label));
+        self
+    }
+}
+
+/// The outcome of a form, shown above the page it returns to
+pub enum Notice {
+    Error(String),
+    TokenIssued {
+        name: String,
+        secret: String,
+    }, // The secret is only ever shown here
+    RecoveryCodes {
+        message: &'static str,
+        codes: Vec<String>,
+    },
+}
+
+pub struct SelectOption {
+    pub value: &'static str,
+    pub label: &'static str,
+    pub selected: bool,
+}
+
+impl SelectOption {
+    /// `(value, label)` pairs, with the one whose value is `selected` selected
+    pub fn list(options: &[(&'static str, &'static str)], selected: Option<&str>) -> Vec<Self> {
+        options
+            .iter()
+            .map(|&(value, label)| Self {
+                value,
+                label,
+                selected: selected.unwrap_or("") == value,
+            })
+            .collect()
+    }
+}
+
+/// A running container with its environment split into variables, secret values withheld
+pub struct RunningContainer {
+    pub container: ContainerSummary,
+    pub environment: Vec<ContainerEnvironmentVariable>,
+}
+
+#[derive(Template)]
+#[template(path = "management.html")]
+pub struct ManagementPage {
+    pub nav: Nav,
+    pub running_containers: Result<Vec<RunningContainer>, String>,
+    pub containers: Result<Vec<ContainerStateSummary>, String>,
+    pub images: Result<Vec<LocalImageSummary>, String>,
+    pub prune_preview: Result<ImagePrunePreview, String>,
+    pub can_reveal: bool, // Offer to reveal secret environment values
+}
+
+#[derive(Template)]
+#[template(path = "dashboard.html")]
+pub struct DashboardPage {
+    pub nav: Nav,
+}
+
+#[derive(Template)]
+#[template(path = "volumes.html")]
+pub struct VolumesPage {
+    pub nav: Nav,
+    pub volumes: Result<Vec<VolumeSummary>, String>,
+}
+
+#[derive(Template)]
+#[template(path = "networks.html")]
+pub struct NetworksPage {
+    pub nav: Nav,
+    pub networks: Result<Vec<NetworkSummary>, String>,
+    pub containers: Vec<ContainerStateSummary>, // Offered when connecting a container
+}
+
+#[derive(Template)]
+#[template(path = "events.html")]
+pub struct EventsPage {
+    pub nav: Nav,
+    pub source_options: Vec<SelectOption>,
+    pub kind_options: Vec<SelectOption>,
+    pub action: String,
+    pub target: String,
+    pub username: String,
+    pub from: String,
+    pub to: String,
+    pub limit: String,
+    pub entries: Result<Vec<TimelineEntry>, String>, // Err when the filter is invalid
+}
+
+#[derive(Template)]
+#[template(path = "logs.html")]
+pub struct LogsPage {
+    pub nav: Nav,
+    pub container_id: String,
+    pub container_name: String,
+    pub tail: String,
+    pub logs: String,
+}
+
+#[derive(Template)]
+#[template(path = "exec.html")]
+pub struct ExecPage {
+    pub nav: Nav,
+    pub container_id: String,
+    pub container_name: String,
+    pub shell_options: Vec<SelectOption>,
+}
+
+#[derive(Template)]
+#[template(path = "tokens.html")]
+pub struct TokensPage {
+    pub nav: Nav,
+    pub notice: Option<Notice>,
+    pub role_options: Vec<SelectOption>,
+    pub tokens: Vec<ApiToken>,
+}
+
+/// A session in the sessions list
+pub struct SessionRow {
+    pub session: Session,
+    pub current: bool, // The session viewing the page
+    pub expires_at: SystemTime,
+}
+
+#[derive(Template)]
+#[template(path = "sessions.html")]
+pub struct SessionsPage {
+    pub nav: Nav,
+    pub notice: Option<Notice>,
+    pub sessions: Vec<SessionRow>,
+}
+
+/// The QR code and key for adding a two-factor secret to an authenticator app
+pub struct AuthenticatorSetup {
+    pub qr_code: String, // data: URI
+    pub secret: String,
+}
+
+impl AuthenticatorSetup {
+    pub fn new(setup: &TotpSetup, username: &str) -> Self {
+        Self {
+            qr_code: setup.qr_code_data_uri(username),
+            secret: setup.secret.clone(),
+        }
+    }
+}
+
+pub enum TwoFactorStatus {
+    On {
+        enabled_at: DateTime<Utc>,
+        recovery_codes_left: usize,
+        required_by: Option<Role>, // Set when the user's role does not allow turning it off
+    },
+    SettingUp(AuthenticatorSetup),
+    Off {
+        required: bool,
+    },
+}
+
+#[derive(Template)]
+#[template(path = "two_factor.html")]
+pub struct TwoFactorPage {
+    pub nav: Nav,
+    pub notice: Option<Notice>,
+    pub status: Option<TwoFactorStatus>, // None when the account has no two-factor settings here
+}
+
+pub enum LoginError {
+    Message(String),
+    LockedOut(String), // How long to wait, e.g. "5 minutes"
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+pub struct LoginPage {
+    pub sso_provider: Option<String>, // Offers single sign-on when set
+    pub error: Option<LoginError>,
+}
+
+pub enum TwoFactorLoginStep {
+    Code {
+        challenge_id: String,
+        username: String,
+        setup: Option<AuthenticatorSetup>, // Set when the user has to enroll first
+    },
+    RecoveryCodes(Vec<String>), // Shown once, after enrolling while signing in
+}
+
+#[derive(Template)]
+#[template(path = "login_two_factor.html")]
+pub struct TwoFactorLoginPage {
+    pub step: TwoFactorLoginStep,
+    pub error: Option<String>,
+}
+
+/// Continues to the management page after single sign-on. The browser arrives from the
+/// identity provider's site, so it would not send the `SameSite=Strict` cookie on a redirect.
+#[derive(Template)]
+#[template(path = "signed_in.html")]
+pub struct SignedInPage;
+
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorPage {
+    pub message: String,
+    pub back: &'static str,
+}
+
+mod filters {
+    use chrono::{DateTime, Utc};
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+
+    use crate::models::{ContainerStateSummary, LocalImageSummary, TimelineSource};
+
+    pub fn status_class(status: &str, _: &dyn askama::Values) -> askama::Result<&'static str> {
+        Ok(match status.to_lowercase().as_str() {
+            s if s.contains("running") || s.contains("up") => "status-running",
+            s if s.contains("exited") || s.contains("stopped") || s.contains("created") => {
+                "status-exited"
+            }
+            _ => "status-other",
+        })
+    }
+
+    pub fn timestamp(
+        timestamp: &Option<DateTime<Utc>>,
+        _: &dyn askama::Values,
+    ) -> askama::Result<String> {
+        Ok(timestamp
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| "—".to_string()))
+    }
+
+    pub fn system_time(time: &SystemTime, _: &dyn askama::Values) -> askama::Result<String> {
+        Ok(DateTime::<Utc>::from(*time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string())
+    }
+
+    pub fn bytes(bytes: &i64, _: &dyn askama::Values) -> askama::Result<String> {
+        const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+        let mut value = (*bytes).max(0) as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        Ok(if unit == 0 {
+            format!("{} {}", (*bytes).max(0), UNITS[0])
+        } else {
+            format!("{:.2} {}", value, UNITS[unit])
+        })
+    }
+
+    /// The exit code of a container that has stopped
+    pub fn exit_code(
+        container: &ContainerStateSummary,
+        _: &dyn askama::Values,
+    ) -> askama::Result<String> {
+        Ok(container
+            .exit_code
+            .filter(|_| container.state != "running" && container.state != "created")
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "—".to_string()))
+    }
+
+    /// The tag an image is started and shown by, or its short ID if it is dangling
+    pub fn display_tag(
+        image: &LocalImageSummary,
+        values: &dyn askama::Values,
+    ) -> askama::Result<String> {
+        match image.repo_tags.first() {
+            Some(tag) => Ok(tag.clone()),
+            None => short_id(&image.id, values),
+        }
+    }
+
+    /// Networks Docker creates itself; they cannot be removed
+    pub fn is_predefined_network(name: &str, _: &dyn askama::Values) -> askama::Result<bool> {
+        Ok(matches!(name, "bridge" | "host" | "none"))
+    }
+
+    pub fn short_id(id: &str, _: &dyn askama::Values) -> askama::Result<String> {
+        let id = id.strip_prefix("sha256:").unwrap_or(id);
+        Ok(id.chars().take(12).collect())
+    }
+
+    pub fn source_name(
+        source: &TimelineSource,
+        _: &dyn askama::Values,
+    ) -> askama::Result<&'static str> {
+        Ok(match source {
+            TimelineSource::Docker => "docker",
+            TimelineSource::User => "user",
+        })
+    }
+
+    /// Labels and options in a stable order
+    pub fn sorted<'a>(
+        map: &'a HashMap<String, String>,
+        _: &dyn askama::Values,
+    ) -> askama::Result<Vec<(&'a String, &'a String)>> {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort();
+        Ok(entries)
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write;
+
+use crate::models::{ContainerMetrics, MetricsResponse};
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+/// One per-container metric family and how to read its value
+struct ContainerMetric {
+    name: &'static str,
+    metric_type: &'static str,
+    help: &'static str,
+    value: fn(&ContainerMetrics) -> f64,
+}
+
+const CONTAINER_METRICS: [ContainerMetric; 9] = [
+    ContainerMetric {
+        name: "sdm_container_cpu_usage_percent",
+        metric_type: "gauge",
+        help: "CPU usage as a percentage of one core",
+        value: |m| m.cpu_usage_percent,
+    },
+    ContainerMetric {
+        name: "sdm_container_memory_usage_bytes",
+        metric_type: "gauge",
+        help: "Memory usage in bytes",
+        value: |m| (m.memory_usage_mb * BYTES_PER_MB).round(),
+    },
+    ContainerMetric {
+        name: "sdm_container_memory_limit_bytes",
+        metric_type: "gauge",
+        help: "Memory limit in bytes",
+        value: |m| (m.memory_limit_mb * BYTES_PER_MB).round(),
+    },
+    ContainerMetric {
+        name: "sdm_container_memory_usage_percent",
+        metric_type: "gauge",
+        help: "Memory usage as a percentage of the limit",
+        value: |m| m.memory_usage_percent,
+    },
+    ContainerMetric {
+        name: "sdm_container_network_receive_bytes_total",
+        metric_type: "counter",
+        help: "Bytes received across all interfaces",
+        value: |m| m.network_rx_bytes as f64,
+    },
+    ContainerMetric {
+        name: "sdm_container_network_transmit_bytes_total",
+        metric_type: "counter",
+        help: "Bytes transmitted across all interfaces",
+        value: |m| m.network_tx_bytes as f64,
+    },
+    ContainerMetric {
+        name: "sdm_container_block_read_bytes_total",
+        metric_type: "counter",
+        help: "Bytes read from block devices",
+        value: |m| m.block_read_bytes as f64,
+    },
+    ContainerMetric {
+        name: "sdm_container_block_write_bytes_total",
+        metric_type: "counter",
+        help: "Bytes written to block devices",
+        value: |m| m.block_write_bytes as f64,
+    },
+    ContainerMetric {
+        name: "sdm_container_pids",
+        metric_type: "gauge",
+        help: "Number of processes",
+        value: |m| m.pids as f64,
+    },
+];
+
+/// Render metrics in the Prometheus text exposition format (version 0.0.4)
+pub fn render(metrics: &MetricsResponse) -> String {
+    let mut out = String::new();
+    let system = &metrics.system;
+
+    write_metric(
+        &mut out,
+        "sdm_docker_info",
+        "gauge",
+        "Docker engine information",
+        [(
+            format!("{{version=\"{}\"}}", escape_label(&system.docker_version)),
+            1.0,
+        )],
+    );
+    write_metric(
+        &mut out,
+        "sdm_containers",
+        "gauge",
+        "Number of containers in any state",
+        [(String::new(), system.total_containers as f64)],
+    );
+    write_metric(
+        &mut out,
+        "sdm_containers_running",
+        "gauge",
+        "Number of running containers",
+        [(String::new(), system.running_containers as f64)],
+    );
+    write_metric(
+        &mut out,
+        "sdm_images",
+        "gauge",
+        "Number of local images",
+        [(String::new(), system.total_images as f64)],
+    );
+
+    for metric in CONTAINER_METRICS {
+        write_metric(
+            &mut out,
+            metric.name,
+            metric.metric_type,
+            metric.help,
+            metrics
+                .containers
+                .iter()
+                .map(|container| (container_labels(container), (metric.value)(container))),
+        );
+    }
+
+    out
+}
+
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    metric_type: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, f64)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+fn container_labels(container: &ContainerMetrics) -> String {
+    format!(
+        "{{container_id=\"{}\",container_name=\"{}\",image=\"{}\"}}",
+        escape_label(&container.container_id),
+        escape_label(&container.container_name),
+        escape_label(&container.image)
+    )
+}
+
+/// Escape a label value as required by the exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SystemMetrics;
+    use chrono::Utc;
+
+    fn sample_metrics() -> MetricsResponse {
+        MetricsResponse {
+            system: SystemMetrics {
+                timestamp: Utc::now(),
+                total_containers: 3,
+                running_containers: 1,
+                total_images: 4,
+                docker_version: "24.0.0".to_string(),
+            },
+            containers: vec![ContainerMetrics {
+                container_id: "abc123".to_string(),
+                container_name: "web".to_string(),
+                image: "nginx:latest".to_string(),
+                timestamp: Utc::now(),
+                cpu_usage_percent: 12.5,
+                memory_usage_mb: 64.0,
+                memory_limit_mb: 512.0,
+                memory_usage_percent: 12.5,
+                network_rx_bytes: 1000,
+                network_tx_bytes: 2000,
+                block_read_bytes: 0,
+                block_write_bytes: 4096,
+                pids: 3,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_render_system_metrics() {
+        let output = render(&sample_metrics());
+
+        assert!(output.contains("# TYPE sdm_containers gauge\nsdm_containers 3\n"));
+        assert!(output.contains("sdm_containers_running 1\n"));
+        assert!(output.contains("sdm_images 4\n"));
+        assert!(output.contains("sdm_docker_info{version=\"24.0.0\"} 1\n"));
+    }
+
+    #[test]
+    fn test_render_container_metrics() {
+        let output = render(&sample_metrics());
+        let labels = r#"{container_id="abc123",container_name="web",image="nginx:latest"}"#;
+
+        assert!(output.contains("# TYPE sdm_container_network_receive_bytes_total counter"));
+        assert!(output.contains(&format!(
+            "sdm_container_network_receive_bytes_total{} 1000\n",
+            labels
+        )));
+        assert!(output.contains(&format!(
+            "sdm_container_memory_usage_bytes{} 67108864\n",
+            labels
+        )));
+        assert!(output.contains(&format!("sdm_container_cpu_usage_percent{} 12.5\n", labels)));
+        assert!(output.contains(&format!("sdm_container_pids{} 3\n", labels)));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label("line\nbreak"), "line\\nbreak");
+    }
+}
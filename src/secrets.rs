@@ -0,0 +1,130 @@
+use crate::config::Config;
+use crate::models::ContainerEnvironmentVariable;
+
+/// Decides which container environment variables hold secrets, from name patterns such as
+/// `*PASSWORD*`. Patterns are matched case-insensitively against the whole name and `*`
+/// matches any run of characters.
+#[derive(Debug, Clone)]
+pub struct SecretMasker {
+    patterns: Vec<String>,
+}
+
+impl SecretMasker {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| pattern.trim().to_ascii_uppercase())
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.secret_env_patterns)
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        let key = key.to_ascii_uppercase();
+        self.patterns
+            .iter()
+            .any(|pattern| wildcard_match(pattern.as_bytes(), key.as_bytes()))
+    }
+
+    /// Split `KEY=value` strings, withholding the values of secrets
+    pub fn mask(&self, environment: &[String]) -> Vec<ContainerEnvironmentVariable> {
+        let variables = environment
+            .iter()
+            .map(|var| {
+                let (key, value) = match var.split_once('=') {
+                    Some((key, value)) => (key, Some(value.to_string())),
+                    None => (var.as_str(), None),
+                };
+                ContainerEnvironmentVariable {
+                    key: key.to_string(),
+                    value,
+                    masked: false,
+                }
+            })
+            .collect();
+        self.mask_variables(variables)
+    }
+
+    /// Withhold the values of secrets among variables that are already split
+    pub fn mask_variables(
+        &self,
+        variables: Vec<ContainerEnvironmentVariable>,
+    ) -> Vec<ContainerEnvironmentVariable> {
+        variables
+            .into_iter()
+            .map(|mut var| {
+                if var.value.is_some() && self.is_secret(&var.key) {
+                    var.value = None;
+                    var.masked = true;
+                }
+                var
+            })
+            .collect()
+    }
+}
+
+/// The value of `key` in `KEY=value` strings
+pub fn env_value<'a>(environment: &'a [String], key: &str) -> Option<&'a str> {
+    environment
+        .iter()
+        .find_map(|var| var.strip_prefix(key)?.strip_prefix('='))
+}
+
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((c, rest)) => text
+            .split_first()
+            .is_some_and(|(t, text)| t == c && wildcard_match(rest, text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_patterns_mask_secrets() {
+        let masker = SecretMasker::from_config(&Config::default());
+        assert!(masker.is_secret("POSTGRES_PASSWORD"));
+        assert!(masker.is_secret("github_token"));
+        assert!(masker.is_secret("SECRET_KEY_BASE"));
+        assert!(!masker.is_secret("PATH"));
+        assert!(!masker.is_secret("PASSWD"));
+
+        let environment = vec![
+            "DB_PASSWORD=hunter2".to_string(),
+            "TZ=UTC".to_string(),
+            "API_TOKEN".to_string(),
+        ];
+        let masked = masker.mask(&environment);
+        assert_eq!(masked[0].key, "DB_PASSWORD");
+        assert!(masked[0].masked);
+        assert_eq!(masked[0].value, None);
+        assert!(!masked[1].masked);
+        assert_eq!(masked[1].value.as_deref(), Some("UTC"));
+        assert!(!masked[2].masked); // Nothing to hide without a value
+
+        assert_eq!(env_value(&environment, "DB_PASSWORD"), Some("hunter2"));
+        assert_eq!(env_value(&environment, "DB"), None);
+        assert_eq!(env_value(&environment, "API_TOKEN"), None);
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        let masker = SecretMasker::new(&["AWS_*".to_string(), "*_KEY".to_string()]);
+        assert!(masker.is_secret("aws_access_key_id"));
+        assert!(masker.is_secret("STRIPE_KEY"));
+        assert!(!masker.is_secret("KEYCLOAK_URL"));
+        assert!(!masker.is_secret("DB_PASSWORD"));
+
+        let disabled = SecretMasker::new(&[String::new()]);
+        assert!(!disabled.is_secret("DB_PASSWORD"));
+    }
+}
@@ -0,0 +1,224 @@
+use futures_util::StreamExt;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{sync::RwLock, task::JoinHandle};
+
+use crate::backend::DockerBackend;
+use crate::config::Config;
+use crate::docker::ContainerIdentity;
+use crate::metrics_history::MetricsHistory;
+use crate::models::{ContainerMetrics, MetricsResponse, SystemMetrics};
+
+/// Long-lived metrics source: keeps one streaming stats subscription per running
+/// container and caches the latest sample from each, so readers never wait on Docker.
+#[derive(Debug)]
+pub struct StatsCollector {
+    latest: RwLock<HashMap<String, ContainerMetrics>>,
+    system: RwLock<Option<SystemMetrics>>,
+    history: MetricsHistory,
+}
+
+impl StatsCollector {
+    pub fn new(history_limit: usize) -> Self {
+        Self {
+            latest: RwLock::new(HashMap::new()),
+            system: RwLock::new(None),
+            history: MetricsHistory::new(history_limit),
+        }
+    }
+
+    pub fn history(&self) -> &MetricsHistory {
+        &self.history
+    }
+
+    /// Latest cached metrics, or `None` until the first system sample has been taken
+    pub async fn snapshot(&self) -> Option<MetricsResponse> {
+        let system = self.system.read().await.clone()?;
+        let mut containers: Vec<ContainerMetrics> =
+            self.latest.read().await.values().cloned().collect();
+
+        // Sort by name for consistent display order
+        containers.sort_by(|a, b| a.container_name.cmp(&b.container_name));
+
+        Some(MetricsResponse { system, containers })
+    }
+
+    async fn update_container(&self, metrics: ContainerMetrics) {
+        self.latest
+            .write()
+            .await
+            .insert(metrics.container_id.clone(), metrics);
+    }
+
+    async fn remove_container(&self, container_id: &str) {
+        self.latest.write().await.remove(container_id);
+    }
+
+    async fn set_system(&self, system: SystemMetrics) {
+        *self.system.write().await = Some(system);
+    }
+}
+
+/// Run the collector for the lifetime of the server. Every `metrics_interval_seconds` it
+/// reconciles stats subscriptions with the running containers, refreshes system metrics
+/// and records a snapshot into the history.
+pub fn spawn(
+    collector: Arc<StatsCollector>,
+    backend: Arc<dyn DockerBackend>,
+    config: &Config,
+) -> JoinHandle<()> {
+    let interval_seconds = config.metrics_interval_seconds.max(1);
+
+    tokio::spawn(async move {
+        let mut subscriptions: HashMap<String, JoinHandle<()>> = HashMap::new();
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_seconds));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            match backend.list_running_container_identities().await {
+                Ok(containers) => {
+                    reconcile_subscriptions(&collector, &mut subscriptions, containers, &*backend)
+                        .await
+                }
+                Err(e) => tracing::warn!("Failed to list containers for stats: {}", e),
+            }
+
+            match backend.get_system_metrics().await {
+                Ok(system) => collector.set_system(system).await,
+                Err(e) => tracing::warn!("Failed to collect system metrics: {}", e),
+            }
+
+            if let Some(snapshot) = collector.snapshot().await {
+                collector.history.record(snapshot).await;
+            }
+        }
+    })
+}
+
+/// Start subscriptions for new containers and stop those for containers that went away
+async fn reconcile_subscriptions(
+    collector: &Arc<StatsCollector>,
+    subscriptions: &mut HashMap<String, JoinHandle<()>>,
+    containers: Vec<ContainerIdentity>,
+    backend: &dyn DockerBackend,
+) {
+    let running: HashMap<String, ContainerIdentity> = containers
+        .into_iter()
+        .map(|container| (container.id.clone(), container))
+        .collect();
+
+    // Drop subscriptions whose container stopped, or whose stream ended on its own
+    let stale: Vec<String> = subscriptions
+        .iter()
+        .filter(|(id, handle)| !running.contains_key(*id) || handle.is_finished())
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in stale {
+        if let Some(handle) = subscriptions.remove(&id) {
+            handle.abort();
+        }
+        collector.remove_container(&id).await;
+    }
+
+    for (id, container) in running {
+        if subscriptions.contains_key(&id) {
+            continue;
+        }
+        match backend.stream_container_metrics(&container) {
+            Ok(mut stream) => {
+                tracing::debug!("Subscribing to stats for container {}", container.name);
+                let collector = collector.clone();
+                let handle = tokio::spawn(async move {
+                    while let Some(sample) = stream.next().await {
+                        match sample {
+                            Ok(metrics) => collector.update_container(metrics).await,
+                            Err(e) => {
+                                tracing::debug!(
+                                    "Stats stream for container {} failed: {}",
+                                    container.name,
+                                    e
+                                );
+                                break;
+                            }
+                        }
+                    }
+                    collector.remove_container(&container.id).await;
+                });
+                subscriptions.insert(id, handle);
+            }
+            Err(e) => tracing::warn!("Failed to subscribe to stats for {}: {}", id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn container_metrics(id: &str, name: &str) -> ContainerMetrics {
+        ContainerMetrics {
+            container_id: id.to_string(),
+            container_name: name.to_string(),
+            image: "nginx:latest".to_string(),
+            timestamp: Utc::now(),
+            cpu_usage_percent: 1.0,
+            memory_usage_mb: 10.0,
+            memory_limit_mb: 100.0,
+            memory_usage_percent: 10.0,
+            network_rx_bytes: 0,
+            network_tx_bytes: 0,
+            block_read_bytes: 0,
+            block_write_bytes: 0,
+            pids: 1,
+        }
+    }
+
+    fn system_metrics() -> SystemMetrics {
+        SystemMetrics {
+            timestamp: Utc::now(),
+            total_containers: 2,
+            running_containers: 2,
+            total_images: 1,
+            docker_version: "24.0.0".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_waits_for_system_metrics() {
+        let collector = StatsCollector::new(10);
+        collector
+            .update_container(container_metrics("abc", "web"))
+            .await;
+        assert!(collector.snapshot().await.is_none());
+
+        collector.set_system(system_metrics()).await;
+        let snapshot = collector.snapshot().await.expect("Should have a snapshot");
+        assert_eq!(snapshot.containers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_keeps_latest_sample_per_container() {
+        let collector = StatsCollector::new(10);
+        collector.set_system(system_metrics()).await;
+
+        collector
+            .update_container(container_metrics("b", "web"))
+            .await;
+        collector
+            .update_container(container_metrics("a", "db"))
+            .await;
+        let mut newer = container_metrics("b", "web");
+        newer.cpu_usage_percent = 50.0;
+        collector.update_container(newer).await;
+
+        let snapshot = collector.snapshot().await.unwrap();
+        assert_eq!(snapshot.containers.len(), 2);
+        assert_eq!(snapshot.containers[0].container_name, "db");
+        assert_eq!(snapshot.containers[1].cpu_usage_percent, 50.0);
+
+        collector.remove_container("a").await;
+        assert_eq!(collector.snapshot().await.unwrap().containers.len(), 1);
+    }
+}
@@ -0,0 +1,370 @@
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::models::{DockerEvent, TimelineEntry, TimelineSource};
+
+/// Entries returned by a query when the caller does not ask for a specific number
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Which timeline entries a query returns
+#[derive(Debug, Default)]
+pub struct TimelineFilter {
+    pub source: Option<TimelineSource>,
+    pub kind: Option<String>,
+    pub action: Option<String>,
+    pub target: Option<String>, // Matches the target (or its prefix, for IDs) or the name
+    pub username: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl TimelineFilter {
+    fn matches(&self, entry: &TimelineEntry) -> bool {
+        self.source.is_none_or(|source| entry.source == source)
+            && self.kind.as_deref().is_none_or(|kind| entry.kind == kind)
+            && self
+                .action
+                .as_deref()
+                .is_none_or(|action| entry.action.starts_with(action))
+            && self.target.as_deref().is_none_or(|target| {
+                entry.target.starts_with(target) || entry.name.as_deref() == Some(target)
+            })
+            && self
+                .username
+                .as_deref()
+                .is_none_or(|username| entry.username.as_deref() == Some(username))
+            && self.from.is_none_or(|from| entry.timestamp >= from)
+            && self.to.is_none_or(|to| entry.timestamp <= to)
+    }
+}
+
+/// Bounded, append-only record of Docker engine events and user actions. Entries are kept
+/// in memory for queries and appended to a JSON Lines file so they survive restarts.
+#[derive(Debug)]
+pub struct Timeline {
+    entries: RwLock<VecDeque<TimelineEntry>>,
+    limit: usize,
+    file: Option<Mutex<TimelineFile>>,
+}
+
+#[derive(Debug)]
+struct TimelineFile {
+    path: PathBuf,
+    lines: usize, // Lines in the file, which may exceed `limit` until the next compaction
+}
+
+impl Timeline {
+    /// A timeline that is not persisted, e.g. when the data directory is not writable
+    pub fn in_memory(limit: usize) -> Self {
+        Self {
+            entries: RwLock::new(VecDeque::new()),
+            limit: limit.max(1),
+            file: None,
+        }
+    }
+
+    /// Open (or create) the timeline file and load its most recent entries
+    pub fn open(path: impl AsRef<Path>, limit: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let limit = limit.max(1);
+        let mut entries = VecDeque::with_capacity(limit);
+        let mut lines = 0;
+
+        if path.exists() {
+            let reader = BufReader::new(fs::File::open(&path)?);
+            for line in reader.lines() {
+                let line = line?;
+                lines += 1;
+                match serde_json::from_str::<TimelineEntry>(&line) {
+                    Ok(entry) => {
+                        if entries.len() == limit {
+                            entries.pop_front();
+                        }
+                        entries.push_back(entry);
+                    }
+                    Err(e) => tracing::warn!("Skipping malformed timeline entry: {}", e),
+                }
+            }
+        } else {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            fs::File::create(&path)?;
+        }
+
+        Ok(Self {
+            entries: RwLock::new(entries),
+            limit,
+            file: Some(Mutex::new(TimelineFile { path, lines })),
+        })
+    }
+
+    pub async fn record(&self, entry: TimelineEntry) {
+        {
+            let mut entries = self.entries.write().await;
+            while entries.len() >= self.limit {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+
+        if let Some(file) = &self.file {
+            let mut file = file.lock().await;
+            if let Err(e) = self.persist(&mut file, &entry).await {
+                tracing::warn!(
+                    "Failed to write timeline file {}: {}",
+                    file.path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Append one entry, rewriting the file with only the retained entries once it has
+    /// grown to twice the limit
+    async fn persist(&self, file: &mut TimelineFile, entry: &TimelineEntry) -> io::Result<()> {
+        if file.lines + 1 > self.limit * 2 {
+            let temp_path = file.path.with_extension("jsonl.tmp");
+            let entries = self.entries.read().await;
+            let mut contents = String::new();
+            for entry in entries.iter() {
+                contents.push_str(&serde_json::to_string(entry)?);
+                contents.push('\n');
+            }
+            fs::write(&temp_path, contents)?;
+            fs::rename(&temp_path, &file.path)?;
+            file.lines = entries.len();
+            return Ok(());
+        }
+
+        let mut handle = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file.path)?;
+        writeln!(handle, "{}", serde_json::to_string(entry)?)?;
+        file.lines += 1;
+        Ok(())
+    }
+
+    /// Record the outcome of an action a user took through the UI or API
+    pub async fn record_action(
+        &self,
+        username: Option<&str>,
+        kind: &str,
+        action: &str,
+        target: &str,
+        details: Option<String>,
+        error: Option<String>,
+    ) {
+        self.record(TimelineEntry {
+            timestamp: Utc::now(),
+            source: TimelineSource::User,
+            kind: kind.to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            name: None,
+            username: username.map(str::to_string),
+            error,
+            details,
+        })
+        .await;
+    }
+
+    /// Matching entries, newest first
+    pub async fn query(&self, filter: &TimelineFilter) -> Vec<TimelineEntry> {
+        let entries = self.entries.read().await;
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| filter.matches(entry))
+            .take(filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT))
+            .cloned()
+            .collect()
+    }
+}
+
+fn docker_event_entry(event: DockerEvent) -> TimelineEntry {
+    TimelineEntry {
+        timestamp: event.timestamp,
+        source: TimelineSource::Docker,
+        kind: event.kind,
+        action: event.action,
+        target: event.id,
+        name: event.name,
+        username: None,
+        error: None,
+        details: event.exit_code.map(|code| format!("exit code {}", code)),
+    }
+}
+
+/// Record every Docker event relayed through the event hub
+pub fn spawn_recorder(
+    timeline: Arc<Timeline>,
+    mut receiver: broadcast::Receiver<DockerEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => timeline.record(docker_event_entry(event)).await,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Timeline missed {} Docker events", skipped)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(source: TimelineSource, action: &str, target: &str) -> TimelineEntry {
+        TimelineEntry {
+            timestamp: Utc::now(),
+            source,
+            kind: "container".to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            name: None,
+            username: None,
+            error: None,
+            details: None,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sdm-{}-{}.jsonl", name, uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_newest_first() {
+        let timeline = Timeline::in_memory(10);
+        timeline
+            .record(entry(TimelineSource::Docker, "start", "abc123"))
+            .await;
+        timeline
+            .record(entry(TimelineSource::User, "stop", "abc123"))
+            .await;
+        timeline
+            .record(entry(
+                TimelineSource::Docker,
+                "health_status: healthy",
+                "def456",
+            ))
+            .await;
+
+        let all = timeline.query(&TimelineFilter::default()).await;
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].target, "def456");
+
+        let user = timeline
+            .query(&TimelineFilter {
+                source: Some(TimelineSource::User),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(user.len(), 1);
+        assert_eq!(user[0].action, "stop");
+
+        let by_target = timeline
+            .query(&TimelineFilter {
+                target: Some("abc".to_string()),
+                limit: Some(1),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(by_target.len(), 1);
+        assert_eq!(by_target[0].action, "stop");
+
+        let health = timeline
+            .query(&TimelineFilter {
+                action: Some("health_status".to_string()),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(health.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_record_action_keeps_user_and_error() {
+        let timeline = Timeline::in_memory(10);
+        timeline
+            .record_action(
+                Some("admin"),
+                "container",
+                "stop",
+                "web",
+                None,
+                Some("No such container: web".to_string()),
+            )
+            .await;
+
+        let entries = timeline.query(&TimelineFilter::default()).await;
+        assert_eq!(entries[0].username.as_deref(), Some("admin"));
+        assert_eq!(entries[0].error.as_deref(), Some("No such container: web"));
+    }
+
+    #[tokio::test]
+    async fn test_entries_survive_reopen() {
+        let path = temp_path("reopen");
+        {
+            let timeline = Timeline::open(&path, 10).unwrap();
+            timeline
+                .record(entry(TimelineSource::User, "stop", "web"))
+                .await;
+        }
+
+        let reopened = Timeline::open(&path, 10).unwrap();
+        let entries = reopened.query(&TimelineFilter::default()).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "stop");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_file_is_compacted_to_limit() {
+        let path = temp_path("compact");
+        let timeline = Timeline::open(&path, 2).unwrap();
+        for i in 0..5 {
+            timeline
+                .record(entry(TimelineSource::Docker, "start", &i.to_string()))
+                .await;
+        }
+
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert!(
+            lines <= 4,
+            "file should be compacted, found {} lines",
+            lines
+        );
+
+        let reopened = Timeline::open(&path, 2).unwrap();
+        let entries = reopened.query(&TimelineFilter::default()).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].target, "4");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_docker_event_entry_keeps_exit_code() {
+        let entry = docker_event_entry(DockerEvent {
+            timestamp: Utc::now(),
+            kind: "container".to_string(),
+            action: "die".to_string(),
+            id: "abc123".to_string(),
+            name: Some("web".to_string()),
+            image: None,
+            exit_code: Some(1),
+        });
+        assert_eq!(entry.source, TimelineSource::Docker);
+        assert_eq!(entry.details.as_deref(), Some("exit code 1"));
+    }
+}
@@ -0,0 +1,238 @@
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::{
+    fs, io,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+
+/// How often the certificate files are checked for changes
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Build the rustls configuration from the certificate, key and client CA files in `config`.
+/// With a client CA, only clients presenting a certificate it issued complete the handshake.
+pub fn server_config(config: &Config) -> io::Result<ServerConfig> {
+    let (cert_file, key_file) = config
+        .tls_files()
+        .ok_or_else(|| invalid_data("SDM_TLS_CERT_FILE and SDM_TLS_KEY_FILE are not set"))?;
+
+    let certs = CertificateDer::pem_slice_iter(&read(cert_file)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid_data(format!("{}: {}", cert_file, e)))?;
+    if certs.is_empty() {
+        return Err(invalid_data(format!(
+            "{}: no certificates found",
+            cert_file
+        )));
+    }
+    let key = PrivateKeyDer::from_pem_slice(&read(key_file)?)
+        .map_err(|e| invalid_data(format!("{}: {}", key_file, e)))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid_data)?;
+    let builder = match config
+        .tls_client_ca_file
+        .as_deref()
+        .filter(|f| !f.is_empty())
+    {
+        Some(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_slice_iter(&read(ca_file)?) {
+                let cert = cert.map_err(|e| invalid_data(format!("{}: {}", ca_file, e)))?;
+                roots
+                    .add(cert)
+                    .map_err(|e| invalid_data(format!("{}: {}", ca_file, e)))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|e| invalid_data(format!("{}: {}", ca_file, e)))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid_data(format!("{}: {}", key_file, e)))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+/// Check the certificate, key and client CA files for changes for the lifetime of the server,
+/// switching new connections to the new files so renewed certificates need no restart. If
+/// the new files cannot be loaded, e.g. halfway through a renewal, the current ones stay.
+pub fn spawn_reloader(tls: RustlsConfig, config: Arc<Config>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_modified = modified_times(&config);
+        let mut interval = tokio::time::interval(RELOAD_CHECK_INTERVAL);
+        interval.tick().await; // The first tick completes immediately
+        loop {
+            interval.tick().await;
+            let modified = modified_times(&config);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            match server_config(&config) {
+                Ok(server_config) => {
+                    tls.reload_from_config(Arc::new(server_config));
+                    tracing::info!("Reloaded the TLS certificate");
+                }
+                Err(e) => {
+                    tracing::warn!("Keeping the current TLS certificate: {}", e);
+                }
+            }
+        }
+    })
+}
+
+fn modified_times(config: &Config) -> Vec<Option<SystemTime>> {
+    [
+        config.tls_cert_file.as_deref(),
+        config.tls_key_file.as_deref(),
+        config.tls_client_ca_file.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+    .collect()
+}
+
+/// Read a file, naming it in the error
+fn read(path: &str) -> io::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    struct TestPki {
+        dir: PathBuf,
+        ca_pem: String,
+        client_identity_pem: String,
+    }
+
+    impl Drop for TestPki {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// A CA, a server certificate for `localhost` and a client certificate, with the
+    /// server's files written to a temporary directory
+    fn test_pki() -> TestPki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let issue = |name: &str, usage: ExtendedKeyUsagePurpose| {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+            params.extended_key_usages = vec![usage];
+            let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+            (cert.pem(), key.serialize_pem())
+        };
+        let (server_cert, server_key) = issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+        let (client_cert, client_key) = issue("ci-runner", ExtendedKeyUsagePurpose::ClientAuth);
+
+        let dir = std::env::temp_dir().join(format!("sdm-tls-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cert.pem"), server_cert).unwrap();
+        fs::write(dir.join("key.pem"), server_key).unwrap();
+        fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        TestPki {
+            dir,
+            ca_pem: ca.pem(),
+            client_identity_pem: format!("{}{}", client_key, client_cert),
+        }
+    }
+
+    fn tls_config(pki: &TestPki, client_ca: bool) -> Config {
+        let path = |name: &str| Some(pki.dir.join(name).to_string_lossy().into_owned());
+        Config {
+            tls_cert_file: path("cert.pem"),
+            tls_key_file: path("key.pem"),
+            tls_client_ca_file: if client_ca { path("ca.pem") } else { None },
+            ..Config::default()
+        }
+    }
+
+    /// Serve a trivial app over HTTPS on a free port, returning its base URL
+    async fn serve(server_config: ServerConfig) -> String {
+        let handle = axum_server::Handle::new();
+        let app = Router::new().route("/", get(|| async { "ok" }));
+        let server = axum_server::bind_rustls(
+            ([127, 0, 0, 1], 0).into(),
+            RustlsConfig::from_config(Arc::new(server_config)),
+        )
+        .handle(handle.clone());
+        tokio::spawn(server.serve(app.into_make_service()));
+        let address = handle.listening().await.unwrap();
+        format!("https://localhost:{}/", address.port())
+    }
+
+    fn client(pki: &TestPki, identity: bool) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .use_rustls_tls()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(reqwest::Certificate::from_pem(pki.ca_pem.as_bytes()).unwrap())
+            .resolve("localhost", ([127, 0, 0, 1], 0).into());
+        if identity {
+            builder = builder
+                .identity(reqwest::Identity::from_pem(pki.client_identity_pem.as_bytes()).unwrap());
+        }
+        builder.build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_and_checks_client_certificates() {
+        let pki = test_pki();
+
+        let url = serve(server_config(&tls_config(&pki, false)).unwrap()).await;
+        let response = client(&pki, false).get(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        let url = serve(server_config(&tls_config(&pki, true)).unwrap()).await;
+        assert!(client(&pki, false).get(&url).send().await.is_err());
+        let response = client(&pki, true).get(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_server_config_rejects_bad_files() {
+        let pki = test_pki();
+
+        // A key that does not belong to the certificate
+        fs::write(
+            pki.dir.join("key.pem"),
+            KeyPair::generate().unwrap().serialize_pem(),
+        )
+        .unwrap();
+        assert!(server_config(&tls_config(&pki, false)).is_err());
+
+        let missing = Config {
+            tls_cert_file: Some(pki.dir.join("missing.pem").to_string_lossy().into_owned()),
+            ..tls_config(&pki, false)
+        };
+        let error = server_config(&missing).unwrap_err();
+        assert!(error.to_string().contains("missing.pem"));
+    }
+}
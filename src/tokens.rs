@@ -0,0 +1,253 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::auth::{constant_time_eq, hash_secret, write_private_json, Role};
+
+/// Prefix of every issued token, so leaked tokens are easy to recognise in logs and scanners
+const TOKEN_PREFIX: &str = "sdm_";
+
+/// A long-lived API token as shown to users. The secret itself is only returned once,
+/// when the token is issued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub owner: String,
+    pub role: Role, // Upper bound; the owner's current role still applies
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires| expires <= Utc::now())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    secret_hash: String, // Hex Blake2s-256 of the full token
+}
+
+/// Issued API tokens, kept in memory and saved to a JSON file whenever they change.
+/// Only a hash of each token is stored, so the file cannot be used to authenticate.
+#[derive(Debug)]
+pub struct TokenStore {
+    tokens: RwLock<Vec<StoredToken>>,
+    path: Option<PathBuf>,
+}
+
+impl TokenStore {
+    /// A store that is not persisted, e.g. when the data directory is not writable
+    pub fn in_memory() -> Self {
+        Self {
+            tokens: RwLock::new(Vec::new()),
+            path: None,
+        }
+    }
+
+    /// Open (or create) the token file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let tokens = if path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            if contents.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&contents)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+        } else {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            Vec::new()
+        };
+
+        let store = Self {
+            tokens: RwLock::new(tokens),
+            path: Some(path),
+        };
+        if let Some(path) = &store.path {
+            if !path.exists() {
+                write_private_json(path, &[] as &[StoredToken])?;
+            }
+        }
+        Ok(store)
+    }
+
+    /// Issue a new token, returning its details and the secret to hand to the client
+    pub async fn issue(
+        &self,
+        name: &str,
+        owner: &str,
+        role: Role,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> io::Result<(ApiToken, String)> {
+        let secret = generate_secret();
+        let token = ApiToken {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            owner: owner.to_string(),
+            role,
+            created_at: Utc::now(),
+            expires_at,
+            last_used_at: None,
+        };
+
+        let mut tokens = self.tokens.write().await;
+        tokens.push(StoredToken {
+            token: token.clone(),
+            secret_hash: hash_secret(&secret),
+        });
+        self.save(&tokens)?;
+
+        tracing::info!(
+            "Issued API token {} ({}) for {}",
+            token.name,
+            token.id,
+            owner
+        );
+        Ok((token, secret))
+    }
+
+    /// Tokens owned by `owner`, or every token when `owner` is `None`, oldest first
+    pub async fn list(&self, owner: Option<&str>) -> Vec<ApiToken> {
+        let tokens = self.tokens.read().await;
+        tokens
+            .iter()
+            .filter(|stored| owner.is_none_or(|owner| stored.token.owner == owner))
+            .map(|stored| stored.token.clone())
+            .collect()
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ApiToken> {
+        let tokens = self.tokens.read().await;
+        tokens
+            .iter()
+            .find(|stored| stored.token.id == id)
+            .map(|stored| stored.token.clone())
+    }
+
+    /// Delete a token; returns whether it existed
+    pub async fn revoke(&self, id: &str) -> io::Result<bool> {
+        let mut tokens = self.tokens.write().await;
+        let before = tokens.len();
+        tokens.retain(|stored| stored.token.id != id);
+        if tokens.len() == before {
+            return Ok(false);
+        }
+        self.save(&tokens)?;
+
+        tracing::info!("Revoked API token {}", id);
+        Ok(true)
+    }
+
+    /// Look up the token a client presented. Expired tokens are rejected. The last-used
+    /// time is updated in memory and saved with the next change to the store.
+    pub async fn authenticate(&self, secret: &str) -> Option<ApiToken> {
+        if !secret.starts_with(TOKEN_PREFIX) {
+            return None;
+        }
+        let hash = hash_secret(secret);
+
+        let mut tokens = self.tokens.write().await;
+        let stored = tokens
+            .iter_mut()
+            .find(|stored| constant_time_eq(stored.secret_hash.as_bytes(), hash.as_bytes()))?;
+        if stored.token.is_expired() {
+            return None;
+        }
+        stored.token.last_used_at = Some(Utc::now());
+        Some(stored.token.clone())
+    }
+
+    fn save(&self, tokens: &[StoredToken]) -> io::Result<()> {
+        match &self.path {
+            Some(path) => write_private_json(path, tokens),
+            None => Ok(()),
+        }
+    }
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_issue_and_authenticate() {
+        let store = TokenStore::in_memory();
+        let (token, secret) = store
+            .issue("ci", "alice", Role::Operator, None)
+            .await
+            .unwrap();
+
+        assert!(secret.starts_with(TOKEN_PREFIX));
+        let authenticated = store.authenticate(&secret).await.unwrap();
+        assert_eq!(authenticated.id, token.id);
+        assert_eq!(authenticated.role, Role::Operator);
+        assert!(authenticated.last_used_at.is_some());
+
+        assert!(store.authenticate("sdm_not-a-token").await.is_none());
+        assert!(store.authenticate("").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_revoked_tokens_are_rejected() {
+        let store = TokenStore::in_memory();
+        let (_, expired) = store
+            .issue(
+                "old",
+                "alice",
+                Role::Viewer,
+                Some(Utc::now() - chrono::Duration::minutes(1)),
+            )
+            .await
+            .unwrap();
+        assert!(store.authenticate(&expired).await.is_none());
+
+        let (token, secret) = store
+            .issue("ci", "alice", Role::Viewer, None)
+            .await
+            .unwrap();
+        assert!(store.revoke(&token.id).await.unwrap());
+        assert!(!store.revoke(&token.id).await.unwrap());
+        assert!(store.authenticate(&secret).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_survive_reopen_without_secrets_on_disk() {
+        let path = std::env::temp_dir().join(format!("sdm-tokens-{}.json", Uuid::new_v4()));
+        let secret = {
+            let store = TokenStore::open(&path).unwrap();
+            let (_, secret) = store.issue("ci", "bob", Role::Admin, None).await.unwrap();
+            secret
+        };
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&secret));
+
+        let reopened = TokenStore::open(&path).unwrap();
+        assert_eq!(reopened.list(Some("bob")).await.len(), 1);
+        assert!(reopened.list(Some("alice")).await.is_empty());
+        assert!(reopened.authenticate(&secret).await.is_some());
+        let _ = fs::remove_file(&path);
+    }
+}
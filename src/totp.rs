@@ -0,0 +1,558 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use qrcode::{render::svg, QrCode};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::auth::{constant_time_eq, hash_secret, write_private_json, Role};
+
+/// Seconds each code is valid for, the period every authenticator app assumes
+const STEP_SECONDS: i64 = 30;
+
+/// Steps either side of the current one that are still accepted, to allow for clock drift
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+
+const CODE_DIGITS: u32 = 6;
+
+/// One-time recovery codes handed out at enrollment
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Account issuer shown in authenticator apps
+const ISSUER: &str = "Simple Docker Manager";
+
+/// How long a user has to enter a code after their password was accepted
+const CHALLENGE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Wrong codes allowed per sign-in before the password has to be entered again
+const MAX_CHALLENGE_ATTEMPTS: u32 = 5;
+
+/// Sign-ins waiting for a code kept at once, so password holders cannot exhaust memory
+const MAX_CHALLENGES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Enrollment {
+    username: String,
+    secret: String, // Base32, as entered into authenticator apps
+    recovery_code_hashes: Vec<String>,
+    enabled_at: DateTime<Utc>,
+    #[serde(default)]
+    last_step: i64, // Newest time step accepted, so a code cannot be used twice
+}
+
+/// A user's two-factor settings as shown on their account page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotpStatus {
+    pub enabled_at: DateTime<Utc>,
+    pub recovery_codes_left: usize,
+}
+
+/// How a user proved their second factor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verified {
+    Code,
+    RecoveryCode,
+}
+
+/// A new secret waiting for the user to confirm it with a code from their app
+#[derive(Debug, Clone)]
+pub struct TotpSetup {
+    pub secret: String,
+    started: Instant,
+}
+
+impl TotpSetup {
+    fn new() -> Self {
+        let mut bytes = [0u8; 20];
+        OsRng.fill_bytes(&mut bytes);
+        Self {
+            secret: BASE32_NOPAD.encode(&bytes),
+            started: Instant::now(),
+        }
+    }
+
+    /// The `otpauth://` URI authenticator apps read from the QR code
+    pub fn provisioning_uri(&self, username: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            urlencoding::encode(ISSUER),
+            urlencoding::encode(username),
+            self.secret,
+            urlencoding::encode(ISSUER),
+            CODE_DIGITS,
+            STEP_SECONDS
+        )
+    }
+
+    /// The provisioning URI as a QR code, ready for an `<img src>`
+    pub fn qr_code_data_uri(&self, username: &str) -> String {
+        let svg = QrCode::new(self.provisioning_uri(username).as_bytes())
+            .map(|code| code.render::<svg::Color>().min_dimensions(200, 200).build())
+            .unwrap_or_default();
+        format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg))
+    }
+
+    fn is_expired(&self) -> bool {
+        self.started.elapsed() >= CHALLENGE_TIMEOUT
+    }
+}
+
+/// A user whose password was accepted and who still has to enter a code
+#[derive(Debug, Clone)]
+pub struct LoginChallenge {
+    pub username: String,
+    pub role: Role,
+    pub setup: Option<TotpSetup>, // Set when the user has to enroll before signing in
+    started: Instant,
+    attempts: u32,
+}
+
+/// Time-based one-time password (RFC 6238) enrollments, saved to a JSON file whenever
+/// they change, plus the sign-ins and setups waiting for a code. The file holds the
+/// shared secrets, so it is only readable by the owner; recovery codes are stored hashed.
+#[derive(Debug)]
+pub struct TotpStore {
+    enrollments: RwLock<Vec<Enrollment>>,
+    path: Option<PathBuf>,
+    setups: Mutex<HashMap<String, TotpSetup>>, // Keyed by username
+    challenges: Mutex<HashMap<String, LoginChallenge>>, // Keyed by a random challenge ID
+}
+
+impl TotpStore {
+    /// A store that is not persisted, e.g. when the data directory is not writable
+    pub fn in_memory() -> Self {
+        Self {
+            enrollments: RwLock::new(Vec::new()),
+            path: None,
+            setups: Mutex::new(HashMap::new()),
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Open (or create) the enrollment file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let enrollments = if path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            if contents.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&contents)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+        } else {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            write_private_json(&path, &[] as &[Enrollment])?;
+            Vec::new()
+        };
+
+        Ok(Self {
+            enrollments: RwLock::new(enrollments),
+            path: Some(path),
+            ..Self::in_memory()
+        })
+    }
+
+    pub async fn status(&self, username: &str) -> Option<TotpStatus> {
+        let enrollments = self.enrollments.read().await;
+        enrollments
+            .iter()
+            .find(|enrollment| enrollment.username == username)
+            .map(|enrollment| TotpStatus {
+                enabled_at: enrollment.enabled_at,
+                recovery_codes_left: enrollment.recovery_code_hashes.len(),
+            })
+    }
+
+    pub async fn is_enrolled(&self, username: &str) -> bool {
+        self.status(username).await.is_some()
+    }
+
+    /// Generate a new secret for a user to add to their app, replacing any unfinished setup
+    pub async fn start_setup(&self, username: &str) -> TotpSetup {
+        let setup = TotpSetup::new();
+        let mut setups = self.setups.lock().await;
+        setups.retain(|_, setup| !setup.is_expired());
+        setups.insert(username.to_string(), setup.clone());
+        setup
+    }
+
+    /// The user's unfinished setup, if it has not expired
+    pub async fn pending_setup(&self, username: &str) -> Option<TotpSetup> {
+        let setups = self.setups.lock().await;
+        setups
+            .get(username)
+            .filter(|setup| !setup.is_expired())
+            .cloned()
+    }
+
+    /// Finish the user's pending setup with a code from their app, returning their
+    /// recovery codes
+    pub async fn confirm_setup(&self, username: &str, code: &str) -> Result<Vec<String>, String> {
+        let setup = self.pending_setup(username).await.ok_or_else(|| {
+            "Two-factor setup has expired; start again to get a new QR code".to_string()
+        })?;
+        let codes = self.enroll(username, &setup, code).await?;
+        self.setups.lock().await.remove(username);
+        Ok(codes)
+    }
+
+    /// Enable two-factor authentication for a user once they prove their app has the
+    /// setup's secret, returning their recovery codes. Fails if it is already enabled.
+    pub async fn enroll(
+        &self,
+        username: &str,
+        setup: &TotpSetup,
+        code: &str,
+    ) -> Result<Vec<String>, String> {
+        let secret = decode_secret(&setup.secret)?;
+        let step = matching_step(&secret, code, current_step(), i64::MIN)
+            .ok_or_else(|| "Incorrect code; check your device's clock and try again".to_string())?;
+
+        let mut enrollments = self.enrollments.write().await;
+        // Replacing a secret would let anyone holding a session or password change devices
+        if enrollments
+            .iter()
+            .any(|enrollment| enrollment.username == username)
+        {
+            return Err("Two-factor authentication is already on".to_string());
+        }
+        let (codes, hashes) = generate_recovery_codes();
+        enrollments.push(Enrollment {
+            username: username.to_string(),
+            secret: setup.secret.clone(),
+            recovery_code_hashes: hashes,
+            enabled_at: Utc::now(),
+            last_step: step,
+        });
+        if let Err(e) = self.save(&enrollments) {
+            enrollments.pop();
+            return Err(format!("Failed to save two-factor settings: {}", e));
+        }
+
+        tracing::info!("Enabled two-factor authentication for {}", username);
+        Ok(codes)
+    }
+
+    /// Turn two-factor authentication off for a user; returns whether it was on
+    pub async fn disable(&self, username: &str) -> io::Result<bool> {
+        let mut enrollments = self.enrollments.write().await;
+        let before = enrollments.len();
+        enrollments.retain(|enrollment| enrollment.username != username);
+        if enrollments.len() == before {
+            return Ok(false);
+        }
+        self.save(&enrollments)?;
+
+        tracing::info!("Disabled two-factor authentication for {}", username);
+        Ok(true)
+    }
+
+    /// Replace a user's recovery codes, returning the new ones
+    pub async fn regenerate_recovery_codes(&self, username: &str) -> io::Result<Vec<String>> {
+        let mut enrollments = self.enrollments.write().await;
+        let enrollment = enrollments
+            .iter_mut()
+            .find(|enrollment| enrollment.username == username)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "two-factor is not enabled"))?;
+        let (codes, hashes) = generate_recovery_codes();
+        enrollment.recovery_code_hashes = hashes;
+        self.save(&enrollments)?;
+        Ok(codes)
+    }
+
+    /// Check a code from the user's app, or one of their recovery codes, which is then
+    /// used up. Each app code is only accepted once.
+    pub async fn verify(&self, username: &str, code: &str) -> Option<Verified> {
+        let mut enrollments = self.enrollments.write().await;
+        let enrollment = enrollments
+            .iter_mut()
+            .find(|enrollment| enrollment.username == username)?;
+
+        let verified = if let Some(step) = decode_secret(&enrollment.secret)
+            .ok()
+            .and_then(|secret| matching_step(&secret, code, current_step(), enrollment.last_step))
+        {
+            enrollment.last_step = step;
+            Verified::Code
+        } else {
+            let hash = hash_secret(&normalize_recovery_code(code));
+            let index = enrollment
+                .recovery_code_hashes
+                .iter()
+                .position(|stored| constant_time_eq(stored.as_bytes(), hash.as_bytes()))?;
+            enrollment.recovery_code_hashes.remove(index);
+            tracing::warn!(
+                "{} used a recovery code; {} left",
+                username,
+                enrollment.recovery_code_hashes.len()
+            );
+            Verified::RecoveryCode
+        };
+
+        if let Err(e) = self.save(&enrollments) {
+            tracing::error!("Failed to save two-factor settings: {}", e);
+        }
+        Some(verified)
+    }
+
+    /// Hold a sign-in whose password was accepted until the user enters a code. Users who
+    /// must use two-factor authentication but have not set it up get a setup to finish.
+    pub async fn begin_challenge(
+        &self,
+        username: &str,
+        role: Role,
+        enroll: bool,
+    ) -> (String, LoginChallenge) {
+        let id = Uuid::new_v4().to_string();
+        let mut challenges = self.challenges.lock().await;
+        challenges.retain(|_, challenge| challenge.started.elapsed() < CHALLENGE_TIMEOUT);
+        if challenges.len() >= MAX_CHALLENGES {
+            // Drop the oldest rather than refusing users who know their password
+            if let Some(oldest) = challenges
+                .iter()
+                .min_by_key(|(_, challenge)| challenge.started)
+                .map(|(id, _)| id.clone())
+            {
+                challenges.remove(&oldest);
+            }
+        }
+        let challenge = LoginChallenge {
+            username: username.to_string(),
+            role,
+            setup: enroll.then(TotpSetup::new),
+            started: Instant::now(),
+            attempts: 0,
+        };
+        challenges.insert(id.clone(), challenge.clone());
+        (id, challenge)
+    }
+
+    /// Take a pending sign-in out of the store, so concurrent submissions cannot both
+    /// use it. Hand it back with `retry_challenge` after a wrong code.
+    pub async fn take_challenge(&self, id: &str) -> Option<LoginChallenge> {
+        self.challenges
+            .lock()
+            .await
+            .remove(id)
+            .filter(|challenge| challenge.started.elapsed() < CHALLENGE_TIMEOUT)
+    }
+
+    /// Put a sign-in back after a wrong code; returns false once it has had too many
+    pub async fn retry_challenge(&self, id: &str, mut challenge: LoginChallenge) -> bool {
+        challenge.attempts += 1;
+        if challenge.attempts >= MAX_CHALLENGE_ATTEMPTS {
+            return false;
+        }
+        self.challenges
+            .lock()
+            .await
+            .insert(id.to_string(), challenge);
+        true
+    }
+
+    fn save(&self, enrollments: &[Enrollment]) -> io::Result<()> {
+        match &self.path {
+            Some(path) => write_private_json(path, enrollments),
+            None => Ok(()),
+        }
+    }
+}
+
+fn current_step() -> i64 {
+    Utc::now().timestamp().div_euclid(STEP_SECONDS)
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|e| format!("Invalid two-factor secret: {}", e))
+}
+
+/// The code for one time step (RFC 4226 HOTP over the step counter)
+fn code_at(secret: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(CODE_DIGITS),
+        width = CODE_DIGITS as usize
+    )
+}
+
+/// The time step a code belongs to, if it is close enough to `now` and newer than `after`
+fn matching_step(secret: &[u8], code: &str, now: i64, after: i64) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    (now - ALLOWED_DRIFT_STEPS..=now + ALLOWED_DRIFT_STEPS)
+        .filter(|step| *step > after)
+        .find(|step| constant_time_eq(code_at(secret, *step).as_bytes(), code.as_bytes()))
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Recovery codes to show the user once, and the hashes to store
+fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            OsRng.fill_bytes(&mut bytes);
+            let raw = BASE32_NOPAD.encode(&bytes).to_ascii_lowercase();
+            let code = format!("{}-{}", &raw[..4], &raw[4..]);
+            let hash = hash_secret(&normalize_recovery_code(&code));
+            (code, hash)
+        })
+        .unzip()
+}
+
+/// The code an authenticator app would show for `secret` right now
+#[cfg(test)]
+pub(crate) fn current_code(secret: &str) -> String {
+    code_at(&decode_secret(secret).unwrap(), current_step())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_code(setup: &TotpSetup, step: i64) -> String {
+        code_at(&decode_secret(&setup.secret).unwrap(), step)
+    }
+
+    #[test]
+    fn test_codes_match_rfc_6238() {
+        // Test vectors from RFC 6238 appendix B, truncated to six digits
+        let secret = b"12345678901234567890";
+        assert_eq!(code_at(secret, 59 / STEP_SECONDS), "287082");
+        assert_eq!(code_at(secret, 1111111109 / STEP_SECONDS), "081804");
+        assert_eq!(code_at(secret, 2000000000 / STEP_SECONDS), "279037");
+
+        let now = 1111111109 / STEP_SECONDS;
+        assert_eq!(matching_step(secret, "081 804", now + 1, 0), Some(now));
+        assert_eq!(matching_step(secret, "081804", now + 2, 0), None);
+        assert_eq!(matching_step(secret, "081804", now, now), None);
+        assert_eq!(matching_step(secret, "08180", now, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_enroll_and_verify_codes_once() {
+        let store = TotpStore::in_memory();
+        let setup = store.start_setup("alice").await;
+        assert!(setup
+            .provisioning_uri("alice")
+            .starts_with("otpauth://totp/Simple%20Docker%20Manager:alice?secret="));
+
+        let window: Vec<String> = (-1..=1)
+            .map(|drift| setup_code(&setup, current_step() + drift))
+            .collect();
+        let wrong = (0..10)
+            .map(|digit| digit.to_string().repeat(6))
+            .find(|code| !window.contains(code))
+            .unwrap();
+        assert!(store.confirm_setup("alice", &wrong).await.is_err());
+        assert!(!store.is_enrolled("alice").await);
+
+        let codes = store
+            .confirm_setup("alice", &setup_code(&setup, current_step()))
+            .await
+            .unwrap();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(store.pending_setup("alice").await.is_none());
+        assert!(store.is_enrolled("alice").await);
+        assert!(!store.is_enrolled("bob").await);
+
+        // The code used to enroll cannot be used again, but the next one can
+        let next = setup_code(&setup, current_step() + 1);
+        assert_eq!(
+            store
+                .verify("alice", &setup_code(&setup, current_step()))
+                .await,
+            None
+        );
+        assert_eq!(store.verify("alice", &next).await, Some(Verified::Code));
+        assert_eq!(store.verify("alice", &next).await, None);
+
+        // Recovery codes work once each, however they are typed
+        let recovery = codes[0].to_uppercase().replace('-', " ");
+        assert_eq!(
+            store.verify("alice", &recovery).await,
+            Some(Verified::RecoveryCode)
+        );
+        assert_eq!(store.verify("alice", &codes[0]).await, None);
+        assert_eq!(
+            store.status("alice").await.unwrap().recovery_codes_left,
+            RECOVERY_CODE_COUNT - 1
+        );
+        assert_eq!(store.verify("bob", &codes[1]).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_challenges_allow_limited_attempts() {
+        let store = TotpStore::in_memory();
+        let (id, _) = store.begin_challenge("alice", Role::Admin, true).await;
+
+        let challenge = store.take_challenge(&id).await.unwrap();
+        assert_eq!(challenge.role, Role::Admin);
+        assert!(challenge.setup.is_some());
+        assert!(store.take_challenge(&id).await.is_none());
+
+        let mut challenge = Some(challenge);
+        for _ in 1..MAX_CHALLENGE_ATTEMPTS {
+            assert!(store.retry_challenge(&id, challenge.take().unwrap()).await);
+            challenge = store.take_challenge(&id).await;
+        }
+        assert!(!store.retry_challenge(&id, challenge.unwrap()).await);
+        assert!(store.take_challenge(&id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_enrollments_survive_reopen() {
+        let path = std::env::temp_dir().join(format!("sdm-totp-{}.json", Uuid::new_v4()));
+        let codes = {
+            let store = TotpStore::open(&path).unwrap();
+            let setup = store.start_setup("bob").await;
+            store
+                .confirm_setup("bob", &setup_code(&setup, current_step()))
+                .await
+                .unwrap()
+        };
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&codes[0]));
+
+        let reopened = TotpStore::open(&path).unwrap();
+        assert!(reopened.is_enrolled("bob").await);
+        assert_eq!(
+            reopened.verify("bob", &codes[0]).await,
+            Some(Verified::RecoveryCode)
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,38 +1,126 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        Form, Path, Query, State, WebSocketUpgrade,
+        ConnectInfo, Form, Path, Query, State, WebSocketUpgrade,
     },
-    http::{HeaderMap, HeaderValue, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     middleware,
-    response::{Html, IntoResponse, Redirect, Response},
-    routing::{get, post},
-    Json, Router,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Redirect, Response,
+    },
+    routing::{delete, get, post},
+    Extension, Json, Router,
 };
-use futures_util::stream::StreamExt;
+use chrono::{DateTime, Utc};
+use futures_util::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::services::ServeDir;
 
-use crate::auth::{LoginForm, SessionStore};
+use crate::auth::{
+    clear_session_cookie, session_cookie, verify_csrf, AuthMethod, AuthState, LoginForm, Role,
+    Session, SessionStore,
+};
+use crate::backend::DockerBackend;
 use crate::config::Config;
 use crate::docker;
+use crate::events::EventHub;
+use crate::login_throttle::{self, LoginThrottle};
 use crate::models::{
-    ContainerPortMapping, ContainerSummary, CreateContainerRequest, EnvironmentVariable,
-    LocalImageSummary,
+    ContainerMount, ContainerPortMapping, ContainerRemovalOptions, ContainerStateSummary,
+    CreateContainerRequest, CreateNetworkRequest, CreateVolumeRequest, EnvironmentVariable,
+    ExecClientMessage, ImageRemovalOptions, NetworkConnectRequest, NetworkDisconnectRequest,
+    PullImageRequest, PullProgress, RevealEnvironmentRequest, TimelineSource,
 };
+use crate::oidc::{self, OidcClient};
+use crate::pages::{
+    self, AuthenticatorSetup, DashboardPage, EventsPage, ExecPage, LoginError, LoginPage, LogsPage,
+    ManagementPage, Nav, NetworksPage, Notice, RunningContainer, SelectOption, SessionRow,
+    SessionsPage, SignedInPage, TokensPage, TwoFactorLoginPage, TwoFactorLoginStep, TwoFactorPage,
+    TwoFactorStatus, VolumesPage,
+};
+use crate::secrets::{self, SecretMasker};
+use crate::stats_collector::StatsCollector;
+use crate::timeline::{Timeline, TimelineFilter, DEFAULT_QUERY_LIMIT};
+use crate::tokens::{ApiToken, TokenStore};
+use crate::totp::{LoginChallenge, TotpStore, Verified};
 
 #[derive(Deserialize)]
 pub struct StartImageParams {
     image_name: String,
 }
 
+#[derive(Deserialize)]
+pub struct RemoveImageParams {
+    image: String,
+    #[serde(default)]
+    force: bool,
+    #[serde(default)]
+    no_prune: bool,
+}
+
+#[derive(Deserialize)]
+pub struct UntagImageParams {
+    tag: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateVolumeParams {
+    name: String,
+    driver: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RemoveVolumeParams {
+    name: String,
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RemoveVolumeQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CreateNetworkParams {
+    name: String,
+    driver: Option<String>,
+    #[serde(default)]
+    internal: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RemoveNetworkParams {
+    network: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConnectNetworkParams {
+    network: String,
+    container: String,
+    aliases: Option<String>, // Comma-separated
+}
+
+#[derive(Deserialize)]
+pub struct DisconnectNetworkParams {
+    network: String,
+    container: String,
+}
+
 #[derive(Deserialize)]
 pub struct EnhancedStartImageParams {
     image_name: String,
     container_name: Option<String>,
     environment_variables: Option<String>, // JSON string of environment variables
     port_mappings: Option<String>,         // JSON string of port mappings
+    mounts: Option<String>,                // JSON string of volume, bind and tmpfs mounts
+    network: Option<String>,
+    network_aliases: Option<String>, // Comma-separated
     restart_policy: Option<String>,
 }
 
@@ -51,653 +139,4787 @@ pub struct ConfigResponse {
     max_chart_containers: usize,
 }
 
+#[derive(Serialize)]
+pub struct ActionResponse {
+    success: bool,
+    message: String,
+}
+
 #[derive(Deserialize)]
 pub struct LogQuery {
     tail: Option<String>,
 }
 
-struct AppState {
-    config: Config,
-    session_store: Arc<SessionStore>,
+#[derive(Deserialize)]
+pub struct ExecQuery {
+    shell: Option<String>, // One of EXEC_SHELLS; defaults to bash with a fallback to sh
+}
+
+/// Shells that can be started in a container
+const EXEC_SHELLS: [&str; 3] = ["/bin/sh", "/bin/bash", "/bin/ash"];
+
+#[derive(Deserialize)]
+pub struct ContainerListQuery {
+    state: Option<String>, // e.g., "running", "exited", "created"
+}
+
+#[derive(Deserialize)]
+pub struct MetricsHistoryQuery {
+    container: Option<String>, // Container ID, ID prefix or name
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
 }
 
-fn get_status_class(status: &str) -> &'static str {
-    match status.to_lowercase().as_str() {
-        s if s.contains("running") || s.contains("up") => "status-running",
-        s if s.contains("exited") || s.contains("stopped") || s.contains("created") => {
-            "status-exited"
+/// Timeline filters as submitted by the events page form or an API client. Empty fields
+/// are ignored so the form can be submitted as-is.
+#[derive(Deserialize)]
+pub struct TimelineQuery {
+    source: Option<String>, // "docker" or "user"
+    kind: Option<String>,   // e.g., "container", "image"
+    action: Option<String>, // Action or action prefix
+    target: Option<String>, // ID, ID prefix or name
+    username: Option<String>,
+    from: Option<String>, // RFC 3339, or a datetime-local value in UTC
+    to: Option<String>,
+    limit: Option<String>,
+}
+
+impl TimelineQuery {
+    fn into_filter(self) -> Result<TimelineFilter, String> {
+        fn present(value: Option<String>) -> Option<String> {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        }
+
+        fn parse_time(field: &str, value: Option<String>) -> Result<Option<DateTime<Utc>>, String> {
+            let Some(value) = present(value) else {
+                return Ok(None);
+            };
+            if let Ok(time) = DateTime::parse_from_rfc3339(&value) {
+                return Ok(Some(time.with_timezone(&Utc)));
+            }
+            chrono::NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M")
+                .map(|time| Some(time.and_utc()))
+                .map_err(|_| format!("`{}` must be an RFC 3339 timestamp", field))
+        }
+
+        let source = match present(self.source).as_deref() {
+            None => None,
+            Some("docker") => Some(TimelineSource::Docker),
+            Some("user") => Some(TimelineSource::User),
+            Some(other) => return Err(format!("Unknown source `{}`", other)),
+        };
+        let limit = match present(self.limit) {
+            None => None,
+            Some(limit) => Some(
+                limit
+                    .parse::<usize>()
+                    .map_err(|_| "`limit` must be a positive number".to_string())?,
+            ),
+        };
+        let from = parse_time("from", self.from)?;
+        let to = parse_time("to", self.to)?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err("`from` must not be later than `to`".to_string());
+            }
         }
-        _ => "status-other",
+
+        Ok(TimelineFilter {
+            source,
+            kind: present(self.kind),
+            action: present(self.action),
+            target: present(self.target),
+            username: present(self.username),
+            from,
+            to,
+            limit,
+        })
     }
 }
 
-fn generate_running_container_rows(containers: &[ContainerSummary]) -> String {
-    if containers.is_empty() {
-        return r#"<tr><td colspan="5"><div class="empty-state">No running containers found</div></td></tr>"#.to_string();
-    }
+#[derive(Deserialize)]
+pub struct CreateTokenParams {
+    name: String,
+    role: Option<String>,
+    expires_in_days: Option<String>, // Empty for a token that never expires
+}
 
-    let mut rows_html = String::new();
-    for container in containers {
-        let status_class = get_status_class(&container.status);
+#[derive(Deserialize)]
+pub struct RevokeTokenParams {
+    id: String,
+}
 
-        // Format ports
-        let ports_display = if container.ports.is_empty() {
-            "<span class='no-ports'>No exposed ports</span>".to_string()
-        } else {
-            container
-                .ports
-                .iter()
-                .map(|port| {
-                    if let Some(host_port) = port.host_port {
-                        format!(
-                            "<span class='port-mapping'>{}:{}->{}/{}</span>",
-                            "0.0.0.0", host_port, port.container_port, port.protocol
-                        )
-                    } else {
-                        format!(
-                            "<span class='port-internal'>{}/{}</span>",
-                            port.container_port, port.protocol
-                        )
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join("<br>")
-        };
+#[derive(Deserialize)]
+pub struct RevokeSessionParams {
+    id: String,
+}
 
-        // Format environment variables for details view
-        let env_vars_display = if container.environment.is_empty() {
-            "<div class='env-empty'>No environment variables</div>".to_string()
-        } else {
-            container.environment.iter()
-                .map(|env| {
-                    if let Some(eq_pos) = env.find('=') {
-                        let (key, value) = env.split_at(eq_pos);
-                        let value = &value[1..]; // Skip the '=' character
-                        format!("<div class='env-var'><span class='env-key'>{}</span>=<span class='env-value'>{}</span></div>", 
-                               html_escape::encode_text(key),
-                               html_escape::encode_text(value))
-                    } else {
-                        format!("<div class='env-var'><span class='env-key'>{}</span></div>", 
-                               html_escape::encode_text(env))
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join("")
-        };
+#[derive(Deserialize)]
+pub struct TwoFactorCodeParams {
+    code: String,
+}
 
-        let actions = format!(
-            r#"
-            <div class="actions">
-                <button class="btn btn-details" onclick="toggleDetails('{}')">
-                    <span id="toggle-{}">▶</span> Details
-                </button>
-                <a href="/logs/{}" class="btn btn-logs">📜 Logs</a>
-                <form action="/stop/{}" method="post">
-                    <button class="btn btn-stop" type="submit">🛑 Stop</button>
-                </form>
-                <form action="/restart/{}" method="post">
-                    <button class="btn btn-restart" type="submit">🔄 Restart</button>
-                </form>
-            </div>
-        "#,
-            container.id, container.id, container.id, container.id, container.id
-        );
+/// The second step of a password login
+#[derive(Deserialize)]
+pub struct TwoFactorLoginForm {
+    challenge: String,
+    code: String, // From the authenticator app, or a recovery code
+}
 
-        // Main container row
-        rows_html.push_str(&format!(
-            r#"
-            <tr>
-                <td>{}</td>
-                <td>{}</td>
-                <td><span class="{}">{}</span></td>
-                <td>{}</td>
-                <td>
-                    {}
-                </td>
-            </tr>
-        "#,
-            container.name, container.image, status_class, container.status, ports_display, actions
-        ));
+#[derive(Deserialize)]
+pub struct OidcCallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
 
-        // Details row (initially hidden)
-        rows_html.push_str(&format!(
-            r#"
-            <tr id="details-{}" style="display: none;" class="details-row">
-                <td colspan="5">
-                    <div class="container-details">
-                        <div class="details-section">
-                            <h4>🔧 Environment Variables</h4>
-                            <div class="env-vars">
-                                {}
-                            </div>
-                        </div>
-                        <div class="details-section">
-                            <h4>📋 Container Information</h4>
-                            <div class="container-info">
-                                <div class="info-item">
-                                    <span class="info-label">Container ID:</span>
-                                    <span class="info-value">{}</span>
-                                </div>
-                                <div class="info-item">
-                                    <span class="info-label">Full Image:</span>
-                                    <span class="info-value">{}</span>
-                                </div>
-                            </div>
-                        </div>
-                    </div>
-                </td>
-            </tr>
-        "#,
-            container.id, env_vars_display, container.id, container.image
-        ));
-    }
-    rows_html
-}
-
-fn generate_image_rows(images: &[LocalImageSummary]) -> String {
-    if images.is_empty() {
-        return r#"<tr><td colspan="2"><div class="empty-state">No downloaded images found</div></td></tr>"#.to_string();
-    }
-
-    let mut rows_html = String::new();
-    for image in images {
-        let display_tag = image.repo_tags.first().map_or("N/A", |s| s.as_str());
-        let actions = format!(
-            r#"
-            <div class="actions">
-                <form action="/start-image" method="post" style="display: inline;">
-                    <input type="hidden" name="image_name" value="{}">
-                    <button class="btn btn-start" type="submit">🚀 Quick Start</button>
-                </form>
-                <button class="btn btn-configure" onclick="showAdvancedForm('{}')">⚙️ Configure & Start</button>
-            </div>
-        "#,
-            html_escape::encode_text(display_tag),
-            html_escape::encode_text(display_tag)
-        );
+#[derive(Deserialize)]
+pub struct CreateTokenRequest {
+    name: String,
+    role: Option<Role>, // Defaults to the caller's role
+    expires_in_days: Option<u32>,
+}
 
-        rows_html.push_str(&format!(
-            r#"
-            <tr>
-                <td>{}</td>
-                <td>
-                    {}
-                </td>
-            </tr>
-        "#,
-            html_escape::encode_text(display_tag),
-            actions
-        ));
+#[derive(Serialize)]
+pub struct IssuedTokenResponse {
+    token: ApiToken,
+    secret: String, // Only ever returned here
+}
+
+struct AppState {
+    config: Config,
+    session_store: Arc<SessionStore>,
+    backend: Arc<dyn DockerBackend>,
+    stats: Arc<StatsCollector>,
+    events: Arc<EventHub>,
+    timeline: Arc<Timeline>,
+    tokens: Arc<TokenStore>,
+    totp: Arc<TotpStore>,
+    login_throttle: LoginThrottle,
+    oidc: Option<OidcClient>,
+    secrets: SecretMasker,
+}
+
+/// Map a Docker error to the HTTP status code to report to API clients
+fn docker_error_status(error: &bollard::errors::Error) -> StatusCode {
+    match error {
+        bollard::errors::Error::DockerResponseServerError { status_code, .. } => {
+            StatusCode::from_u16(*status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
     }
-    rows_html
 }
 
-async fn index_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let docker_socket = state.config.docker_socket.as_deref();
-    let running_containers_result =
-        crate::docker::list_running_containers_with_config(docker_socket).await;
-    let downloaded_images_result =
-        crate::docker::list_downloaded_images_with_config(docker_socket).await;
+/// Record an action a user took and its outcome in the timeline
+async fn audit<T>(
+    state: &AppState,
+    session: &Option<Extension<Session>>,
+    kind: &str,
+    action: &str,
+    target: &str,
+    details: Option<String>,
+    result: &Result<T, bollard::errors::Error>,
+) {
+    state
+        .timeline
+        .record_action(
+            session_username(session).as_deref(),
+            kind,
+            action,
+            target,
+            details,
+            result.as_ref().err().map(docker::error_message),
+        )
+        .await;
+}
 
-    // Load the template
-    let template = include_str!("../templates/management.html");
+/// The signed-in user; `None` when authentication is disabled
+fn session_username(session: &Option<Extension<Session>>) -> Option<String> {
+    session
+        .as_ref()
+        .map(|Extension(session)| session.username.clone())
+}
 
-    // Generate running containers rows
-    let running_containers_rows = match running_containers_result {
-        Ok(containers) => generate_running_container_rows(&containers),
-        Err(e) => format!(
-            r#"<tr><td colspan="4"><div class="error-message">Error listing running containers: {}</div></td></tr>"#,
-            e
-        ),
+/// Whether a WebSocket handshake comes from a page of this site. Browsers send the session
+/// cookie with cross-site handshakes too, and they never pass through the CSRF checks, so
+/// a request whose `Origin` names another host is refused. Behind a reverse proxy the host
+/// may arrive in `X-Forwarded-Host`, which pages cannot set on a handshake. Clients that are
+/// not browsers, such as scripts using API tokens, send no `Origin` and are let through.
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
     };
-
-    // Generate image rows
-    let image_rows = match downloaded_images_result {
-        Ok(images) => generate_image_rows(&images),
-        Err(e) => format!(
-            r#"<tr><td colspan="2"><div class="error-message">Error listing images: {}</div></td></tr>"#,
-            e
-        ),
+    let Some((_, origin_host)) = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+    else {
+        return false;
     };
+    [header::HOST.as_str(), "x-forwarded-host"]
+        .into_iter()
+        .filter_map(|name| headers.get(name)?.to_str().ok())
+        .any(|host| host.trim().eq_ignore_ascii_case(origin_host))
+}
 
-    // Generate logout button if auth is enabled
-    let logout_button = if state.config.auth_enabled {
-        r#"<form action="/logout" method="post" style="display: inline;">
-            <button type="submit" class="btn btn-logout" style="background: #e74c3c; color: white; padding: 0.5rem 1rem; border: none; border-radius: 5px; cursor: pointer;">🚪 Logout</button>
-        </form>"#
-    } else {
-        ""
+fn cross_origin_rejection(path: &str) -> Response {
+    tracing::warn!("Refused cross-origin WebSocket request to {}", path);
+    (
+        StatusCode::FORBIDDEN,
+        "Cross-origin WebSocket requests are not allowed",
+    )
+        .into_response()
+}
+
+fn removal_details(options: &ContainerRemovalOptions) -> Option<String> {
+    match (options.force, options.remove_volumes) {
+        (false, false) => None,
+        (true, false) => Some("force".to_string()),
+        (false, true) => Some("with volumes".to_string()),
+        (true, true) => Some("force, with volumes".to_string()),
+    }
+}
+
+async fn volumes_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let volumes = state
+        .backend
+        .list_volumes()
+        .await
+        .map_err(|e| e.to_string());
+
+    pages::render(&VolumesPage {
+        nav: Nav::new(&state.config, session.as_deref()).with_link("/api/volumes", "🔗 JSON API"),
+        volumes,
+    })
+}
+
+async fn create_volume_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<CreateVolumeParams>,
+) -> impl IntoResponse {
+    let request = CreateVolumeRequest {
+        name: params.name.trim().to_string(),
+        driver: params.driver,
+        labels: std::collections::HashMap::new(),
     };
 
-    // Replace placeholders in template
-    let html_output = template
-        .replace("{{RUNNING_CONTAINERS_ROWS}}", &running_containers_rows)
-        .replace("{{IMAGE_ROWS}}", &image_rows)
-        .replace("{{AUTH_LOGOUT_BUTTON}}", logout_button);
+    let result = state.backend.create_volume(&request).await;
+    audit(
+        &state,
+        &session,
+        "volume",
+        "create",
+        &request.name,
+        None,
+        &result,
+    )
+    .await;
 
-    Html(html_output)
+    match result {
+        Ok(volume) => {
+            tracing::info!("Created volume {}", volume.name);
+            Redirect::to("/volumes").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to create volume {}: {}", request.name, e);
+            pages::error_page(
+                format!(
+                    "Error creating volume {}: {}",
+                    request.name,
+                    docker::error_message(&e)
+                ),
+                "/volumes",
+            )
+        }
+    }
 }
 
-async fn start_image_handler(
-    State(_state): State<Arc<AppState>>,
-    Form(params): Form<StartImageParams>,
+async fn remove_volume_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<RemoveVolumeParams>,
 ) -> impl IntoResponse {
-    match docker::create_and_start_container_from_image(&params.image_name).await {
-        Ok(_) => Redirect::to("/").into_response(),
+    let result = state
+        .backend
+        .remove_volume(&params.name, params.force)
+        .await;
+    audit(
+        &state,
+        &session,
+        "volume",
+        "remove",
+        &params.name,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("Removed volume {} (force: {})", params.name, params.force);
+            Redirect::to("/volumes").into_response()
+        }
         Err(e) => {
-            tracing::error!(
-                "Failed to start container from image {}: {}",
-                params.image_name,
-                e
+            tracing::error!("Failed to remove volume {}: {}", params.name, e);
+            pages::error_page(
+                format!(
+                    "Error removing volume {}: {}",
+                    params.name,
+                    docker::error_message(&e)
+                ),
+                "/volumes",
+            )
+        }
+    }
+}
+
+async fn prune_volumes_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let result = state.backend.prune_volumes().await;
+    audit(&state, &session, "volume", "prune", "unused", None, &result).await;
+
+    match result {
+        Ok(result) => {
+            tracing::info!(
+                "Pruned {} unused volumes, reclaimed {} bytes",
+                result.deleted.len(),
+                result.space_reclaimed_bytes
             );
-            Html(format!(
-                "Error starting container from image {}: {}. <a href=\"/\">Go back</a>",
-                html_escape::encode_text(&params.image_name),
-                e
-            ))
-            .into_response()
+            Redirect::to("/volumes").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to prune volumes: {}", e);
+            pages::error_page(
+                format!("Error pruning volumes: {}", docker::error_message(&e)),
+                "/volumes",
+            )
         }
     }
 }
 
-async fn start_container_handler(Path(container_id): Path<String>) -> impl IntoResponse {
-    match docker::start_container(&container_id).await {
-        Ok(_) => Redirect::to("/").into_response(),
-        Err(e) => Html(format!("Error starting container {}: {}", container_id, e)).into_response(),
+async fn volumes_json_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.backend.list_volumes().await {
+        Ok(volumes) => Json(volumes).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to list volumes: {}", e);
+            (
+                docker_error_status(&e),
+                format!("Error listing volumes: {}", e),
+            )
+                .into_response()
+        }
     }
 }
 
-async fn stop_container_handler(Path(container_id): Path<String>) -> impl IntoResponse {
-    match docker::stop_container(&container_id).await {
-        Ok(_) => Redirect::to("/").into_response(),
-        Err(e) => Html(format!("Error stopping container {}: {}", container_id, e)).into_response(),
+async fn volume_info_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.backend.inspect_volume(&name).await {
+        Ok(volume) => Json(volume).into_response(),
+        Err(e) => (
+            docker_error_status(&e),
+            format!("Error inspecting volume: {}", docker::error_message(&e)),
+        )
+            .into_response(),
     }
 }
 
-async fn restart_container_handler(Path(container_id): Path<String>) -> impl IntoResponse {
-    match docker::restart_container(&container_id).await {
-        Ok(_) => Redirect::to("/").into_response(),
-        Err(e) => Html(format!(
-            "Error restarting container {}: {}",
-            container_id, e
-        ))
-        .into_response(),
+async fn create_volume_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Json(request): Json<CreateVolumeRequest>,
+) -> impl IntoResponse {
+    let result = state.backend.create_volume(&request).await;
+    audit(
+        &state,
+        &session,
+        "volume",
+        "create",
+        &request.name,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(volume) => {
+            tracing::info!("Created volume {} via API", volume.name);
+            (StatusCode::CREATED, Json(volume)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to create volume {}: {}", request.name, e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error creating volume: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
+        }
     }
 }
 
-async fn metrics_json_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let docker_socket = state.config.docker_socket.as_deref();
-    match docker::get_all_metrics_with_config(docker_socket).await {
-        Ok(metrics) => Json(metrics).into_response(),
+async fn remove_volume_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(name): Path<String>,
+    Query(params): Query<RemoveVolumeQuery>,
+) -> impl IntoResponse {
+    let result = state.backend.remove_volume(&name, params.force).await;
+    audit(&state, &session, "volume", "remove", &name, None, &result).await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("Removed volume {} via API (force: {})", name, params.force);
+            Json(ActionResponse {
+                success: true,
+                message: format!("Volume {} removed", name),
+            })
+            .into_response()
+        }
         Err(e) => {
-            tracing::error!("Failed to get metrics: {}", e);
+            tracing::error!("Failed to remove volume {}: {}", name, e);
             (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error getting metrics: {}", e),
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error removing volume: {}", docker::error_message(&e)),
+                }),
             )
                 .into_response()
         }
     }
 }
 
-async fn metrics_dashboard_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let template = include_str!("../templates/dashboard.html");
+async fn prune_volumes_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let result = state.backend.prune_volumes().await;
+    audit(&state, &session, "volume", "prune", "unused", None, &result).await;
 
-    // Generate logout button if auth is enabled
-    let logout_button = if state.config.auth_enabled {
-        r#"<form action="/logout" method="post" style="display: inline;">
-            <button type="submit" class="btn btn-logout" style="background: #e74c3c; color: white; padding: 0.5rem 1rem; border: none; border-radius: 5px; cursor: pointer;">🚪 Logout</button>
-        </form>"#
-    } else {
-        ""
-    };
+    match result {
+        Ok(result) => {
+            tracing::info!(
+                "Pruned {} unused volumes via API, reclaimed {} bytes",
+                result.deleted.len(),
+                result.space_reclaimed_bytes
+            );
+            Json(result).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to prune volumes: {}", e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error pruning volumes: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
 
-    let html_output = template.replace("{{AUTH_LOGOUT_BUTTON}}", logout_button);
-    Html(html_output)
+/// Split a comma-separated alias list from a form field
+fn split_aliases(aliases: Option<&str>) -> Vec<String> {
+    aliases
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
-async fn health_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let docker_socket = state.config.docker_socket.as_deref();
-    let docker_available = crate::docker::list_running_containers_with_config(docker_socket)
+async fn networks_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let containers = state
+        .backend
+        .list_all_containers()
         .await
-        .is_ok();
+        .unwrap_or_default();
+    let networks = state
+        .backend
+        .list_networks()
+        .await
+        .map_err(|e| e.to_string());
 
-    let health = HealthResponse {
-        status: if docker_available {
-            "healthy".to_string()
-        } else {
-            "unhealthy".to_string()
-        },
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        docker_available,
-        timestamp: chrono::Utc::now().to_rfc3339(),
-    };
+    pages::render(&NetworksPage {
+        nav: Nav::new(&state.config, session.as_deref()).with_link("/api/networks", "🔗 JSON API"),
+        networks,
+        containers,
+    })
+}
 
-    let status_code = if docker_available {
-        StatusCode::OK
-    } else {
-        StatusCode::SERVICE_UNAVAILABLE
+async fn create_network_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<CreateNetworkParams>,
+) -> impl IntoResponse {
+    let request = CreateNetworkRequest {
+        name: params.name.trim().to_string(),
+        driver: params.driver,
+        internal: params.internal,
+        labels: std::collections::HashMap::new(),
     };
 
-    (status_code, Json(health))
-}
+    let result = state.backend.create_network(&request).await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "create",
+        &request.name,
+        None,
+        &result,
+    )
+    .await;
 
-async fn readiness_handler() -> impl IntoResponse {
-    // Simple readiness check - just verify we can respond
-    Json(serde_json::json!({
-        "status": "ready",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
+    match result {
+        Ok(network) => {
+            tracing::info!("Created network {} ({})", network.name, network.id);
+            Redirect::to("/networks").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to create network {}: {}", request.name, e);
+            pages::error_page(
+                format!(
+                    "Error creating network {}: {}",
+                    request.name,
+                    docker::error_message(&e)
+                ),
+                "/networks",
+            )
+        }
+    }
 }
 
-async fn config_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    Json(ConfigResponse {
-        metrics_interval_seconds: state.config.metrics_interval_seconds,
-        metrics_history_limit: state.config.metrics_history_limit,
-        max_chart_containers: state.config.max_chart_containers,
-    })
+async fn remove_network_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<RemoveNetworkParams>,
+) -> impl IntoResponse {
+    let result = state.backend.remove_network(&params.network).await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "remove",
+        &params.network,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("Removed network {}", params.network);
+            Redirect::to("/networks").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to remove network {}: {}", params.network, e);
+            pages::error_page(
+                format!(
+                    "Error removing network {}: {}",
+                    params.network,
+                    docker::error_message(&e)
+                ),
+                "/networks",
+            )
+        }
+    }
 }
 
-async fn logs_handler(
-    Path(container_id): Path<String>,
-    Query(params): Query<LogQuery>,
+async fn connect_network_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<ConnectNetworkParams>,
 ) -> impl IntoResponse {
-    let tail = params.tail.as_deref();
-
-    // Get container info first
-    let container_info = match crate::docker::list_running_containers().await {
-        Ok(containers) => containers
-            .into_iter()
-            .find(|c| c.id == container_id || c.name == container_id),
-        Err(_) => None,
+    let request = NetworkConnectRequest {
+        container: params.container.clone(),
+        aliases: split_aliases(params.aliases.as_deref()),
     };
 
-    let container_name = container_info
-        .map(|c| c.name)
-        .unwrap_or_else(|| container_id.clone());
+    let result = state
+        .backend
+        .connect_container_to_network(&params.network, &request)
+        .await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "connect",
+        &params.network,
+        Some(format!("container {}", params.container)),
+        &result,
+    )
+    .await;
 
-    // Get recent logs
-    let logs_result = crate::docker::get_container_logs_recent(&container_id, tail).await;
-    let logs_content = match logs_result {
-        Ok(logs) => logs.join("\n"),
-        Err(e) => format!("Error fetching logs: {}", e),
-    };
-
-    // Load the logs template
-    let template = include_str!("../templates/logs.html");
+    match result {
+        Ok(_) => {
+            tracing::info!(
+                "Connected container {} to network {}",
+                params.container,
+                params.network
+            );
+            Redirect::to("/networks").into_response()
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to connect container {} to network {}: {}",
+                params.container,
+                params.network,
+                e
+            );
+            pages::error_page(
+                format!(
+                    "Error connecting container to network: {}",
+                    docker::error_message(&e)
+                ),
+                "/networks",
+            )
+        }
+    }
+}
 
-    let html_output = template
-        .replace("{{CONTAINER_ID}}", &html_escape::encode_text(&container_id))
-        .replace(
-            "{{CONTAINER_NAME}}",
-            &html_escape::encode_text(&container_name),
-        )
-        .replace("{{LOGS_CONTENT}}", &html_escape::encode_text(&logs_content))
-        .replace("{{TAIL_VALUE}}", tail.unwrap_or("1000"));
+async fn disconnect_network_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<DisconnectNetworkParams>,
+) -> impl IntoResponse {
+    let result = state
+        .backend
+        .disconnect_container_from_network(&params.network, &params.container, false)
+        .await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "disconnect",
+        &params.network,
+        Some(format!("container {}", params.container)),
+        &result,
+    )
+    .await;
 
-    Html(html_output)
+    match result {
+        Ok(_) => {
+            tracing::info!(
+                "Disconnected container {} from network {}",
+                params.container,
+                params.network
+            );
+            Redirect::to("/networks").into_response()
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to disconnect container {} from network {}: {}",
+                params.container,
+                params.network,
+                e
+            );
+            pages::error_page(
+                format!(
+                    "Error disconnecting container from network: {}",
+                    docker::error_message(&e)
+                ),
+                "/networks",
+            )
+        }
+    }
 }
 
-async fn logs_ws_handler(Path(container_id): Path<String>, ws: WebSocketUpgrade) -> Response {
-    ws.on_upgrade(move |socket| logs_websocket(socket, container_id))
+async fn networks_json_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.backend.list_networks().await {
+        Ok(networks) => Json(networks).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to list networks: {}", e);
+            (
+                docker_error_status(&e),
+                format!("Error listing networks: {}", e),
+            )
+                .into_response()
+        }
+    }
 }
 
-async fn logs_websocket(mut socket: WebSocket, container_id: String) {
-    // Get the logs stream
-    let logs_stream =
-        match crate::docker::get_container_logs(&container_id, Some("100"), true).await {
-            Ok(stream) => stream,
-            Err(e) => {
-                let _ = socket.send(Message::Text(format!("Error: {}", e))).await;
-                let _ = socket.close().await;
-                return;
-            }
-        };
-
-    let mut logs_stream = std::pin::pin!(logs_stream);
+async fn network_info_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.backend.inspect_network(&id).await {
+        Ok(network) => Json(network).into_response(),
+        Err(e) => (
+            docker_error_status(&e),
+            format!("Error inspecting network: {}", docker::error_message(&e)),
+        )
+            .into_response(),
+    }
+}
 
-    // Send initial message
-    let _ = socket
-        .send(Message::Text("Connected to logs stream...".to_string()))
-        .await;
+async fn create_network_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Json(request): Json<CreateNetworkRequest>,
+) -> impl IntoResponse {
+    let result = state.backend.create_network(&request).await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "create",
+        &request.name,
+        None,
+        &result,
+    )
+    .await;
 
-    // Stream logs to websocket
-    while let Some(log_result) = logs_stream.next().await {
-        match log_result {
-            Ok(log_output) => {
-                let log_text = String::from_utf8_lossy(&log_output.into_bytes())
-                    .trim()
-                    .to_string();
-                if !log_text.is_empty() && socket.send(Message::Text(log_text)).await.is_err() {
-                    break;
-                }
-            }
-            Err(e) => {
-                let _ = socket.send(Message::Text(format!("Error: {}", e))).await;
-                break;
-            }
+    match result {
+        Ok(network) => {
+            tracing::info!("Created network {} via API", network.name);
+            (StatusCode::CREATED, Json(network)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to create network {}: {}", request.name, e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error creating network: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
         }
     }
-
-    let _ = socket.close().await;
 }
 
-async fn login_handler_wrapper(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    // If auth is disabled, redirect to main page
-    if !state.config.auth_enabled {
-        return Redirect::to("/").into_response();
-    }
+async fn remove_network_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let result = state.backend.remove_network(&id).await;
+    audit(&state, &session, "network", "remove", &id, None, &result).await;
 
-    let template = include_str!("../templates/login.html");
-    let html = template.replace("{{ERROR_MESSAGE}}", "");
-    Html(html).into_response()
+    match result {
+        Ok(_) => {
+            tracing::info!("Removed network {} via API", id);
+            Json(ActionResponse {
+                success: true,
+                message: format!("Network {} removed", id),
+            })
+            .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to remove network {}: {}", id, e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error removing network: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
+        }
+    }
 }
 
-#[axum::debug_handler]
-async fn login_post_handler_wrapper(
+async fn connect_network_api_handler(
     State(state): State<Arc<AppState>>,
-    Form(form): Form<LoginForm>,
+    session: Option<Extension<Session>>,
+    Path(id): Path<String>,
+    Json(request): Json<NetworkConnectRequest>,
 ) -> impl IntoResponse {
-    // If auth is disabled, redirect to main page
-    if !state.config.auth_enabled {
-        return Redirect::to("/").into_response();
+    let result = state
+        .backend
+        .connect_container_to_network(&id, &request)
+        .await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "connect",
+        &id,
+        Some(format!("container {}", request.container)),
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!(
+                "Connected container {} to network {} via API",
+                request.container,
+                id
+            );
+            Json(ActionResponse {
+                success: true,
+                message: format!("Container {} connected to {}", request.container, id),
+            })
+            .into_response()
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to connect container {} to network {}: {}",
+                request.container,
+                id,
+                e
+            );
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error connecting container: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
+        }
     }
+}
 
-    // Verify credentials
-    if form.username == state.config.auth_username {
-        match state.config.verify_password(&form.password) {
-            Ok(true) => {
-                // Create session
-                let session_id = state.session_store.create_session(&form.username).await;
-
-                // Set session cookie and redirect
-                let cookie = format!(
-                    "session_id={}; HttpOnly; SameSite=Strict; Path=/; Max-Age={}",
-                    session_id, state.config.session_timeout_seconds
-                );
+async fn disconnect_network_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(id): Path<String>,
+    Json(request): Json<NetworkDisconnectRequest>,
+) -> impl IntoResponse {
+    let result = state
+        .backend
+        .disconnect_container_from_network(&id, &request.container, request.force)
+        .await;
+    audit(
+        &state,
+        &session,
+        "network",
+        "disconnect",
+        &id,
+        Some(format!("container {}", request.container)),
+        &result,
+    )
+    .await;
 
-                let mut response = Redirect::to("/").into_response();
-                response
-                    .headers_mut()
-                    .insert("Set-Cookie", HeaderValue::from_str(&cookie).unwrap());
-                response
-            }
-            _ => {
-                tracing::warn!("Failed login attempt for user: {}", form.username);
-                let template = include_str!("../templates/login.html");
-                let error_html =
-                    r#"<div class="error-message">❌ Invalid username or password</div>"#;
-                let html = template.replace("{{ERROR_MESSAGE}}", error_html);
-                Html(html).into_response()
-            }
+    match result {
+        Ok(_) => {
+            tracing::info!(
+                "Disconnected container {} from network {} via API",
+                request.container,
+                id
+            );
+            Json(ActionResponse {
+                success: true,
+                message: format!("Container {} disconnected from {}", request.container, id),
+            })
+            .into_response()
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to disconnect container {} from network {}: {}",
+                request.container,
+                id,
+                e
+            );
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!(
+                        "Error disconnecting container: {}",
+                        docker::error_message(&e)
+                    ),
+                }),
+            )
+                .into_response()
         }
-    } else {
-        tracing::warn!("Failed login attempt for unknown user: {}", form.username);
-        let template = include_str!("../templates/login.html");
-        let error_html = r#"<div class="error-message">❌ Invalid username or password</div>"#;
-        let html = template.replace("{{ERROR_MESSAGE}}", error_html);
-        Html(html).into_response()
     }
 }
 
-async fn logout_handler_wrapper(
+async fn index_handler(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    session: Option<Extension<Session>>,
 ) -> impl IntoResponse {
-    // Extract session ID from cookie and remove session
-    if let Some(cookie_header) = headers.get("cookie") {
-        if let Ok(cookie_str) = cookie_header.to_str() {
-            if let Some(session_id) = extract_session_id(cookie_str) {
-                state.session_store.remove_session(&session_id).await;
-            }
+    // Withhold secret environment values; only admins may reveal them
+    let running_containers = state
+        .backend
+        .list_running_containers()
+        .await
+        .map(|containers| {
+            containers
+                .into_iter()
+                .map(|container| RunningContainer {
+                    environment: state.secrets.mask(&container.environment),
+                    container,
+                })
+                .collect()
+        })
+        .map_err(|e| e.to_string());
+    let can_reveal = session
+        .as_ref()
+        .is_none_or(|Extension(session)| session.role >= Role::Admin);
+
+    pages::render(&ManagementPage {
+        nav: Nav::new(&state.config, session.as_deref()).with_link("/api/metrics", "🔗 JSON API"),
+        running_containers,
+        containers: state
+            .backend
+            .list_all_containers()
+            .await
+            .map_err(|e| e.to_string()),
+        images: state
+            .backend
+            .list_downloaded_images()
+            .await
+            .map_err(|e| e.to_string()),
+        prune_preview: state
+            .backend
+            .preview_dangling_image_prune()
+            .await
+            .map_err(|e| e.to_string()),
+        can_reveal,
+    })
+}
+
+async fn start_image_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<StartImageParams>,
+) -> impl IntoResponse {
+    let result = state
+        .backend
+        .create_and_start_container_from_image(&params.image_name)
+        .await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "create",
+        &params.image_name,
+        Some(format!("image {}", params.image_name)),
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => Redirect::to("/").into_response(),
+        Err(e) => {
+            tracing::error!(
+                "Failed to start container from image {}: {}",
+                params.image_name,
+                e
+            );
+            pages::error_page(
+                format!(
+                    "Error starting container from image {}: {}",
+                    params.image_name,
+                    docker::error_message(&e)
+                ),
+                "/",
+            )
         }
     }
+}
 
-    // Clear cookie and redirect to login
-    let mut response = Redirect::to("/login").into_response();
-    response.headers_mut().insert(
-        "Set-Cookie",
-        HeaderValue::from_str("session_id=; HttpOnly; SameSite=Strict; Path=/; Max-Age=0").unwrap(),
-    );
-    response
+async fn start_container_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+) -> impl IntoResponse {
+    let result = state.backend.start_container(&container_id).await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "start",
+        &container_id,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => Redirect::to("/").into_response(),
+        Err(e) => pages::error_page(
+            format!("Error starting container {}: {}", container_id, e),
+            "/",
+        ),
+    }
 }
 
-fn extract_session_id(cookie_str: &str) -> Option<String> {
-    for cookie in cookie_str.split(';') {
-        let cookie = cookie.trim();
-        if let Some(value) = cookie.strip_prefix("session_id=") {
-            return Some(value.to_string());
-        }
+async fn stop_container_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+) -> impl IntoResponse {
+    let result = state.backend.stop_container(&container_id).await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "stop",
+        &container_id,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => Redirect::to("/").into_response(),
+        Err(e) => pages::error_page(
+            format!("Error stopping container {}: {}", container_id, e),
+            "/",
+        ),
     }
-    None
 }
 
-async fn start_image_enhanced_handler(
-    State(_state): State<Arc<AppState>>,
-    Form(params): Form<EnhancedStartImageParams>,
+async fn restart_container_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
 ) -> impl IntoResponse {
-    // Parse environment variables from JSON string
-    let environment_variables = if let Some(env_str) = &params.environment_variables {
-        if env_str.trim().is_empty() {
-            Vec::new()
-        } else {
-            match serde_json::from_str::<Vec<EnvironmentVariable>>(env_str) {
-                Ok(vars) => vars,
-                Err(e) => {
-                    tracing::error!("Failed to parse environment variables: {}", e);
-                    return Html(format!(
-                        "Error parsing environment variables: {}. <a href=\"/\">Go back</a>",
-                        e
-                    ))
-                    .into_response();
-                }
-            }
-        }
-    } else {
-        Vec::new()
-    };
+    let result = state.backend.restart_container(&container_id).await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "restart",
+        &container_id,
+        None,
+        &result,
+    )
+    .await;
 
-    // Parse port mappings from JSON string
-    let port_mappings = if let Some(ports_str) = &params.port_mappings {
-        if ports_str.trim().is_empty() {
-            Vec::new()
-        } else {
-            match serde_json::from_str::<Vec<ContainerPortMapping>>(ports_str) {
-                Ok(ports) => ports,
-                Err(e) => {
-                    tracing::error!("Failed to parse port mappings: {}", e);
-                    return Html(format!(
-                        "Error parsing port mappings: {}. <a href=\"/\">Go back</a>",
-                        e
-                    ))
-                    .into_response();
-                }
-            }
-        }
-    } else {
-        Vec::new()
-    };
+    match result {
+        Ok(_) => Redirect::to("/").into_response(),
+        Err(e) => pages::error_page(
+            format!("Error restarting container {}: {}", container_id, e),
+            "/",
+        ),
+    }
+}
 
-    let request = CreateContainerRequest {
-        image_name: params.image_name.clone(),
-        container_name: params.container_name.filter(|s| !s.trim().is_empty()),
-        environment_variables,
-        port_mappings,
-        restart_policy: params.restart_policy.filter(|s| !s.trim().is_empty()),
-    };
+async fn remove_container_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+    Form(options): Form<ContainerRemovalOptions>,
+) -> impl IntoResponse {
+    let result = state
+        .backend
+        .remove_container(&container_id, &options)
+        .await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "remove",
+        &container_id,
+        removal_details(&options),
+        &result,
+    )
+    .await;
 
-    match docker::create_and_start_container_enhanced(request).await {
-        Ok(container_id) => {
+    match result {
+        Ok(_) => {
             tracing::info!(
-                "Successfully created and started container {} from image {}",
+                "Removed container {} (force: {}, volumes: {})",
                 container_id,
-                params.image_name
+                options.force,
+                options.remove_volumes
             );
             Redirect::to("/").into_response()
         }
         Err(e) => {
-            tracing::error!(
-                "Failed to start container from image {}: {}",
-                params.image_name,
-                e
-            );
-            Html(format!(
-                "Error starting container from image {}: {}. <a href=\"/\">Go back</a>",
-                html_escape::encode_text(&params.image_name),
-                e
-            ))
-            .into_response()
+            tracing::error!("Failed to remove container {}: {}", container_id, e);
+            pages::error_page(
+                format!("Error removing container {}: {}", container_id, e),
+                "/",
+            )
         }
     }
 }
 
-async fn image_info_handler(Path(image_name): Path<String>) -> impl IntoResponse {
-    // URL decode the image name (in case it contains special characters like :)
-    let decoded_image_name = urlencoding::decode(&image_name)
-        .map_err(|e| format!("Invalid image name encoding: {}", e))
-        .unwrap_or_else(|_| std::borrow::Cow::Borrowed(&image_name));
+async fn remove_container_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+    Query(options): Query<ContainerRemovalOptions>,
+) -> impl IntoResponse {
+    let result = state
+        .backend
+        .remove_container(&container_id, &options)
+        .await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "remove",
+        &container_id,
+        removal_details(&options),
+        &result,
+    )
+    .await;
 
-    match docker::get_image_info(&decoded_image_name).await {
-        Ok(image_info) => Json(image_info).into_response(),
+    match result {
+        Ok(_) => {
+            tracing::info!(
+                "Removed container {} via API (force: {}, volumes: {})",
+                container_id,
+                options.force,
+                options.remove_volumes
+            );
+            Json(ActionResponse {
+                success: true,
+                message: format!("Container {} removed", container_id),
+            })
+            .into_response()
+        }
         Err(e) => {
-            tracing::error!("Failed to get image info for {}: {}", decoded_image_name, e);
+            tracing::error!("Failed to remove container {}: {}", container_id, e);
             (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error getting image info: {}", e),
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error removing container: {}", e),
+                }),
             )
                 .into_response()
         }
     }
 }
 
-pub fn app_router(config: &Config) -> Router {
-    let state = Arc::new(AppState {
-        config: config.clone(),
-        session_store: Arc::new(SessionStore::new(Arc::new(config.clone()))),
+/// A container's environment variables, with the values of secrets withheld
+async fn container_environment_handler(
+    State(state): State<Arc<AppState>>,
+    Path(container_id): Path<String>,
+) -> impl IntoResponse {
+    match state.backend.get_container_environment(&container_id).await {
+        Ok(environment) => Json(state.secrets.mask(&environment)).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to inspect container {}: {}", container_id, e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error inspecting container: {}", e),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Show the value of one environment variable, secret or not. Every reveal is recorded
+/// on the timeline with the variable's name.
+async fn reveal_environment_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+    Json(request): Json<RevealEnvironmentRequest>,
+) -> impl IntoResponse {
+    let result = state
+        .backend
+        .get_container_environment(&container_id)
+        .await
+        .map_err(|e| (docker_error_status(&e), docker::error_message(&e)))
+        .and_then(|environment| {
+            secrets::env_value(&environment, &request.key)
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    (
+                        StatusCode::NOT_FOUND,
+                        format!("No environment variable named {}", request.key),
+                    )
+                })
+        });
+
+    let username = session_username(&session);
+    state
+        .timeline
+        .record_action(
+            username.as_deref(),
+            "container",
+            "reveal-env",
+            &container_id,
+            Some(request.key.clone()),
+            result.as_ref().err().map(|(_, message)| message.clone()),
+        )
+        .await;
+
+    match result {
+        Ok(value) => {
+            tracing::info!(
+                "{} revealed environment variable {} of container {}",
+                username.as_deref().unwrap_or("anonymous"),
+                request.key,
+                container_id
+            );
+            Json(EnvironmentVariable {
+                key: request.key,
+                value,
+            })
+            .into_response()
+        }
+        Err((status, message)) => (
+            status,
+            Json(ActionResponse {
+                success: false,
+                message,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn containers_json_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ContainerListQuery>,
+) -> impl IntoResponse {
+    match state.backend.list_all_containers().await {
+        Ok(containers) => {
+            let containers: Vec<ContainerStateSummary> = match params.state.as_deref() {
+                Some(filter) if !filter.is_empty() => containers
+                    .into_iter()
+                    .filter(|c| c.state.eq_ignore_ascii_case(filter))
+                    .collect(),
+                _ => containers,
+            };
+            Json(containers).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to list containers: {}", e);
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error listing containers: {}", e),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Server-sent events feed of Docker engine events. A subscriber that falls behind gets a
+/// `lagged` event so the page can reload instead of showing stale state.
+async fn events_stream_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let receiver = state.events.subscribe();
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(event) => Event::default().event("docker").json_data(&event),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                Ok(Event::default().event("lagged").data(skipped.to_string()))
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((event, receiver))
     });
 
-    Router::new()
-        .route("/", get(index_handler))
-        .route("/health", get(health_handler))
-        .route("/ready", get(readiness_handler))
-        .route("/api/config", get(config_handler))
-        .route("/api/image/:image_name", get(image_info_handler))
-        .route("/start-image", post(start_image_handler))
-        .route("/start/:id", post(start_container_handler))
-        .route("/stop/:id", post(stop_container_handler))
-        .route("/restart/:id", post(restart_container_handler))
-        .route("/metrics", get(metrics_dashboard_handler))
-        .route("/api/metrics", get(metrics_json_handler))
-        .route("/logs/:id", get(logs_handler))
-        .route("/logs/:id/ws", get(logs_ws_handler))
-        .route("/login", get(login_handler_wrapper))
-        .route("/login", post(login_post_handler_wrapper))
-        .route("/logout", post(logout_handler_wrapper))
-        .route("/start-image-enhanced", post(start_image_enhanced_handler))
-        .nest_service("/static", ServeDir::new("static"))
-        .layer(middleware::from_fn_with_state(
-            state.session_store.clone(),
-            crate::auth::auth_middleware,
-        ))
-        .with_state(state)
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn events_json_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TimelineQuery>,
+) -> impl IntoResponse {
+    match params.into_filter() {
+        Ok(filter) => Json(state.timeline.query(&filter).await).into_response(),
+        Err(message) => (
+            StatusCode::BAD_REQUEST,
+            Json(ActionResponse {
+                success: false,
+                message,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn events_page_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Query(params): Query<TimelineQuery>,
+) -> impl IntoResponse {
+    let field = |value: &Option<String>| value.clone().unwrap_or_default();
+    let page = EventsPage {
+        nav: Nav::new(&state.config, session.as_deref()).with_link("/api/events", "🔗 JSON API"),
+        source_options: SelectOption::list(
+            &[("", "All sources"), ("docker", "Docker"), ("user", "User")],
+            params.source.as_deref(),
+        ),
+        kind_options: SelectOption::list(
+            &[
+                ("", "All types"),
+                ("container", "Containers"),
+                ("image", "Images"),
+                ("volume", "Volumes"),
+                ("network", "Networks"),
+            ],
+            params.kind.as_deref(),
+        ),
+        action: field(&params.action),
+        target: field(&params.target),
+        username: field(&params.username),
+        from: field(&params.from),
+        to: field(&params.to),
+        limit: params
+            .limit
+            .clone()
+            .filter(|l| !l.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_QUERY_LIMIT.to_string()),
+        entries: match params.into_filter() {
+            Ok(filter) => Ok(state.timeline.query(&filter).await),
+            Err(message) => Err(message),
+        },
+    };
+
+    pages::render(&page)
+}
+
+async fn metrics_json_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.stats.snapshot().await {
+        Some(metrics) => Json(metrics).into_response(),
+        None => metrics_not_ready(),
+    }
+}
+
+async fn prometheus_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.stats.snapshot().await {
+        Some(metrics) => (
+            [(
+                axum::http::header::CONTENT_TYPE,
+                "text/plain; version=0.0.4; charset=utf-8",
+            )],
+            crate::prometheus::render(&metrics),
+        )
+            .into_response(),
+        None => metrics_not_ready(),
+    }
+}
+
+/// Returned until the stats collector has taken its first sample (or while Docker is unreachable)
+fn metrics_not_ready() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Metrics are not available yet; the collector has not reached Docker".to_string(),
+    )
+        .into_response()
+}
+
+async fn metrics_history_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<MetricsHistoryQuery>,
+) -> impl IntoResponse {
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return (
+                StatusCode::BAD_REQUEST,
+                "`from` must not be later than `to`".to_string(),
+            )
+                .into_response();
+        }
+    }
+
+    let container = params.container.as_deref().filter(|c| !c.is_empty());
+    Json(
+        state
+            .stats
+            .history()
+            .response(container, params.from, params.to)
+            .await,
+    )
+    .into_response()
+}
+
+async fn metrics_dashboard_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    pages::render(&DashboardPage {
+        nav: Nav::new(&state.config, session.as_deref()).with_link("/api/metrics", "🔗 JSON API"),
+    })
+}
+
+async fn health_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let docker_available = state.backend.list_running_containers().await.is_ok();
+
+    let health = HealthResponse {
+        status: if docker_available {
+            "healthy".to_string()
+        } else {
+            "unhealthy".to_string()
+        },
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        docker_available,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let status_code = if docker_available {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status_code, Json(health))
+}
+
+async fn readiness_handler() -> impl IntoResponse {
+    // Simple readiness check - just verify we can respond
+    Json(serde_json::json!({
+        "status": "ready",
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+async fn config_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(ConfigResponse {
+        metrics_interval_seconds: state.config.metrics_interval_seconds,
+        metrics_history_limit: state.config.metrics_history_limit,
+        max_chart_containers: state.config.max_chart_containers,
+    })
+}
+
+async fn logs_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+    Query(params): Query<LogQuery>,
+) -> impl IntoResponse {
+    let tail = params.tail.as_deref();
+
+    // Get container info first
+    let container_info = match state.backend.list_running_containers().await {
+        Ok(containers) => containers
+            .into_iter()
+            .find(|c| c.id == container_id || c.name == container_id),
+        Err(_) => None,
+    };
+
+    let container_name = container_info
+        .map(|c| c.name)
+        .unwrap_or_else(|| container_id.clone());
+
+    // Get recent logs
+    let logs_result = state
+        .backend
+        .get_container_logs_recent(&container_id, tail)
+        .await;
+    let logs_content = match logs_result {
+        Ok(logs) => logs.join("\n"),
+        Err(e) => format!("Error fetching logs: {}", e),
+    };
+
+    pages::render(&LogsPage {
+        nav: Nav::new(&state.config, session.as_deref()).with_link("/api/metrics", "🔗 JSON API"),
+        container_id,
+        container_name,
+        tail: tail.unwrap_or("1000").to_string(),
+        logs: logs_content,
+    })
+}
+
+async fn logs_ws_handler(
+    State(state): State<Arc<AppState>>,
+    Path(container_id): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !is_same_origin(&headers) {
+        return cross_origin_rejection(&format!("/logs/{}/ws", container_id));
+    }
+    let backend = state.backend.clone();
+    ws.on_upgrade(move |socket| logs_websocket(socket, container_id, backend))
+}
+
+async fn logs_websocket(
+    mut socket: WebSocket,
+    container_id: String,
+    backend: Arc<dyn DockerBackend>,
+) {
+    // Get the logs stream
+    let mut logs_stream = match backend
+        .get_container_logs(&container_id, Some("100"), true)
+        .await
+    {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = socket.send(Message::Text(format!("Error: {}", e))).await;
+            let _ = socket.close().await;
+            return;
+        }
+    };
+
+    // Send initial message
+    let _ = socket
+        .send(Message::Text("Connected to logs stream...".to_string()))
+        .await;
+
+    // Stream logs to websocket
+    while let Some(log_result) = logs_stream.next().await {
+        match log_result {
+            Ok(log_output) => {
+                let log_text = String::from_utf8_lossy(&log_output.into_bytes())
+                    .trim()
+                    .to_string();
+                if !log_text.is_empty() && socket.send(Message::Text(log_text)).await.is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                let _ = socket.send(Message::Text(format!("Error: {}", e))).await;
+                break;
+            }
+        }
+    }
+
+    let _ = socket.close().await;
+}
+
+async fn exec_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+    Query(params): Query<ExecQuery>,
+) -> impl IntoResponse {
+    // Get container info first
+    let container_info = match state.backend.list_running_containers().await {
+        Ok(containers) => containers
+            .into_iter()
+            .find(|c| c.id == container_id || c.name == container_id),
+        Err(_) => None,
+    };
+
+    let container_name = container_info
+        .map(|c| c.name)
+        .unwrap_or_else(|| container_id.clone());
+
+    pages::render(&ExecPage {
+        nav: Nav::new(&state.config, session.as_deref())
+            .with_link(format!("/logs/{}", container_id), "📜 Logs"),
+        container_id,
+        container_name,
+        shell_options: SelectOption::list(
+            &std::iter::once(("", "auto (bash or sh)"))
+                .chain(EXEC_SHELLS.map(|shell| (shell, shell)))
+                .collect::<Vec<_>>(),
+            params.shell.as_deref(),
+        ),
+    })
+}
+
+async fn exec_ws_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(container_id): Path<String>,
+    Query(params): Query<ExecQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !is_same_origin(&headers) {
+        return cross_origin_rejection(&format!("/exec/{}/ws", container_id));
+    }
+    let command = match params.shell.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(shell) if EXEC_SHELLS.contains(&shell) => Some(vec![shell.to_string()]),
+        Some(shell) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported shell {}", shell),
+            )
+                .into_response()
+        }
+    };
+    let username = session_username(&session);
+    ws.on_upgrade(move |socket| exec_websocket(socket, container_id, command, state, username))
+}
+
+async fn exec_websocket(
+    socket: WebSocket,
+    container_id: String,
+    command: Option<Vec<String>>,
+    state: Arc<AppState>,
+    username: Option<String>,
+) {
+    use tokio::io::AsyncWriteExt;
+
+    let (mut sender, mut receiver) = socket.split();
+
+    let result = state
+        .backend
+        .create_exec_session(&container_id, command)
+        .await;
+    state
+        .timeline
+        .record_action(
+            username.as_deref(),
+            "container",
+            "exec",
+            &container_id,
+            None,
+            result.as_ref().err().map(docker::error_message),
+        )
+        .await;
+
+    let session = match result {
+        Ok(session) => session,
+        Err(e) => {
+            let _ = sender.send(Message::Text(format!("Error: {}", e))).await;
+            let _ = sender.close().await;
+            return;
+        }
+    };
+
+    tracing::info!(
+        "Started exec session {} in container {}",
+        session.exec_id,
+        container_id
+    );
+
+    let crate::docker::ExecSession {
+        exec_id,
+        mut output,
+        mut input,
+    } = session;
+
+    loop {
+        tokio::select! {
+            chunk = output.next() => match chunk {
+                // Terminal output is forwarded as raw bytes so escape sequences survive intact
+                Some(Ok(log_output)) => {
+                    if sender
+                        .send(Message::Binary(log_output.into_bytes().to_vec()))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Some(Err(e)) => {
+                    let _ = sender.send(Message::Text(format!("Error: {}", e))).await;
+                    break;
+                }
+                // The shell exited
+                None => break,
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ExecClientMessage>(&text) {
+                    Ok(ExecClientMessage::Input { data }) => {
+                        if input.write_all(data.as_bytes()).await.is_err()
+                            || input.flush().await.is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Ok(ExecClientMessage::Resize { cols, rows }) => {
+                        if let Err(e) = state.backend.resize_exec(&exec_id, cols, rows)
+                        .await
+                        {
+                            tracing::warn!("Failed to resize exec session {}: {}", exec_id, e);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Ignoring malformed exec message: {}", e),
+                },
+                Some(Ok(Message::Binary(data))) => {
+                    if input.write_all(&data).await.is_err() || input.flush().await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    tracing::info!(
+        "Closed exec session {} in container {}",
+        exec_id,
+        container_id
+    );
+    let _ = sender.close().await;
+}
+
+async fn pull_image_ws_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !is_same_origin(&headers) {
+        return cross_origin_rejection("/images/pull/ws");
+    }
+    let username = session_username(&session);
+    ws.on_upgrade(move |socket| pull_image_websocket(socket, state, username))
+}
+
+fn pull_progress_message(progress: &PullProgress) -> Message {
+    Message::Text(serde_json::to_string(progress).unwrap_or_default())
+}
+
+fn pull_error(message: String) -> PullProgress {
+    PullProgress {
+        layer_id: None,
+        status: "Error".to_string(),
+        current: None,
+        total: None,
+        error: Some(message),
+        complete: true,
+    }
+}
+
+async fn pull_image_websocket(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    username: Option<String>,
+) {
+    // The first message carries the pull request, so credentials never appear in URLs or access logs
+    let request = match socket.recv().await {
+        Some(Ok(Message::Text(text))) => match serde_json::from_str::<PullImageRequest>(&text) {
+            Ok(request) if !request.image.trim().is_empty() => request,
+            Ok(_) => {
+                let error = pull_error("Image name is required".to_string());
+                let _ = socket.send(pull_progress_message(&error)).await;
+                let _ = socket.close().await;
+                return;
+            }
+            Err(e) => {
+                let error = pull_error(format!("Invalid pull request: {}", e));
+                let _ = socket.send(pull_progress_message(&error)).await;
+                let _ = socket.close().await;
+                return;
+            }
+        },
+        _ => return,
+    };
+
+    tracing::info!("Pulling image {}", request.image);
+
+    let record_pull = |error: Option<String>| {
+        let state = state.clone();
+        let username = username.clone();
+        let image = request.image.clone();
+        async move {
+            state
+                .timeline
+                .record_action(username.as_deref(), "image", "pull", &image, None, error)
+                .await
+        }
+    };
+
+    let mut pull_stream = match state.backend.pull_image(&request) {
+        Ok(stream) => stream,
+        Err(e) => {
+            let message = docker::error_message(&e);
+            record_pull(Some(message.clone())).await;
+            let error = pull_error(message);
+            let _ = socket.send(pull_progress_message(&error)).await;
+            let _ = socket.close().await;
+            return;
+        }
+    };
+
+    // Stream progress to websocket
+    while let Some(result) = pull_stream.next().await {
+        match result {
+            Ok(progress) => {
+                if socket.send(pull_progress_message(&progress)).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let message = docker::error_message(&e);
+                tracing::error!("Failed to pull image {}: {}", request.image, message);
+                record_pull(Some(message.clone())).await;
+                let _ = socket
+                    .send(pull_progress_message(&pull_error(message)))
+                    .await;
+                let _ = socket.close().await;
+                return;
+            }
+        }
+    }
+
+    tracing::info!("Successfully pulled image {}", request.image);
+    record_pull(None).await;
+    let done = PullProgress {
+        layer_id: None,
+        status: format!("Pulled {}", request.image),
+        current: None,
+        total: None,
+        error: None,
+        complete: true,
+    };
+    let _ = socket.send(pull_progress_message(&done)).await;
+    let _ = socket.close().await;
+}
+
+/// Issue an API token for the signed-in user. The token's role may not exceed theirs, and
+/// tokens cannot mint further tokens.
+async fn issue_token(
+    state: &AppState,
+    session: Option<&Session>,
+    name: &str,
+    role: Option<Role>,
+    expires_in_days: Option<u32>,
+) -> Result<(ApiToken, String), (StatusCode, String)> {
+    let Some(session) = session else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "API tokens require authentication to be enabled".to_string(),
+        ));
+    };
+    if session.token_id.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "API tokens cannot issue other tokens".to_string(),
+        ));
+    }
+    // Tokens act as a local account, whose role can be checked on every use
+    if session.method != AuthMethod::Password {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "API tokens are only available to local accounts that sign in with a password"
+                .to_string(),
+        ));
+    }
+
+    let name = name.trim();
+    if name.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Token name is required".to_string(),
+        ));
+    }
+    let role = role.unwrap_or(session.role);
+    if role > session.role {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "You cannot issue a token with the {} role; your role is {}",
+                role, session.role
+            ),
+        ));
+    }
+    let expires_at = match expires_in_days {
+        Some(0) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Expiry must be at least one day".to_string(),
+            ))
+        }
+        Some(days) => Some(Utc::now() + chrono::Duration::days(days.into())),
+        None => None,
+    };
+
+    let (token, secret) = state
+        .tokens
+        .issue(name, &session.username, role, expires_at)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to save API token: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to save API token: {}", e),
+            )
+        })?;
+    state
+        .timeline
+        .record_action(
+            Some(&session.username),
+            "token",
+            "issue",
+            &token.name,
+            Some(format!("role {}", role)),
+            None,
+        )
+        .await;
+    Ok((token, secret))
+}
+
+/// Revoke one of the signed-in user's tokens; admins may revoke anyone's
+async fn revoke_token(
+    state: &AppState,
+    session: Option<&Session>,
+    id: &str,
+) -> Result<ApiToken, (StatusCode, String)> {
+    let Some(session) = session else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "API tokens require authentication to be enabled".to_string(),
+        ));
+    };
+    let token = state
+        .tokens
+        .get(id)
+        .await
+        .filter(|token| token.owner == session.username || session.role == Role::Admin)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No such API token: {}", id)))?;
+
+    state.tokens.revoke(id).await.map_err(|e| {
+        tracing::error!("Failed to revoke API token {}: {}", id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to revoke API token: {}", e),
+        )
+    })?;
+    state
+        .timeline
+        .record_action(
+            Some(&session.username),
+            "token",
+            "revoke",
+            &token.name,
+            Some(format!("owner {}", token.owner)),
+            None,
+        )
+        .await;
+    Ok(token)
+}
+
+/// Tokens the user may see: their own, or all of them for admins
+async fn visible_tokens(state: &AppState, session: &Session) -> Vec<ApiToken> {
+    let owner = (session.role != Role::Admin).then_some(session.username.as_str());
+    state.tokens.list(owner).await
+}
+
+async fn render_tokens_page(
+    state: &AppState,
+    session: Option<&Session>,
+    notice: Option<Notice>,
+) -> Response {
+    let page = match session {
+        Some(session) => {
+            let roles: Vec<(&'static str, &'static str)> =
+                [Role::Viewer, Role::Operator, Role::Admin]
+                    .into_iter()
+                    .filter(|role| *role <= session.role)
+                    .map(|role| (role.as_str(), role.as_str()))
+                    .collect();
+            TokensPage {
+                nav: Nav::new(&state.config, Some(session)).with_link("/api/tokens", "🔗 JSON API"),
+                notice,
+                role_options: SelectOption::list(&roles, Some(session.role.as_str())),
+                tokens: visible_tokens(state, session).await,
+            }
+        }
+        None => TokensPage {
+            nav: Nav::new(&state.config, None).with_link("/api/tokens", "🔗 JSON API"),
+            notice: Some(Notice::Error(
+                "API tokens require authentication to be enabled".to_string(),
+            )),
+            role_options: Vec::new(),
+            tokens: Vec::new(),
+        },
+    };
+
+    pages::render(&page)
+}
+
+fn error_notice(message: impl Into<String>) -> Option<Notice> {
+    Some(Notice::Error(message.into()))
+}
+
+async fn tokens_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    render_tokens_page(&state, session.as_deref(), None).await
+}
+
+async fn create_token_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<CreateTokenParams>,
+) -> impl IntoResponse {
+    let role = match params
+        .role
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    {
+        Some(role) => match role.parse::<Role>() {
+            Ok(role) => Some(role),
+            Err(e) => return render_tokens_page(&state, session.as_deref(), error_notice(e)).await,
+        },
+        None => None,
+    };
+    let expires_in_days = match params
+        .expires_in_days
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+    {
+        Some(days) => match days.parse::<u32>() {
+            Ok(days) => Some(days),
+            Err(_) => {
+                return render_tokens_page(
+                    &state,
+                    session.as_deref(),
+                    error_notice("Expiry must be a whole number of days"),
+                )
+                .await
+            }
+        },
+        None => None,
+    };
+
+    let notice = match issue_token(
+        &state,
+        session.as_deref(),
+        &params.name,
+        role,
+        expires_in_days,
+    )
+    .await
+    {
+        Ok((token, secret)) => Some(Notice::TokenIssued {
+            name: token.name,
+            secret,
+        }),
+        Err((_, message)) => error_notice(message),
+    };
+    render_tokens_page(&state, session.as_deref(), notice).await
+}
+
+async fn revoke_token_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<RevokeTokenParams>,
+) -> impl IntoResponse {
+    match revoke_token(&state, session.as_deref(), &params.id).await {
+        Ok(_) => Redirect::to("/tokens").into_response(),
+        Err((_, message)) => {
+            render_tokens_page(&state, session.as_deref(), error_notice(message)).await
+        }
+    }
+}
+
+async fn tokens_json_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    match session.as_deref() {
+        Some(session) => Json(visible_tokens(&state, session).await).into_response(),
+        None => Json(Vec::<ApiToken>::new()).into_response(),
+    }
+}
+
+async fn create_token_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Json(request): Json<CreateTokenRequest>,
+) -> impl IntoResponse {
+    match issue_token(
+        &state,
+        session.as_deref(),
+        &request.name,
+        request.role,
+        request.expires_in_days,
+    )
+    .await
+    {
+        Ok((token, secret)) => (
+            StatusCode::CREATED,
+            Json(IssuedTokenResponse { token, secret }),
+        )
+            .into_response(),
+        Err((status, message)) => (
+            status,
+            Json(ActionResponse {
+                success: false,
+                message,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn revoke_token_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match revoke_token(&state, session.as_deref(), &id).await {
+        Ok(token) => Json(ActionResponse {
+            success: true,
+            message: format!("Token {} revoked", token.name),
+        })
+        .into_response(),
+        Err((status, message)) => (
+            status,
+            Json(ActionResponse {
+                success: false,
+                message,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Sessions the user may see: their own, or everyone's for admins
+async fn visible_sessions(state: &AppState, session: &Session) -> Vec<Session> {
+    let username = (session.role != Role::Admin).then_some(session.username.as_str());
+    state.session_store.list(username).await
+}
+
+async fn render_sessions_page(
+    state: &AppState,
+    session: Option<&Session>,
+    notice: Option<Notice>,
+) -> Response {
+    let config = &state.config;
+    let page = match session {
+        Some(current) => SessionsPage {
+            nav: Nav::new(config, session),
+            notice,
+            sessions: visible_sessions(state, current)
+                .await
+                .into_iter()
+                .map(|session| SessionRow {
+                    current: session.id == current.id,
+                    expires_at: (session.last_accessed
+                        + Duration::from_secs(config.session_timeout_seconds))
+                    .min(
+                        session.created_at
+                            + Duration::from_secs(config.session_max_lifetime_seconds),
+                    ),
+                    session,
+                })
+                .collect(),
+        },
+        None => SessionsPage {
+            nav: Nav::new(config, session),
+            notice: error_notice("Sessions require authentication to be enabled"),
+            sessions: Vec::new(),
+        },
+    };
+
+    pages::render(&page)
+}
+
+async fn sessions_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    render_sessions_page(&state, session.as_deref(), None).await
+}
+
+/// Sign out one of the user's sessions; admins may sign out anyone's
+async fn revoke_session_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<RevokeSessionParams>,
+) -> impl IntoResponse {
+    let Some(current) = session.as_deref() else {
+        return render_sessions_page(&state, None, None).await;
+    };
+
+    let visible = visible_sessions(&state, current)
+        .await
+        .iter()
+        .any(|session| session.id == params.id);
+    let revoked = if visible {
+        state.session_store.revoke(&params.id).await
+    } else {
+        None
+    };
+    let Some(revoked) = revoked else {
+        let notice = error_notice(format!("No such session: {}", params.id));
+        return render_sessions_page(&state, Some(current), notice).await;
+    };
+
+    state
+        .timeline
+        .record_action(
+            Some(&current.username),
+            "session",
+            "revoke",
+            &revoked.username,
+            revoked
+                .address
+                .as_ref()
+                .map(|address| format!("from {}", address)),
+            None,
+        )
+        .await;
+    Redirect::to("/sessions").into_response()
+}
+
+/// The signed-in local account, or why two-factor settings are not available to it
+fn two_factor_account<'a>(
+    state: &AppState,
+    session: Option<&'a Session>,
+) -> Result<&'a Session, String> {
+    match session {
+        None => Err("Two-factor authentication requires authentication to be enabled".to_string()),
+        Some(session) if session.token_id.is_some() => {
+            Err("Two-factor settings cannot be changed with an API token".to_string())
+        }
+        Some(session) if session.method == AuthMethod::Oidc => Err(format!(
+            "You signed in with {}; two-factor authentication is managed there",
+            state
+                .oidc
+                .as_ref()
+                .map_or("single sign-on", |oidc| oidc.provider_name.as_str())
+        )),
+        Some(session) if session.method == AuthMethod::Proxy => Err(
+            "You were signed in by the authenticating proxy; two-factor authentication is managed there"
+                .to_string(),
+        ),
+        Some(session) => Ok(session),
+    }
+}
+
+async fn two_factor_status(state: &AppState, session: &Session) -> TwoFactorStatus {
+    let required = state.config.totp_required_for(session.role);
+
+    if let Some(status) = state.totp.status(&session.username).await {
+        return TwoFactorStatus::On {
+            enabled_at: status.enabled_at,
+            recovery_codes_left: status.recovery_codes_left,
+            required_by: required.then_some(session.role),
+        };
+    }
+    match state.totp.pending_setup(&session.username).await {
+        Some(setup) => {
+            TwoFactorStatus::SettingUp(AuthenticatorSetup::new(&setup, &session.username))
+        }
+        None => TwoFactorStatus::Off { required },
+    }
+}
+
+async fn render_two_factor_page(
+    state: &AppState,
+    session: Option<&Session>,
+    notice: Option<Notice>,
+) -> Response {
+    let (status, notice) = match two_factor_account(state, session) {
+        Ok(account) => (Some(two_factor_status(state, account).await), notice),
+        Err(e) => (None, error_notice(e)),
+    };
+
+    pages::render(&TwoFactorPage {
+        nav: Nav::new(&state.config, session),
+        notice,
+        status,
+    })
+}
+
+/// Check a code entered on the two-factor page. Wrong codes count towards the login
+/// lockout, so a stolen session cannot be used to guess its way to turning two-factor off.
+async fn verify_account_code(state: &AppState, username: &str, code: &str) -> Result<(), String> {
+    if state
+        .login_throttle
+        .locked_for(None, username)
+        .await
+        .is_some()
+    {
+        return Err("Too many incorrect codes; try again later".to_string());
+    }
+    match state.totp.verify(username, code).await {
+        Some(_) => Ok(()),
+        None => {
+            state.login_throttle.record_failure(None, username).await;
+            Err("Incorrect code".to_string())
+        }
+    }
+}
+
+async fn two_factor_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    render_two_factor_page(&state, session.as_deref(), None).await
+}
+
+/// Generate a secret for the user to scan. Users who already have two-factor
+/// authentication must turn it off first, which takes a code.
+async fn two_factor_setup_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let Ok(account) = two_factor_account(&state, session.as_deref()) else {
+        return render_two_factor_page(&state, session.as_deref(), None).await;
+    };
+    if state.totp.is_enrolled(&account.username).await {
+        let notice = error_notice("Two-factor authentication is already on");
+        return render_two_factor_page(&state, Some(account), notice).await;
+    }
+
+    state.totp.start_setup(&account.username).await;
+    Redirect::to("/two-factor").into_response()
+}
+
+async fn two_factor_enable_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<TwoFactorCodeParams>,
+) -> impl IntoResponse {
+    let Ok(account) = two_factor_account(&state, session.as_deref()) else {
+        return render_two_factor_page(&state, session.as_deref(), None).await;
+    };
+
+    let notice = match state
+        .totp
+        .confirm_setup(&account.username, &params.code)
+        .await
+    {
+        Ok(codes) => {
+            state
+                .timeline
+                .record_action(
+                    Some(&account.username),
+                    "two-factor",
+                    "enable",
+                    &account.username,
+                    None,
+                    None,
+                )
+                .await;
+            Some(Notice::RecoveryCodes {
+                message: "✅ Two-factor authentication is on. Save these recovery codes somewhere safe; each one signs you in once if you lose your device. They will not be shown again.",
+                codes,
+            })
+        }
+        Err(e) => error_notice(e),
+    };
+    render_two_factor_page(&state, Some(account), notice).await
+}
+
+async fn two_factor_disable_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<TwoFactorCodeParams>,
+) -> impl IntoResponse {
+    let Ok(account) = two_factor_account(&state, session.as_deref()) else {
+        return render_two_factor_page(&state, session.as_deref(), None).await;
+    };
+    if state.config.totp_required_for(account.role) {
+        let notice = error_notice(format!(
+            "Your role ({}) requires two-factor authentication",
+            account.role
+        ));
+        return render_two_factor_page(&state, Some(account), notice).await;
+    }
+    if let Err(e) = verify_account_code(&state, &account.username, &params.code).await {
+        return render_two_factor_page(&state, Some(account), error_notice(e)).await;
+    }
+
+    match state.totp.disable(&account.username).await {
+        Ok(_) => {
+            state
+                .timeline
+                .record_action(
+                    Some(&account.username),
+                    "two-factor",
+                    "disable",
+                    &account.username,
+                    None,
+                    None,
+                )
+                .await;
+            Redirect::to("/two-factor").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to save two-factor settings: {}", e);
+            let notice = error_notice(format!("Failed to save two-factor settings: {}", e));
+            render_two_factor_page(&state, Some(account), notice).await
+        }
+    }
+}
+
+/// Replace the user's recovery codes, e.g. when they are running out
+async fn two_factor_recovery_codes_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<TwoFactorCodeParams>,
+) -> impl IntoResponse {
+    let Ok(account) = two_factor_account(&state, session.as_deref()) else {
+        return render_two_factor_page(&state, session.as_deref(), None).await;
+    };
+
+    let notice = match verify_account_code(&state, &account.username, &params.code).await {
+        Ok(()) => match state
+            .totp
+            .regenerate_recovery_codes(&account.username)
+            .await
+        {
+            Ok(codes) => {
+                state
+                    .timeline
+                    .record_action(
+                        Some(&account.username),
+                        "two-factor",
+                        "recovery-codes",
+                        &account.username,
+                        None,
+                        None,
+                    )
+                    .await;
+                Some(Notice::RecoveryCodes {
+                    message: "✅ New recovery codes issued; the old ones no longer work. Save these somewhere safe; they will not be shown again.",
+                    codes,
+                })
+            }
+            Err(e) => {
+                tracing::error!("Failed to issue recovery codes: {}", e);
+                error_notice(format!("Failed to issue recovery codes: {}", e))
+            }
+        },
+        Err(e) => error_notice(e),
+    };
+    render_two_factor_page(&state, Some(account), notice).await
+}
+
+/// The login page with an optional error, offering single sign-on when it is configured
+fn login_page(state: &AppState, error: Option<LoginError>) -> Response {
+    pages::render(&LoginPage {
+        sso_provider: state.oidc.as_ref().map(|oidc| oidc.provider_name.clone()),
+        error,
+    })
+}
+
+/// Attach a new session's cookie
+fn with_session_cookie(state: &AppState, session_id: &str, mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert("Set-Cookie", session_cookie(session_id, &state.config));
+    response
+}
+
+/// The address of the client signing in, looking through trusted proxies
+fn request_client(
+    state: &AppState,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: &HeaderMap,
+) -> Option<IpAddr> {
+    login_throttle::client_ip(
+        connect_info.map(|ConnectInfo(addr)| addr.ip()),
+        headers,
+        &state.config.trusted_proxies,
+    )
+}
+
+fn user_agent(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+}
+
+async fn login_handler_wrapper(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    // If auth is disabled, redirect to main page
+    if !state.config.auth_enabled {
+        return Redirect::to("/").into_response();
+    }
+
+    login_page(&state, None)
+}
+
+#[axum::debug_handler]
+async fn login_post_handler_wrapper(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> impl IntoResponse {
+    // If auth is disabled, redirect to main page
+    if !state.config.auth_enabled {
+        return Redirect::to("/").into_response();
+    }
+
+    let client = request_client(&state, connect_info, &headers);
+    let client_name = client.map_or_else(|| "unknown address".to_string(), |ip| ip.to_string());
+
+    // Refuse locked-out clients before spending any effort on the password
+    if let Some(remaining) = state
+        .login_throttle
+        .locked_for(client, &form.username)
+        .await
+    {
+        tracing::warn!(
+            "Refused login attempt for user {} from {}: locked out",
+            form.username,
+            client_name
+        );
+        return login_lockout_response(&state, remaining);
+    }
+
+    // Verify credentials
+    match state.config.authenticate(&form.username, &form.password) {
+        Some(role) => {
+            // Users with two-factor authentication (or who must set it up) enter a code
+            // before they get a session
+            let enrolled = state.totp.is_enrolled(&form.username).await;
+            if enrolled || state.config.totp_required_for(role) {
+                let (challenge_id, challenge) = state
+                    .totp
+                    .begin_challenge(&form.username, role, !enrolled)
+                    .await;
+                return two_factor_login_page(&challenge_id, &challenge, None);
+            }
+
+            state.login_throttle.record_success(&form.username).await;
+            let session_id =
+                start_local_session(&state, &form.username, role, client, &headers).await;
+
+            // Set session cookie and redirect
+            with_session_cookie(&state, &session_id, Redirect::to("/").into_response())
+        }
+        None => {
+            tracing::warn!(
+                "Failed login attempt for user {} from {}",
+                form.username,
+                client_name
+            );
+            if let Some(lockout) = state
+                .login_throttle
+                .record_failure(client, &form.username)
+                .await
+            {
+                return login_lockout_response(&state, lockout);
+            }
+
+            login_page(&state, login_error("Invalid username or password"))
+        }
+    }
+}
+
+/// The login page explaining how long to wait, with a `Retry-After` header for scripts
+fn login_lockout_response(state: &AppState, remaining: Duration) -> Response {
+    let seconds = remaining.as_secs_f64().ceil() as u64;
+    let wait = if seconds < 120 {
+        format!("{} seconds", seconds)
+    } else {
+        format!("{} minutes", seconds.div_ceil(60))
+    };
+
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [("Retry-After", seconds.to_string())],
+        login_page(state, Some(LoginError::LockedOut(wait))),
+    )
+        .into_response()
+}
+
+/// Start a session for a local account once its password, and code if needed, are accepted
+async fn start_local_session(
+    state: &AppState,
+    username: &str,
+    role: Role,
+    client: Option<IpAddr>,
+    headers: &HeaderMap,
+) -> String {
+    state
+        .session_store
+        .create_session_for(
+            username,
+            role,
+            AuthMethod::Password,
+            client.map(|ip| ip.to_string()),
+            user_agent(headers),
+        )
+        .await
+}
+
+/// The second login step, asking for a code. Users who still have to set up two-factor
+/// authentication are shown the QR code to scan first.
+fn two_factor_login_page(
+    challenge_id: &str,
+    challenge: &LoginChallenge,
+    error: Option<String>,
+) -> Response {
+    pages::render(&TwoFactorLoginPage {
+        step: TwoFactorLoginStep::Code {
+            challenge_id: challenge_id.to_string(),
+            username: challenge.username.clone(),
+            setup: challenge
+                .setup
+                .as_ref()
+                .map(|setup| AuthenticatorSetup::new(setup, &challenge.username)),
+        },
+        error,
+    })
+}
+
+/// Check the code of a user whose password was accepted, and sign them in
+async fn login_two_factor_handler(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Form(form): Form<TwoFactorLoginForm>,
+) -> impl IntoResponse {
+    if !state.config.auth_enabled {
+        return Redirect::to("/").into_response();
+    }
+
+    let Some(challenge) = state.totp.take_challenge(&form.challenge).await else {
+        return login_page(
+            &state,
+            login_error("Your sign-in has expired; enter your password again"),
+        );
+    };
+    let client = request_client(&state, connect_info, &headers);
+    let client_name = client.map_or_else(|| "unknown address".to_string(), |ip| ip.to_string());
+    if let Some(remaining) = state
+        .login_throttle
+        .locked_for(client, &challenge.username)
+        .await
+    {
+        return login_lockout_response(&state, remaining);
+    }
+
+    let result = match &challenge.setup {
+        Some(setup) => state
+            .totp
+            .enroll(&challenge.username, setup, &form.code)
+            .await
+            .map(Some),
+        None => match state.totp.verify(&challenge.username, &form.code).await {
+            Some(Verified::Code) => Ok(None),
+            Some(Verified::RecoveryCode) => {
+                state
+                    .timeline
+                    .record_action(
+                        Some(&challenge.username),
+                        "two-factor",
+                        "recovery-code",
+                        &challenge.username,
+                        Some(format!("signed in from {}", client_name)),
+                        None,
+                    )
+                    .await;
+                Ok(None)
+            }
+            None => Err("Incorrect code".to_string()),
+        },
+    };
+
+    let recovery_codes = match result {
+        Ok(codes) => codes,
+        Err(e) => {
+            tracing::warn!(
+                "Failed two-factor code for user {} from {}",
+                challenge.username,
+                client_name
+            );
+            if let Some(lockout) = state
+                .login_throttle
+                .record_failure(client, &challenge.username)
+                .await
+            {
+                return login_lockout_response(&state, lockout);
+            }
+            if !state
+                .totp
+                .retry_challenge(&form.challenge, challenge.clone())
+                .await
+            {
+                return login_page(
+                    &state,
+                    login_error("Too many incorrect codes; enter your password again"),
+                );
+            }
+            return two_factor_login_page(&form.challenge, &challenge, Some(e));
+        }
+    };
+
+    state
+        .login_throttle
+        .record_success(&challenge.username)
+        .await;
+    let session_id = start_local_session(
+        &state,
+        &challenge.username,
+        challenge.role,
+        client,
+        &headers,
+    )
+    .await;
+
+    let Some(codes) = recovery_codes else {
+        return with_session_cookie(&state, &session_id, Redirect::to("/").into_response());
+    };
+
+    // Signing in also finished setting up two-factor authentication; show the recovery
+    // codes once before continuing
+    state
+        .timeline
+        .record_action(
+            Some(&challenge.username),
+            "two-factor",
+            "enable",
+            &challenge.username,
+            None,
+            None,
+        )
+        .await;
+    let page = pages::render(&TwoFactorLoginPage {
+        step: TwoFactorLoginStep::RecoveryCodes(codes),
+        error: None,
+    });
+    with_session_cookie(&state, &session_id, page)
+}
+
+/// Send the browser to the identity provider to sign in
+async fn oidc_login_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let Some(oidc) = state.oidc.as_ref().filter(|_| state.config.auth_enabled) else {
+        return Redirect::to("/login").into_response();
+    };
+
+    match oidc.authorization_url().await {
+        Ok((url, login_state)) => {
+            let mut response = Redirect::to(&url).into_response();
+            response.headers_mut().insert(
+                "Set-Cookie",
+                oidc::state_cookie(&login_state, &state.config),
+            );
+            response
+        }
+        Err(e) => {
+            tracing::warn!("Cannot start single sign-on: {}", e);
+            login_page(&state, login_error(e))
+        }
+    }
+}
+
+/// Where the identity provider sends the browser back to after signing in
+async fn oidc_callback_handler(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Query(params): Query<OidcCallbackParams>,
+) -> impl IntoResponse {
+    let Some(oidc) = state.oidc.as_ref().filter(|_| state.config.auth_enabled) else {
+        return Redirect::to("/login").into_response();
+    };
+
+    let identity = match (params.error, params.code, params.state) {
+        (Some(error), _, _) => Err(format!(
+            "The identity provider refused the sign-in: {}",
+            params.error_description.unwrap_or(error)
+        )),
+        (None, Some(_), Some(login_state))
+            if !oidc::state_cookie_matches(&headers, &login_state) =>
+        {
+            Err("This sign-in was started in another browser; sign in again".to_string())
+        }
+        (None, Some(code), Some(login_state)) => oidc.complete(&code, &login_state).await,
+        _ => Err("The identity provider did not send an authorization code".to_string()),
+    };
+    // A provider account must not take over a local account of the same name
+    let identity = identity.and_then(|identity| {
+        if state.config.role_of(&identity.username).is_some() {
+            Err(format!(
+                "{} is a local account; sign in with its password instead",
+                identity.username
+            ))
+        } else {
+            Ok(identity)
+        }
+    });
+
+    let identity = match identity {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::warn!("Single sign-on failed: {}", e);
+            return with_cleared_state_cookie(&state, login_page(&state, login_error(e)));
+        }
+    };
+
+    let client = request_client(&state, connect_info, &headers);
+    let session_id = state
+        .session_store
+        .create_session_for(
+            &identity.username,
+            identity.role,
+            AuthMethod::Oidc,
+            client.map(|ip| ip.to_string()),
+            user_agent(&headers),
+        )
+        .await;
+    tracing::info!(
+        "{} signed in through {} as {}",
+        identity.username,
+        oidc.provider_name,
+        identity.role
+    );
+
+    // Not a redirect; see SignedInPage
+    let page = with_session_cookie(&state, &session_id, pages::render(&SignedInPage));
+    with_cleared_state_cookie(&state, page)
+}
+
+fn with_cleared_state_cookie(state: &AppState, mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .append("Set-Cookie", oidc::clear_state_cookie(&state.config));
+    response
+}
+
+fn login_error(message: impl Into<String>) -> Option<LoginError> {
+    Some(LoginError::Message(message.into()))
+}
+
+async fn logout_handler_wrapper(
+    State(state): State<Arc<AppState>>,
+    request: axum::extract::Request,
+) -> impl IntoResponse {
+    // Extract session ID from cookie and remove session. Logging out skips the auth and
+    // CSRF middleware, so the token is checked here; otherwise any site could sign users out.
+    let session_id = request
+        .headers()
+        .get("cookie")
+        .and_then(|cookie_header| cookie_header.to_str().ok())
+        .and_then(extract_session_id);
+    let mut signed_in_by_proxy = false;
+    if let Some(session_id) = session_id {
+        if let Some(session) = state.session_store.get_session(&session_id).await {
+            if let Err(response) = verify_csrf(request, &session).await {
+                return response;
+            }
+            signed_in_by_proxy = session.method == AuthMethod::Proxy;
+        }
+        state.session_store.remove_session(&session_id).await;
+    }
+
+    // Clear cookie and redirect to login, or to the proxy's sign-out page so the proxy does
+    // not sign the user straight back in
+    let logout_url = match &state.config.proxy_auth_logout_url {
+        Some(url) if signed_in_by_proxy => url.as_str(),
+        _ => "/login",
+    };
+    let mut response = Redirect::to(logout_url).into_response();
+    response
+        .headers_mut()
+        .insert("Set-Cookie", clear_session_cookie(&state.config));
+    response
+}
+
+fn extract_session_id(cookie_str: &str) -> Option<String> {
+    for cookie in cookie_str.split(';') {
+        let cookie = cookie.trim();
+        if let Some(value) = cookie.strip_prefix("session_id=") {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+async fn start_image_enhanced_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<EnhancedStartImageParams>,
+) -> impl IntoResponse {
+    // Parse environment variables from JSON string
+    let environment_variables = if let Some(env_str) = &params.environment_variables {
+        if env_str.trim().is_empty() {
+            Vec::new()
+        } else {
+            match serde_json::from_str::<Vec<EnvironmentVariable>>(env_str) {
+                Ok(vars) => vars,
+                Err(e) => {
+                    tracing::error!("Failed to parse environment variables: {}", e);
+                    return pages::error_page(
+                        format!("Error parsing environment variables: {}", e),
+                        "/",
+                    );
+                }
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Parse port mappings from JSON string
+    let port_mappings = if let Some(ports_str) = &params.port_mappings {
+        if ports_str.trim().is_empty() {
+            Vec::new()
+        } else {
+            match serde_json::from_str::<Vec<ContainerPortMapping>>(ports_str) {
+                Ok(ports) => ports,
+                Err(e) => {
+                    tracing::error!("Failed to parse port mappings: {}", e);
+                    return pages::error_page(format!("Error parsing port mappings: {}", e), "/");
+                }
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Parse mounts from JSON string
+    let mounts = if let Some(mounts_str) = &params.mounts {
+        if mounts_str.trim().is_empty() {
+            Vec::new()
+        } else {
+            match serde_json::from_str::<Vec<ContainerMount>>(mounts_str) {
+                Ok(mounts) => mounts,
+                Err(e) => {
+                    tracing::error!("Failed to parse mounts: {}", e);
+                    return pages::error_page(format!("Error parsing mounts: {}", e), "/");
+                }
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    let target = params
+        .container_name
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| params.image_name.clone());
+    let request = CreateContainerRequest {
+        image_name: params.image_name.clone(),
+        container_name: params.container_name.filter(|s| !s.trim().is_empty()),
+        environment_variables,
+        port_mappings,
+        restart_policy: params.restart_policy.filter(|s| !s.trim().is_empty()),
+        mounts,
+        network: params.network.filter(|s| !s.trim().is_empty()),
+        network_aliases: split_aliases(params.network_aliases.as_deref()),
+    };
+
+    let result = state
+        .backend
+        .create_and_start_container_enhanced(request)
+        .await;
+    audit(
+        &state,
+        &session,
+        "container",
+        "create",
+        &target,
+        Some(format!("image {}", params.image_name)),
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(container_id) => {
+            tracing::info!(
+                "Successfully created and started container {} from image {}",
+                container_id,
+                params.image_name
+            );
+            Redirect::to("/").into_response()
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to start container from image {}: {}",
+                params.image_name,
+                e
+            );
+            pages::error_page(
+                format!(
+                    "Error starting container from image {}: {}",
+                    params.image_name,
+                    docker::error_message(&e)
+                ),
+                "/",
+            )
+        }
+    }
+}
+
+async fn remove_image_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<RemoveImageParams>,
+) -> impl IntoResponse {
+    let options = ImageRemovalOptions {
+        force: params.force,
+        no_prune: params.no_prune,
+    };
+
+    let result = state.backend.remove_image(&params.image, &options).await;
+    audit(
+        &state,
+        &session,
+        "image",
+        "remove",
+        &params.image,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(result) => {
+            tracing::info!(
+                "Removed image {} ({} untagged, {} deleted)",
+                params.image,
+                result.untagged.len(),
+                result.deleted.len()
+            );
+            Redirect::to("/").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to remove image {}: {}", params.image, e);
+            pages::error_page(
+                format!(
+                    "Error removing image {}: {}",
+                    params.image,
+                    docker::error_message(&e)
+                ),
+                "/",
+            )
+        }
+    }
+}
+
+async fn untag_image_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Form(params): Form<UntagImageParams>,
+) -> impl IntoResponse {
+    let result = state.backend.untag_image(&params.tag).await;
+    audit(
+        &state,
+        &session,
+        "image",
+        "untag",
+        &params.tag,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("Removed image tag {}", params.tag);
+            Redirect::to("/").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to untag image {}: {}", params.tag, e);
+            pages::error_page(
+                format!(
+                    "Error removing tag {}: {}",
+                    params.tag,
+                    docker::error_message(&e)
+                ),
+                "/",
+            )
+        }
+    }
+}
+
+async fn prune_images_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let result = state.backend.prune_dangling_images().await;
+    audit(
+        &state, &session, "image", "prune", "dangling", None, &result,
+    )
+    .await;
+
+    match result {
+        Ok(result) => {
+            tracing::info!(
+                "Pruned {} dangling images, reclaimed {} bytes",
+                result.deleted.len(),
+                result.space_reclaimed_bytes
+            );
+            Redirect::to("/").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to prune dangling images: {}", e);
+            pages::error_page(
+                format!(
+                    "Error pruning dangling images: {}",
+                    docker::error_message(&e)
+                ),
+                "/",
+            )
+        }
+    }
+}
+
+async fn remove_image_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+    Path(image_name): Path<String>,
+    Query(options): Query<ImageRemovalOptions>,
+) -> impl IntoResponse {
+    let result = state.backend.remove_image(&image_name, &options).await;
+    audit(
+        &state,
+        &session,
+        "image",
+        "remove",
+        &image_name,
+        None,
+        &result,
+    )
+    .await;
+
+    match result {
+        Ok(result) => {
+            tracing::info!("Removed image {} via API", image_name);
+            Json(result).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to remove image {}: {}", image_name, e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error removing image: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn prune_preview_api_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.backend.preview_dangling_image_prune().await {
+        Ok(preview) => Json(preview).into_response(),
+        Err(e) => (
+            docker_error_status(&e),
+            format!("Error listing dangling images: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+async fn prune_images_api_handler(
+    State(state): State<Arc<AppState>>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let result = state.backend.prune_dangling_images().await;
+    audit(
+        &state, &session, "image", "prune", "dangling", None, &result,
+    )
+    .await;
+
+    match result {
+        Ok(result) => {
+            tracing::info!(
+                "Pruned {} dangling images via API, reclaimed {} bytes",
+                result.deleted.len(),
+                result.space_reclaimed_bytes
+            );
+            Json(result).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to prune dangling images: {}", e);
+            (
+                docker_error_status(&e),
+                Json(ActionResponse {
+                    success: false,
+                    message: format!("Error pruning images: {}", docker::error_message(&e)),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn image_info_handler(
+    State(state): State<Arc<AppState>>,
+    Path(image_name): Path<String>,
+) -> impl IntoResponse {
+    // URL decode the image name (in case it contains special characters like :)
+    let decoded_image_name = urlencoding::decode(&image_name)
+        .map_err(|e| format!("Invalid image name encoding: {}", e))
+        .unwrap_or_else(|_| std::borrow::Cow::Borrowed(&image_name));
+
+    match state.backend.get_image_info(&decoded_image_name).await {
+        Ok(mut image_info) => {
+            image_info.environment_variables = state
+                .secrets
+                .mask_variables(image_info.environment_variables);
+            Json(image_info).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to get image info for {}: {}", decoded_image_name, e);
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error getting image info: {}", e),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn app_router(
+    config: &Config,
+    backend: Arc<dyn DockerBackend>,
+    stats: Arc<StatsCollector>,
+    events: Arc<EventHub>,
+    timeline: Arc<Timeline>,
+    tokens: Arc<TokenStore>,
+    sessions: Arc<SessionStore>,
+    totp: Arc<TotpStore>,
+) -> Router {
+    let state = Arc::new(AppState {
+        config: config.clone(),
+        session_store: sessions,
+        backend,
+        stats,
+        events,
+        timeline,
+        tokens,
+        totp,
+        login_throttle: LoginThrottle::from_config(config),
+        oidc: OidcClient::from_config(config),
+        secrets: SecretMasker::from_config(config),
+    });
+    let auth_state = AuthState {
+        sessions: state.session_store.clone(),
+        tokens: state.tokens.clone(),
+    };
+
+    // Read-only pages and APIs, available to every signed-in user
+    let viewer_routes = Router::new()
+        .route("/", get(index_handler))
+        .route("/api/config", get(config_handler))
+        .route("/api/containers", get(containers_json_handler))
+        .route(
+            "/api/containers/:id/environment",
+            get(container_environment_handler),
+        )
+        .route("/api/image/:image_name", get(image_info_handler))
+        .route("/api/images/prune", get(prune_preview_api_handler))
+        .route("/api/networks", get(networks_json_handler))
+        .route("/api/networks/:id", get(network_info_handler))
+        .route("/api/volumes", get(volumes_json_handler))
+        .route("/api/volumes/:name", get(volume_info_handler))
+        .route("/metrics", get(metrics_dashboard_handler))
+        .route("/events", get(events_page_handler))
+        .route("/api/events", get(events_json_handler))
+        .route("/api/events/stream", get(events_stream_handler))
+        .route("/api/metrics", get(metrics_json_handler))
+        .route("/api/metrics/history", get(metrics_history_handler))
+        .route("/api/prometheus", get(prometheus_handler))
+        .route("/logs/:id", get(logs_handler))
+        .route("/logs/:id/ws", get(logs_ws_handler))
+        .route("/networks", get(networks_handler))
+        .route("/volumes", get(volumes_handler))
+        // Every user manages their own API tokens; the handlers cap each token's role
+        .route("/tokens", get(tokens_handler))
+        .route("/tokens/create", post(create_token_handler))
+        .route("/tokens/revoke", post(revoke_token_handler))
+        // ...and their own sessions; admins can see and revoke everyone's
+        .route("/sessions", get(sessions_handler))
+        .route("/sessions/revoke", post(revoke_session_handler))
+        // ...and their own two-factor authentication
+        .route("/two-factor", get(two_factor_handler))
+        .route("/two-factor/setup", post(two_factor_setup_handler))
+        .route("/two-factor/enable", post(two_factor_enable_handler))
+        .route("/two-factor/disable", post(two_factor_disable_handler))
+        .route(
+            "/two-factor/recovery-codes",
+            post(two_factor_recovery_codes_handler),
+        )
+        .route("/api/tokens", get(tokens_json_handler))
+        .route("/api/tokens", post(create_token_api_handler))
+        .route("/api/tokens/:id", delete(revoke_token_api_handler));
+
+    // Lifecycle actions on existing containers
+    let operator_routes = Router::new()
+        .route("/start/:id", post(start_container_handler))
+        .route("/stop/:id", post(stop_container_handler))
+        .route("/restart/:id", post(restart_container_handler))
+        .route_layer(middleware::from_fn_with_state(
+            Role::Operator,
+            crate::auth::require_role,
+        ));
+
+    // Creating and removing resources, and shells inside containers
+    let admin_routes = Router::new()
+        .route("/api/containers/:id", delete(remove_container_api_handler))
+        .route(
+            "/api/containers/:id/environment/reveal",
+            post(reveal_environment_api_handler),
+        )
+        .route("/api/images/prune", post(prune_images_api_handler))
+        .route("/api/images/:image_name", delete(remove_image_api_handler))
+        .route("/api/networks", post(create_network_api_handler))
+        .route("/api/networks/:id", delete(remove_network_api_handler))
+        .route(
+            "/api/networks/:id/connect",
+            post(connect_network_api_handler),
+        )
+        .route(
+            "/api/networks/:id/disconnect",
+            post(disconnect_network_api_handler),
+        )
+        .route("/api/volumes", post(create_volume_api_handler))
+        .route("/api/volumes/prune", post(prune_volumes_api_handler))
+        .route("/api/volumes/:name", delete(remove_volume_api_handler))
+        .route("/start-image", post(start_image_handler))
+        .route("/start-image-enhanced", post(start_image_enhanced_handler))
+        .route("/remove/:id", post(remove_container_handler))
+        .route("/images/pull/ws", get(pull_image_ws_handler))
+        .route("/images/remove", post(remove_image_handler))
+        .route("/images/untag", post(untag_image_handler))
+        .route("/images/prune", post(prune_images_handler))
+        .route("/networks/create", post(create_network_handler))
+        .route("/networks/remove", post(remove_network_handler))
+        .route("/networks/connect", post(connect_network_handler))
+        .route("/networks/disconnect", post(disconnect_network_handler))
+        .route("/volumes/create", post(create_volume_handler))
+        .route("/volumes/remove", post(remove_volume_handler))
+        .route("/volumes/prune", post(prune_volumes_handler))
+        .route("/exec/:id", get(exec_handler))
+        .route("/exec/:id/ws", get(exec_ws_handler))
+        .route_layer(middleware::from_fn_with_state(
+            Role::Admin,
+            crate::auth::require_role,
+        ));
+
+    Router::new()
+        .route("/health", get(health_handler))
+        .route("/ready", get(readiness_handler))
+        .route("/login", get(login_handler_wrapper))
+        .route("/login", post(login_post_handler_wrapper))
+        .route("/login/two-factor", post(login_two_factor_handler))
+        .route("/logout", post(logout_handler_wrapper))
+        .route("/auth/oidc/login", get(oidc_login_handler))
+        .route("/auth/oidc/callback", get(oidc_callback_handler))
+        .merge(viewer_routes)
+        .merge(operator_routes)
+        .merge(admin_routes)
+        .nest_service("/static", ServeDir::new("static"))
+        // Layers run outermost-last: authentication attaches the session the CSRF check needs
+        .layer(middleware::from_fn(crate::auth::csrf_middleware))
+        .layer(middleware::from_fn_with_state(
+            auth_state,
+            crate::auth::auth_middleware,
+        ))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::models::{ContainerEnvironmentVariable, ImageInfo, TimelineEntry};
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use tower::ServiceExt;
+
+    fn test_router(backend: Arc<FakeBackend>) -> Router {
+        let config = Config {
+            auth_enabled: false,
+            ..Config::default()
+        };
+        let stats = Arc::new(StatsCollector::new(config.metrics_history_limit));
+        app_router(
+            &config,
+            backend,
+            stats,
+            Arc::new(EventHub::new(16)),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        )
+    }
+
+    async fn send(router: Router, method: Method, uri: &str, body: Body) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_index_lists_containers_and_images() {
+        let backend = Arc::new(
+            FakeBackend::new()
+                .with_container("web", "nginx:latest", "running")
+                .with_container("db", "postgres:16", "exited")
+                .with_image("nginx:latest"),
+        );
+
+        let (status, body) = send(test_router(backend), Method::GET, "/", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("web"));
+        assert!(body.contains("postgres:16"));
+        assert!(body.contains("nginx:latest"));
+    }
+
+    #[tokio::test]
+    async fn test_dangling_images_are_listed_and_removable() {
+        let backend = Arc::new(
+            FakeBackend::new()
+                .with_image("nginx:latest")
+                .with_dangling_image(),
+        );
+
+        let (status, body) = send(
+            test_router(backend.clone()),
+            Method::GET,
+            "/",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<code>000000000002</code>"));
+        assert!(body.contains("&lt;none&gt;"));
+        assert!(body.contains(r#"data-image-id="sha256:000000000002""#));
+
+        let (status, _) = send(
+            test_router(backend.clone()),
+            Method::DELETE,
+            "/api/images/sha256:000000000002",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(test_router(backend), Method::GET, "/", Body::empty()).await;
+        assert!(!body.contains("000000000002"));
+        assert!(body.contains("nginx:latest"));
+    }
+
+    #[tokio::test]
+    async fn test_start_image_form_shows_daemon_error() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/start-image-enhanced")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from("image_name=nginx&container_name=web"))
+            .unwrap();
+        let response = test_router(backend).oneshot(request).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&bytes);
+        assert!(body.contains("Error starting container from image nginx: Conflict."));
+        assert!(!body.contains("Docker responded with status code"));
+    }
+
+    #[tokio::test]
+    async fn test_stop_and_start_container() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+
+        let (status, _) = send(
+            test_router(backend.clone()),
+            Method::POST,
+            "/stop/web",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        assert_eq!(backend.container_state("web").as_deref(), Some("exited"));
+
+        send(
+            test_router(backend.clone()),
+            Method::POST,
+            "/start/web",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(backend.container_state("web").as_deref(), Some("running"));
+    }
+
+    /// A browser session: the session cookie and the CSRF token its pages carry
+    struct SignedIn {
+        cookie: String,
+        csrf_token: String,
+    }
+
+    impl SignedIn {
+        fn headers(&self) -> [(&str, &str); 2] {
+            [
+                ("cookie", self.cookie.as_str()),
+                (crate::auth::CSRF_HEADER, self.csrf_token.as_str()),
+            ]
+        }
+    }
+
+    /// Sign in through the login form, then read the CSRF token from a page
+    async fn login(router: Router, username: &str, password: &str) -> SignedIn {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/login")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "username={}&password={}",
+                username, password
+            )))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let cookie = response
+            .headers()
+            .get("set-cookie")
+            .expect("login should set a session cookie")
+            .to_str()
+            .unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+
+        let (_, page) = send_with(
+            router,
+            Method::GET,
+            "/tokens",
+            &[("cookie", &cookie)],
+            Body::empty(),
+        )
+        .await;
+        let csrf_token = page
+            .split(r#"<meta name="csrf-token" content=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .expect("pages should carry the CSRF token")
+            .to_string();
+        SignedIn { cookie, csrf_token }
+    }
+
+    async fn send_with(
+        router: Router,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: Body,
+    ) -> (StatusCode, String) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = router.oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    async fn send_as(router: Router, method: Method, uri: &str, user: &SignedIn) -> StatusCode {
+        send_with(router, method, uri, &user.headers(), Body::empty())
+            .await
+            .0
+    }
+
+    /// Auth enabled with a viewer (alice) and an operator (bob)
+    fn multi_user_router(backend: Arc<FakeBackend>) -> Router {
+        let users = format!(
+            "alice:viewer:{}\nbob:operator:{}\n",
+            Config::hash_password("alice-pw").unwrap(),
+            Config::hash_password("bob-pw").unwrap()
+        );
+        let config = Config {
+            users: crate::auth::parse_users(&users).unwrap(),
+            ..Config::default()
+        };
+        app_router(
+            &config,
+            backend.clone(),
+            Arc::new(StatsCollector::new(1)),
+            Arc::new(EventHub::new(16)),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_routes_enforce_roles() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+        let router = multi_user_router(backend.clone());
+
+        let viewer = login(router.clone(), "alice", "alice-pw").await;
+        let status = send_as(router.clone(), Method::GET, "/api/containers", &viewer).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = send_as(router.clone(), Method::GET, "/api/images/prune", &viewer).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = send_as(router.clone(), Method::POST, "/stop/web", &viewer).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(backend.container_state("web").as_deref(), Some("running"));
+
+        let operator = login(router.clone(), "bob", "bob-pw").await;
+        let status = send_as(router.clone(), Method::POST, "/stop/web", &operator).await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        assert_eq!(backend.container_state("web").as_deref(), Some("exited"));
+        let status = send_as(
+            router.clone(),
+            Method::DELETE,
+            "/api/containers/web",
+            &operator,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = send_as(router, Method::POST, "/api/images/prune", &operator).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_api_tokens_authenticate_with_capped_role() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+        let router = multi_user_router(backend.clone());
+        let operator = login(router.clone(), "bob", "bob-pw").await;
+
+        // A token cannot outrank its owner
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/api/tokens",
+            &operator.headers(),
+            Body::from(r#"{"name": "ci", "role": "admin"}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = send_with(
+            router.clone(),
+            Method::POST,
+            "/api/tokens",
+            &operator.headers(),
+            Body::from(r#"{"name": "ci", "expires_in_days": 30}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let issued: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let bearer = format!("Bearer {}", issued["secret"].as_str().unwrap());
+        let id = issued["token"]["id"].as_str().unwrap().to_string();
+
+        let auth = &[("authorization", bearer.as_str())];
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/stop/web",
+            auth,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        assert_eq!(backend.container_state("web").as_deref(), Some("exited"));
+        let (status, _) = send_with(
+            router.clone(),
+            Method::DELETE,
+            "/api/containers/web",
+            auth,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/api/tokens",
+            auth,
+            Body::from(r#"{"name": "another"}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send_with(
+            router.clone(),
+            Method::GET,
+            "/api/containers",
+            &[("authorization", "Bearer sdm_wrong")],
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let status = send_as(
+            router.clone(),
+            Method::DELETE,
+            &format!("/api/tokens/{}", id),
+            &operator,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) =
+            send_with(router, Method::GET, "/api/containers", auth, Body::empty()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_users_revoke_only_their_own_sessions() {
+        let router = multi_user_router(Arc::new(FakeBackend::new()));
+        let viewer = login(router.clone(), "alice", "alice-pw").await;
+        let operator = login(router.clone(), "bob", "bob-pw").await;
+
+        let session_id = |page: &str| {
+            page.split(r#"<input type="hidden" name="id" value=""#)
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .expect("the sessions page should list the session")
+                .to_string()
+        };
+        let (status, page) = send_with(
+            router.clone(),
+            Method::GET,
+            "/sessions",
+            &operator.headers(),
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("This session"));
+        assert!(!page.contains("alice"));
+        let operator_session = session_id(&page);
+
+        let revoke = |user: &SignedIn, id: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/sessions/revoke")
+                .header("cookie", &user.cookie)
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "csrf_token={}&id={}",
+                    user.csrf_token, id
+                )))
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(revoke(&viewer, &operator_session))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let status = send_as(router.clone(), Method::GET, "/sessions", &operator).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let response = router
+            .clone()
+            .oneshot(revoke(&operator, &operator_session))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let status = send_as(router.clone(), Method::GET, "/sessions", &operator).await;
+        assert_eq!(status, StatusCode::SEE_OTHER); // Back to the login page
+        let status = send_as(router, Method::GET, "/sessions", &viewer).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    /// A local identity provider that signs carol in as a member of `docker-operators`
+    #[derive(Clone)]
+    struct MockIdp {
+        issuer: String,
+        expected: Arc<std::sync::Mutex<Option<(String, String)>>>, // Code challenge and nonce
+    }
+
+    async fn spawn_mock_idp() -> MockIdp {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        use sha2::{Digest, Sha256};
+        use std::collections::HashMap;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let idp = MockIdp {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            expected: Arc::new(std::sync::Mutex::new(None)),
+        };
+
+        let issuer = idp.issuer.clone();
+        let discovery = move || async move {
+            Json(serde_json::json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+            }))
+        };
+        let token_idp = idp.clone();
+        let token = move |Form(form): Form<HashMap<String, String>>| async move {
+            let (challenge, nonce) = token_idp.expected.lock().unwrap().clone().unwrap();
+            let verifier_hash =
+                URL_SAFE_NO_PAD.encode(Sha256::digest(form["code_verifier"].as_bytes()));
+            if form["code"] != "good-code" || verifier_hash != challenge {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": "invalid_grant"})),
+                );
+            }
+            let claims = serde_json::json!({
+                "iss": token_idp.issuer,
+                "aud": "sdm",
+                "exp": Utc::now().timestamp() + 300,
+                "nonce": nonce,
+                "preferred_username": "carol",
+                "groups": ["docker-operators"],
+            });
+            let id_token = format!(
+                "eyJhbGciOiJSUzI1NiJ9.{}.signature",
+                URL_SAFE_NO_PAD.encode(claims.to_string())
+            );
+            (
+                StatusCode::OK,
+                Json(
+                    serde_json::json!({"access_token": "at", "token_type": "Bearer", "id_token": id_token}),
+                ),
+            )
+        };
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/token", post(token));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        idp
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login_with_pkce() {
+        let idp = spawn_mock_idp().await;
+        let config = Config {
+            oidc_issuer: Some(idp.issuer.clone()),
+            oidc_client_id: Some("sdm".to_string()),
+            oidc_redirect_url: Some("http://sdm.test/auth/oidc/callback".to_string()),
+            oidc_operator_groups: vec!["docker-operators".to_string()],
+            ..Config::default()
+        };
+        let router = app_router(
+            &config,
+            Arc::new(FakeBackend::new()),
+            Arc::new(StatsCollector::new(1)),
+            Arc::new(EventHub::new(16)),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        );
+        let get_request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let (_, page) = send_with(router.clone(), Method::GET, "/login", &[], Body::empty()).await;
+        assert!(page.contains(r#"href="/auth/oidc/login""#));
+
+        let response = router
+            .clone()
+            .oneshot(get_request("/auth/oidc/login"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let location =
+            reqwest::Url::parse(response.headers()["location"].to_str().unwrap()).unwrap();
+        assert!(location
+            .as_str()
+            .starts_with(&format!("{}/authorize?", idp.issuer)));
+        let params: std::collections::HashMap<String, String> =
+            location.query_pairs().into_owned().collect();
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["redirect_uri"], "http://sdm.test/auth/oidc/callback");
+        *idp.expected.lock().unwrap() =
+            Some((params["code_challenge"].clone(), params["nonce"].clone()));
+        let state_cookie = response.headers()["set-cookie"].to_str().unwrap();
+        assert!(state_cookie.contains("SameSite=Lax"));
+        assert!(!state_cookie.contains(&params["state"]));
+        let state_cookie = state_cookie.split(';').next().unwrap().to_string();
+
+        // A callback link opened in another browser does not sign it in
+        let callback = format!(
+            "/auth/oidc/callback?code=good-code&state={}",
+            params["state"]
+        );
+        let response = router
+            .clone()
+            .oneshot(get_request(&callback))
+            .await
+            .unwrap();
+        assert!(!response.headers()["set-cookie"]
+            .to_str()
+            .unwrap()
+            .starts_with("session_id="));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("started in another browser"));
+
+        let callback_request = || {
+            Request::builder()
+                .uri(&callback)
+                .header("cookie", &state_cookie)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = router.clone().oneshot(callback_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cleared = response.headers().get_all("set-cookie").iter().nth(1);
+        assert!(cleared
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("oidc_state=;")));
+        let cookie = response.headers()["set-cookie"]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+
+        let (status, page) = send_with(
+            router.clone(),
+            Method::GET,
+            "/sessions",
+            &[("cookie", &cookie)],
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("carol"));
+        assert!(page.contains("operator"));
+
+        // Each sign-in can only be completed once
+        let response = router.oneshot(callback_request()).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("already used"));
+    }
+
+    #[tokio::test]
+    async fn test_operators_must_set_up_two_factor_at_login() {
+        /// Post a form, returning the status, the session cookie if one was set, and the body
+        async fn post_form(
+            router: &Router,
+            uri: &str,
+            body: String,
+        ) -> (StatusCode, Option<String>, String) {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let cookie = response.headers().get("set-cookie").map(|value| {
+                value
+                    .to_str()
+                    .unwrap()
+                    .split(';')
+                    .next()
+                    .unwrap()
+                    .to_string()
+            });
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, cookie, String::from_utf8_lossy(&bytes).to_string())
+        }
+        fn between<'a>(page: &'a str, start: &str, end: &str) -> &'a str {
+            page.split(start).nth(1).unwrap().split(end).next().unwrap()
+        }
+
+        let users = format!(
+            "alice:viewer:{}\nbob:operator:{}\n",
+            Config::hash_password("alice-pw").unwrap(),
+            Config::hash_password("bob-pw").unwrap()
+        );
+        let config = Config {
+            users: crate::auth::parse_users(&users).unwrap(),
+            totp_required_role: Some(Role::Operator),
+            ..Config::default()
+        };
+        let router = app_router(
+            &config,
+            Arc::new(FakeBackend::new()),
+            Arc::new(StatsCollector::new(1)),
+            Arc::new(EventHub::new(16)),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        );
+
+        // Viewers are not required to use two-factor authentication
+        let (status, cookie, _) = post_form(
+            &router,
+            "/login",
+            "username=alice&password=alice-pw".to_string(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        assert!(cookie.is_some());
+
+        // The operator's password alone does not start a session; they set up an app first
+        let (status, cookie, page) = post_form(
+            &router,
+            "/login",
+            "username=bob&password=bob-pw".to_string(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(cookie.is_none());
+        assert!(page.contains(r#"class="two-factor-qr""#));
+        let challenge = between(&page, r#"name="challenge" value=""#, r#"""#).to_string();
+        let secret = between(&page, r#"<code class="two-factor-secret">"#, "<").to_string();
+
+        let (_, cookie, page) = post_form(
+            &router,
+            "/login/two-factor",
+            format!("challenge={}&code=abcdef", challenge),
+        )
+        .await;
+        assert!(cookie.is_none());
+        assert!(page.contains("Incorrect code"));
+
+        let (status, cookie, page) = post_form(
+            &router,
+            "/login/two-factor",
+            format!(
+                "challenge={}&code={}",
+                challenge,
+                crate::totp::current_code(&secret)
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(cookie.is_some());
+        let recovery_codes = between(&page, r#"<code class="token-secret recovery-codes">"#, "<")
+            .trim()
+            .to_string();
+
+        // From now on the password is followed by a code, or a recovery code
+        let (_, cookie, page) = post_form(
+            &router,
+            "/login",
+            "username=bob&password=bob-pw".to_string(),
+        )
+        .await;
+        assert!(cookie.is_none());
+        assert!(!page.contains(r#"class="two-factor-qr""#));
+        let challenge = between(&page, r#"name="challenge" value=""#, r#"""#).to_string();
+        let (status, cookie, _) = post_form(
+            &router,
+            "/login/two-factor",
+            format!("challenge={}&code={}", challenge, recovery_codes),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        let cookie = cookie.unwrap();
+
+        // A sign-in can only be completed once
+        let (_, cookie_again, page) = post_form(
+            &router,
+            "/login/two-factor",
+            format!("challenge={}&code={}", challenge, recovery_codes),
+        )
+        .await;
+        assert!(cookie_again.is_none());
+        assert!(page.contains("enter your password again"));
+
+        // Operators cannot turn it off
+        let (_, page) = send_with(
+            router.clone(),
+            Method::GET,
+            "/two-factor",
+            &[("cookie", &cookie)],
+            Body::empty(),
+        )
+        .await;
+        assert!(page.contains("9 recovery codes left"));
+        assert!(!page.contains("/two-factor/disable"));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_login_failures_lock_out_forwarded_client() {
+        use axum::extract::connect_info::MockConnectInfo;
+
+        let users = format!(
+            "alice:viewer:{}\nbob:operator:{}\n",
+            Config::hash_password("alice-pw").unwrap(),
+            Config::hash_password("bob-pw").unwrap()
+        );
+        let config = Config {
+            users: crate::auth::parse_users(&users).unwrap(),
+            login_max_failures: 2,
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Config::default()
+        };
+        let router = app_router(
+            &config,
+            Arc::new(FakeBackend::new()),
+            Arc::new(StatsCollector::new(1)),
+            Arc::new(EventHub::new(16)),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        )
+        .layer(MockConnectInfo(SocketAddr::from(([10, 0, 0, 1], 40000))));
+
+        let attempt = |username: &str, password: &str, client: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/login")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .header("x-forwarded-for", client)
+                .body(Body::from(format!(
+                    "username={}&password={}",
+                    username, password
+                )))
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(attempt("alice", "wrong", "203.0.113.7"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = router
+            .clone()
+            .oneshot(attempt("alice", "wrong", "203.0.113.7"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("Too many failed login attempts"));
+
+        // The right password does not get through while locked out
+        let response = router
+            .clone()
+            .oneshot(attempt("alice", "alice-pw", "198.51.100.2"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = router
+            .clone()
+            .oneshot(attempt("bob", "bob-pw", "203.0.113.7"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Other users behind the same proxy are unaffected
+        let response = router
+            .oneshot(attempt("bob", "bob-pw", "198.51.100.2"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_proxy_headers_sign_users_in() {
+        use axum::extract::connect_info::MockConnectInfo;
+
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+        let config = Config {
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+            proxy_auth_user_header: Some("X-Forwarded-User".to_string()),
+            proxy_auth_groups_header: Some("X-Forwarded-Groups".to_string()),
+            proxy_auth_operator_groups: vec!["ops".to_string()],
+            proxy_auth_logout_url: Some("/oauth2/sign_out".to_string()),
+            ..Config::default()
+        };
+        let router_from = |peer: [u8; 4]| {
+            app_router(
+                &config,
+                backend.clone(),
+                Arc::new(StatsCollector::new(1)),
+                Arc::new(EventHub::new(16)),
+                Arc::new(Timeline::in_memory(16)),
+                Arc::new(TokenStore::in_memory()),
+                Arc::new(SessionStore::new(Arc::new(config.clone()))),
+                Arc::new(TotpStore::in_memory()),
+            )
+            .layer(MockConnectInfo(SocketAddr::from((peer, 40000))))
+        };
+        let proxied =
+            |method: Method, uri: &str, user: &str, groups: &str, cookie: Option<&str>| {
+                let mut request = Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("x-forwarded-user", user)
+                    .header("x-forwarded-groups", groups);
+                if let Some(cookie) = cookie {
+                    request = request.header("cookie", cookie);
+                }
+                request.body(Body::empty()).unwrap()
+            };
+
+        let router = router_from([10, 0, 0, 1]);
+        let response = router
+            .clone()
+            .oneshot(proxied(
+                Method::GET,
+                "/api/containers",
+                "carol",
+                "dev, ops",
+                None,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response
+            .headers()
+            .get("set-cookie")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .expect("a session cookie should be set")
+            .to_string();
+
+        // The browser keeps its session while the proxy vouches for the same user
+        let response = router
+            .clone()
+            .oneshot(proxied(
+                Method::GET,
+                "/sessions",
+                "carol",
+                "ops",
+                Some(&cookie),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("set-cookie"));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let page = String::from_utf8_lossy(&bytes);
+        assert!(page.contains("carol"));
+        let csrf_token = page
+            .split(r#"<meta name="csrf-token" content=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .expect("pages should carry the CSRF token")
+            .to_string();
+
+        // Users in no mapped group are refused
+        let response = router
+            .clone()
+            .oneshot(proxied(
+                Method::GET,
+                "/api/containers",
+                "mallory",
+                "dev",
+                None,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut logout = proxied(Method::POST, "/logout", "carol", "ops", Some(&cookie));
+        logout
+            .headers_mut()
+            .insert(crate::auth::CSRF_HEADER, csrf_token.parse().unwrap());
+        let response = router.oneshot(logout).await.unwrap();
+        assert_eq!(response.headers()["location"], "/oauth2/sign_out");
+
+        // Anyone not connecting through the proxy could set the header themselves
+        let response = router_from([203, 0, 113, 5])
+            .oneshot(proxied(
+                Method::GET,
+                "/api/containers",
+                "carol",
+                "ops",
+                None,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_state_changing_requests_require_csrf_token() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+        let router = multi_user_router(backend.clone());
+        let operator = login(router.clone(), "bob", "bob-pw").await;
+        let cookie = [("cookie", operator.cookie.as_str())];
+
+        // A cross-site form post carries the cookie but not the token
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/stop/web",
+            &cookie,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let forged = [
+            ("cookie", operator.cookie.as_str()),
+            (crate::auth::CSRF_HEADER, "forged"),
+        ];
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/stop/web",
+            &forged,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(backend.container_state("web").as_deref(), Some("running"));
+
+        let (_, page) = send_with(router.clone(), Method::GET, "/", &cookie, Body::empty()).await;
+        assert!(page.contains(&format!(
+            r#"<input type="hidden" name="csrf_token" value="{}">"#,
+            operator.csrf_token
+        )));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/stop/web")
+            .header("cookie", &operator.cookie)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!("csrf_token={}", operator.csrf_token)))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(backend.container_state("web").as_deref(), Some("exited"));
+
+        let status = send_as(router.clone(), Method::POST, "/start/web", &operator).await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        assert_eq!(backend.container_state("web").as_deref(), Some("running"));
+
+        // Bearer tokens are not sent by browsers, so they need no CSRF token
+        let (_, body) = send_with(
+            router.clone(),
+            Method::POST,
+            "/api/tokens",
+            &operator.headers(),
+            Body::from(r#"{"name": "ci"}"#),
+        )
+        .await;
+        let issued: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let bearer = format!("Bearer {}", issued["secret"].as_str().unwrap());
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/stop/web",
+            &[("authorization", bearer.as_str())],
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+
+        // Logging out skips the auth middleware, but another site cannot sign users out
+        let (status, _) = send_with(
+            router.clone(),
+            Method::POST,
+            "/logout",
+            &cookie,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send_with(router.clone(), Method::GET, "/", &cookie, Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/logout")
+            .header("cookie", &operator.cookie)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!("csrf_token={}", operator.csrf_token)))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let (status, _) = send_with(
+            router,
+            Method::GET,
+            "/api/containers",
+            &cookie,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_user_actions_are_recorded_in_timeline() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+        let router = test_router(backend);
+
+        send(router.clone(), Method::POST, "/stop/web", Body::empty()).await;
+        send(router.clone(), Method::POST, "/stop/missing", Body::empty()).await;
+
+        let (status, body) = send(
+            router.clone(),
+            Method::GET,
+            "/api/events?source=user&action=stop",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let entries: Vec<TimelineEntry> = serde_json::from_str(&body).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].target, "missing");
+        assert!(entries[0].error.is_some());
+        assert_eq!(entries[1].target, "web");
+        assert!(entries[1].error.is_none());
+
+        let (status, body) = send(
+            router.clone(),
+            Method::GET,
+            "/events?target=web&from=",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("source-user"));
+        assert!(!body.contains("missing"));
+
+        let (status, _) = send(
+            router,
+            Method::GET,
+            "/api/events?from=yesterday",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_secret_environment_values_are_masked_until_revealed() {
+        let backend = Arc::new(
+            FakeBackend::new()
+                .with_container("db", "postgres", "running")
+                .with_environment("db", &["POSTGRES_PASSWORD=hunter2", "PGDATA=/data"])
+                .with_image("postgres:16")
+                .with_image_environment(
+                    "postgres:16",
+                    &["POSTGRES_PASSWORD=swordfish", "PGDATA=/pg"],
+                ),
+        );
+        let users = format!(
+            "alice:viewer:{}\ncarol:admin:{}\n",
+            Config::hash_password("alice-pw").unwrap(),
+            Config::hash_password("carol-pw").unwrap()
+        );
+        let config = Config {
+            users: crate::auth::parse_users(&users).unwrap(),
+            ..Config::default()
+        };
+        let router = app_router(
+            &config,
+            backend,
+            Arc::new(StatsCollector::new(1)),
+            Arc::new(EventHub::new(16)),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        );
+        let viewer = login(router.clone(), "alice", "alice-pw").await;
+        let admin = login(router.clone(), "carol", "carol-pw").await;
+
+        let (status, page) = send_with(
+            router.clone(),
+            Method::GET,
+            "/",
+            &viewer.headers(),
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("/data"));
+        assert!(!page.contains("hunter2"));
+        assert!(!page.contains("revealEnvironmentVariable(this)"));
+        let (_, page) = send_with(
+            router.clone(),
+            Method::GET,
+            "/",
+            &admin.headers(),
+            Body::empty(),
+        )
+        .await;
+        assert!(!page.contains("hunter2"));
+        assert!(page.contains(r#"data-key="POSTGRES_PASSWORD""#));
+
+        let (status, body) = send_with(
+            router.clone(),
+            Method::GET,
+            "/api/containers/db/environment",
+            &viewer.headers(),
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let environment: Vec<ContainerEnvironmentVariable> = serde_json::from_str(&body).unwrap();
+        assert!(environment[0].masked);
+        assert_eq!(environment[0].value, None);
+        assert_eq!(environment[1].value.as_deref(), Some("/data"));
+
+        // Defaults baked into an image are masked the same way
+        let (status, body) = send_with(
+            router.clone(),
+            Method::GET,
+            "/api/image/postgres%3A16",
+            &admin.headers(),
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.contains("swordfish"));
+        let image: ImageInfo = serde_json::from_str(&body).unwrap();
+        assert!(image.environment_variables[0].masked);
+        assert_eq!(image.environment_variables[1].value.as_deref(), Some("/pg"));
+
+        async fn reveal(router: Router, user: &SignedIn, key: &str) -> (StatusCode, String) {
+            send_with(
+                router,
+                Method::POST,
+                "/api/containers/db/environment/reveal",
+                &user.headers(),
+                Body::from(format!(r#"{{"key":"{}"}}"#, key)),
+            )
+            .await
+        }
+        let (status, _) = reveal(router.clone(), &viewer, "POSTGRES_PASSWORD").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = reveal(router.clone(), &admin, "POSTGRES_PASSWORD").await;
+        assert_eq!(status, StatusCode::OK);
+        let variable: EnvironmentVariable = serde_json::from_str(&body).unwrap();
+        assert_eq!(variable.value, "hunter2");
+        let (status, _) = reveal(router.clone(), &admin, "MISSING").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Every reveal by an admin is on the timeline
+        let (_, body) = send_with(
+            router,
+            Method::GET,
+            "/api/events?source=user&action=reveal-env",
+            &admin.headers(),
+            Body::empty(),
+        )
+        .await;
+        let entries: Vec<TimelineEntry> = serde_json::from_str(&body).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].details.as_deref(), Some("MISSING"));
+        assert!(entries[0].error.is_some());
+        assert_eq!(entries[1].username.as_deref(), Some("carol"));
+        assert_eq!(entries[1].target, "db");
+        assert_eq!(entries[1].details.as_deref(), Some("POSTGRES_PASSWORD"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_running_container_requires_force() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+
+        let (status, body) = send(
+            test_router(backend.clone()),
+            Method::DELETE,
+            "/api/containers/web",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(body.contains("\"success\":false"));
+
+        let (status, _) = send(
+            test_router(backend.clone()),
+            Method::DELETE,
+            "/api/containers/web?force=true",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(backend.container_state("web").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_volume_api_create_and_remove() {
+        let backend = Arc::new(FakeBackend::new().with_volume("existing"));
+
+        let (status, body) = send(
+            test_router(backend.clone()),
+            Method::POST,
+            "/api/volumes",
+            Body::from(r#"{"name":"data","driver":null}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(body.contains("\"name\":\"data\""));
+        assert_eq!(backend.volume_names(), vec!["existing", "data"]);
+
+        let (status, _) = send(
+            test_router(backend.clone()),
+            Method::DELETE,
+            "/api/volumes/existing",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(backend.volume_names(), vec!["data"]);
+
+        let (status, _) = send(
+            test_router(backend),
+            Method::GET,
+            "/api/volumes/missing",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    /// Send a WebSocket handshake to the router served on a local port, returning the
+    /// response status. `oneshot` requests cannot be upgraded.
+    async fn websocket_handshake(router: Router, uri: &str, origin: Option<&str>) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let origin = origin
+            .map(|origin| {
+                format!(
+                    "Origin: {}\r\n",
+                    origin.replace("{host}", &address.to_string())
+                )
+            })
+            .unwrap_or_default();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\n{}Connection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            uri, address, origin
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = [0; 1024];
+        let read = stream.read(&mut response).await.unwrap();
+        String::from_utf8_lossy(&response[..read])
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exec_websocket_checks_origin_and_shell() {
+        let backend = Arc::new(FakeBackend::new().with_container("web", "nginx", "running"));
+        let router = || test_router(backend.clone());
+
+        let status = websocket_handshake(router(), "/exec/web/ws", Some("http://{host}")).await;
+        assert_eq!(status, 101);
+        let status = websocket_handshake(router(), "/exec/web/ws?shell=/bin/ash", None).await;
+        assert_eq!(status, 101);
+
+        let status =
+            websocket_handshake(router(), "/exec/web/ws", Some("https://evil.example")).await;
+        assert_eq!(status, 403);
+        let status = websocket_handshake(router(), "/images/pull/ws", Some("null")).await;
+        assert_eq!(status, 403);
+
+        let status = websocket_handshake(router(), "/exec/web/ws?shell=sh%20-l", None).await;
+        assert_eq!(status, 400);
+        let status = websocket_handshake(router(), "/exec/web/ws?shell=/tmp/x", None).await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_logs_page_escapes_backend_output() {
+        let backend = Arc::new(
+            FakeBackend::new()
+                .with_container("web", "nginx", "running")
+                .with_logs("web", &["GET /index.html <200>"]),
+        );
+
+        let (status, body) = send(
+            test_router(backend),
+            Method::GET,
+            "/logs/web",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("GET /index.html &#60;200&#62;"));
+        assert!(!body.contains("<200>"));
+    }
+
+    #[tokio::test]
+    async fn test_pages_escape_container_names() {
+        let backend = Arc::new(FakeBackend::new().with_container(
+            "<script>alert(1)</script>",
+            "evil\"><img src=x>",
+            "running",
+        ));
+
+        let (status, body) = send(test_router(backend), Method::GET, "/", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("&#60;script&#62;alert(1)&#60;/script&#62;"));
+        assert!(!body.contains("<script>alert(1)"));
+        assert!(!body.contains("evil\"><img"));
+    }
+
+    #[tokio::test]
+    async fn test_health_and_metrics_readiness() {
+        let backend = Arc::new(FakeBackend::new());
+
+        let (status, body) = send(
+            test_router(backend.clone()),
+            Method::GET,
+            "/health",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"docker_available\":true"));
+
+        // Nothing has been collected yet, since the collector is not running in tests
+        let (status, _) = send(
+            test_router(backend),
+            Method::GET,
+            "/api/metrics",
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_events_stream_relays_published_events() {
+        let config = Config {
+            auth_enabled: false,
+            ..Config::default()
+        };
+        let events = Arc::new(EventHub::new(16));
+        let router = app_router(
+            &config,
+            Arc::new(FakeBackend::new()),
+            Arc::new(StatsCollector::new(1)),
+            events.clone(),
+            Arc::new(Timeline::in_memory(16)),
+            Arc::new(TokenStore::in_memory()),
+            Arc::new(SessionStore::new(Arc::new(config.clone()))),
+            Arc::new(TotpStore::in_memory()),
+        );
+
+        let request = Request::builder()
+            .uri("/api/events/stream")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+
+        events.publish(crate::models::DockerEvent {
+            timestamp: Utc::now(),
+            kind: "container".to_string(),
+            action: "start".to_string(),
+            id: "abc123".to_string(),
+            name: Some("web".to_string()),
+            image: None,
+            exit_code: None,
+        });
+
+        let mut body = response.into_body().into_data_stream();
+        let chunk = body.next().await.unwrap().unwrap();
+        let text = String::from_utf8_lossy(&chunk);
+        assert!(text.contains("event: docker"));
+        assert!(text.contains("\"action\":\"start\""));
+    }
 }
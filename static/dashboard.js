@@ -24,6 +24,25 @@ async function fetchConfig() {
   }
 }
 
+// Seed the charts with the server-side history so a reload doesn't start from scratch
+async function fetchHistory() {
+  try {
+    const response = await fetch("/api/metrics/history", {
+      credentials: "same-origin",
+    });
+    if (!response.ok) throw new Error("Failed to fetch metrics history");
+    const history = await response.json();
+
+    const historyLimit = window.dashboardConfig?.metrics_history_limit || 20;
+    metricsHistory = history.samples.slice(-historyLimit).map((sample) => ({
+      time: new Date(sample.system.timestamp).toLocaleTimeString(),
+      containers: sample.containers,
+    }));
+  } catch (error) {
+    console.error("Error fetching metrics history:", error);
+  }
+}
+
 async function fetchMetrics() {
   try {
     const response = await fetch("/api/metrics", {
@@ -417,6 +436,7 @@ document.addEventListener("DOMContentLoaded", async () => {
   console.log("Dashboard initialized with config:", window.dashboardConfig);
 
   initializeCharts();
+  await fetchHistory();
   await updateDashboard();
 
   // Use configurable update interval (convert seconds to milliseconds)
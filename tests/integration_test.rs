@@ -53,7 +53,7 @@ mod integration_tests {
         let store = SessionStore::new(config);
 
         // Create session
-        let session_id = store.create_session("admin").await;
+        let session_id = store.create_session("admin", auth::Role::Admin).await;
         assert!(!session_id.is_empty());
 
         // Retrieve session
@@ -69,4 +69,26 @@ mod integration_tests {
         let session = store.get_session(&session_id).await;
         assert!(session.is_none());
     }
+
+    #[test]
+    fn test_app_router_builds() {
+        // Route registration panics on conflicting paths, so building the router
+        // catches overlapping routes without needing a Docker daemon
+        let config = config::Config::default();
+        let stats = std::sync::Arc::new(stats_collector::StatsCollector::new(
+            config.metrics_history_limit,
+        ));
+        let backend = std::sync::Arc::new(
+            backend::BollardBackend::connect(None).expect("Client creation should not connect"),
+        );
+        let events = std::sync::Arc::new(events::EventHub::new(16));
+        let timeline = std::sync::Arc::new(timeline::Timeline::in_memory(16));
+        let tokens = std::sync::Arc::new(tokens::TokenStore::in_memory());
+        let totp = std::sync::Arc::new(totp::TotpStore::in_memory());
+        let sessions =
+            std::sync::Arc::new(auth::SessionStore::new(std::sync::Arc::new(config.clone())));
+        let _router = web::app_router(
+            &config, backend, stats, events, timeline, tokens, sessions, totp,
+        );
+    }
 }